@@ -0,0 +1,138 @@
+//! Derive macros for the mlld Rust SDK.
+//!
+//! `#[derive(MlldPayload)]` and `#[derive(MlldExports)]` implement the
+//! `mlld::MlldPayload` / `mlld::MlldExports` traits for named-field
+//! structs, generating state-path accessors and JSON Schema metadata
+//! from the struct definition so payload/export contracts live in one
+//! place.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, GenericArgument, PathArguments, Type};
+
+/// Derive `mlld::MlldPayload` for a named-field struct.
+#[proc_macro_derive(MlldPayload)]
+pub fn derive_mlld_payload(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input, quote!(MlldPayload))
+}
+
+/// Derive `mlld::MlldExports` for a named-field struct.
+#[proc_macro_derive(MlldExports)]
+pub fn derive_mlld_exports(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input, quote!(MlldExports))
+}
+
+fn expand(input: DeriveInput, trait_name: proc_macro2::TokenStream) -> TokenStream {
+    let name = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => {
+                return syn::Error::new_spanned(
+                    name,
+                    "mlld derives require a struct with named fields",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "mlld derives require a struct")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let mut field_paths = Vec::new();
+    let mut property_entries = Vec::new();
+    let mut required = Vec::new();
+
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let path = ident.to_string();
+        let (schema_type, optional) = schema_type_for(&field.ty);
+
+        field_paths.push(path.clone());
+        property_entries.push(quote! {
+            properties.insert(
+                #path.to_string(),
+                ::mlld::serde_json::json!({ "type": #schema_type }),
+            );
+        });
+        if !optional {
+            required.push(path);
+        }
+    }
+
+    let expanded = quote! {
+        impl ::mlld::#trait_name for #name {
+            fn field_paths() -> &'static [&'static str] {
+                &[#(#field_paths),*]
+            }
+
+            fn schema() -> ::mlld::serde_json::Value {
+                let mut properties = ::mlld::serde_json::Map::new();
+                #(#property_entries)*
+                ::mlld::serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": [#(#required),*]
+                })
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+/// Map a Rust field type onto a JSON Schema type name, unwrapping
+/// `Option<T>` to mark the field non-required.
+fn schema_type_for(ty: &Type) -> (&'static str, bool) {
+    if let Some(inner) = option_inner(ty) {
+        let (schema_type, _) = schema_type_for(inner);
+        return (schema_type, true);
+    }
+
+    let name = match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    };
+
+    let schema_type = match name.as_str() {
+        "String" | "str" => "string",
+        "bool" => "boolean",
+        "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize" => {
+            "integer"
+        }
+        "f32" | "f64" => "number",
+        "Vec" => "array",
+        _ => "object",
+    };
+
+    (schema_type, false)
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
@@ -0,0 +1,527 @@
+//! Native async client built on tokio.
+//!
+//! [`AsyncClient`] mirrors `Client::process/execute/analyze` as async
+//! fns over the same live protocol, using `tokio::process::Command` and
+//! async channels instead of blocking threads, so axum-style services
+//! can call mlld without wrapping every request in `spawn_blocking`.
+//!
+//! In-flight requests are plain futures: `process_async`/`execute_async`
+//! return handles implementing [`IntoFuture`], so several can run
+//! concurrently with `tokio::join!` instead of blocking a thread per
+//! `wait()`.
+//!
+//! Configuration reuses the blocking [`Client`] builders:
+//!
+//! ```no_run
+//! # async fn demo() -> mlld::Result<()> {
+//! let client = mlld::AsyncClient::from_client(
+//!     mlld::Client::new().with_working_dir("/srv/scripts"),
+//! );
+//! let output = client.process("/show `hi`", None).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    build_execute_params, build_process_params, error_from_payload, parse_analyze_result,
+    parse_envelope, parse_execute_result, parse_state_write_event, spawn_error,
+    value_to_request_id, AnalyzeResult, Client, Envelope, Error, ExecuteOptions, ExecuteResult,
+    ProcessOptions, Result, StateWrite, TransportMessage,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Async client for the mlld live protocol.
+///
+/// Spawns `mlld live --stdio` lazily on first use, exactly like the
+/// blocking client, and multiplexes concurrent requests over the one
+/// child process. Cloning is cheap and shares the transport. Stderr is
+/// discarded; use the blocking client's stderr modes when stderr capture
+/// matters.
+#[derive(Clone)]
+pub struct AsyncClient {
+    inner: Arc<AsyncClientInner>,
+}
+
+struct AsyncClientInner {
+    config: Client,
+    transport: tokio::sync::Mutex<Option<AsyncTransport>>,
+    next_request_id: AtomicU64,
+}
+
+impl AsyncClient {
+    /// Create an async client with default settings.
+    pub fn new() -> Self {
+        Self::from_client(Client::new())
+    }
+
+    /// Create an async client from a configured blocking [`Client`],
+    /// reusing its command, working directory, timeout, and parsing
+    /// settings.
+    pub fn from_client(config: Client) -> Self {
+        Self {
+            inner: Arc::new(AsyncClientInner {
+                config,
+                transport: tokio::sync::Mutex::new(None),
+                next_request_id: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    /// Execute an mlld script string and return the output.
+    pub async fn process(&self, script: &str, opts: Option<ProcessOptions>) -> Result<String> {
+        self.process_async(script, opts).await?.await
+    }
+
+    /// Start an mlld script execution and return an awaitable handle.
+    pub async fn process_async(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<AsyncProcessHandle> {
+        let opts = opts.unwrap_or_default();
+        let timeout = opts.timeout.or(self.inner.config.timeout);
+        let params = build_process_params(script, opts)?;
+
+        let (request_id, receiver) = self.start_request("process", Value::Object(params)).await?;
+
+        Ok(AsyncProcessHandle {
+            client: self.clone(),
+            request_id,
+            receiver,
+            timeout,
+        })
+    }
+
+    /// Run an mlld file with a payload and return structured output.
+    pub async fn execute<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<ExecuteResult> {
+        self.execute_async(filepath, payload, opts).await?.await
+    }
+
+    /// Start an mlld file execution and return an awaitable handle.
+    pub async fn execute_async<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<AsyncExecuteHandle> {
+        let opts = opts.unwrap_or_default();
+        let timeout = opts.timeout.or(self.inner.config.timeout);
+        let exports_schema = opts.exports_schema.clone();
+        let params = build_execute_params(filepath, payload, opts)?;
+
+        let (request_id, receiver) = self.start_request("execute", Value::Object(params)).await?;
+
+        Ok(AsyncExecuteHandle {
+            client: self.clone(),
+            request_id,
+            receiver,
+            timeout,
+            exports_schema,
+        })
+    }
+
+    /// Analyze an mlld file without executing it.
+    pub async fn analyze(&self, filepath: &str) -> Result<AnalyzeResult> {
+        let (request_id, receiver) = self
+            .start_request("analyze", json!({ "filepath": filepath }))
+            .await?;
+        let (result, _) = self
+            .await_request(request_id, receiver, self.inner.config.timeout)
+            .await?;
+        parse_analyze_result(result)
+    }
+
+    /// Shut down the live child process, if one is running.
+    pub async fn close(&self) {
+        let mut guard = self.inner.transport.lock().await;
+        if let Some(mut transport) = guard.take() {
+            transport.shutdown().await;
+        }
+    }
+
+    async fn start_request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<(u64, UnboundedReceiver<TransportMessage>)> {
+        let request_id = self.inner.next_request_id.fetch_add(1, Ordering::Relaxed);
+
+        let mut guard = self.inner.transport.lock().await;
+        if guard.is_none() {
+            *guard = Some(AsyncTransport::spawn(&self.inner.config).await?);
+        }
+        let transport = guard.as_mut().expect("transport just ensured");
+
+        let receiver = transport.register_request(request_id);
+        let envelope = json!({
+            "method": method,
+            "id": request_id,
+            "params": params
+        });
+        transport.send_json(&envelope).await?;
+
+        Ok((request_id, receiver))
+    }
+
+    async fn await_request(
+        &self,
+        request_id: u64,
+        mut receiver: UnboundedReceiver<TransportMessage>,
+        timeout: Option<Duration>,
+    ) -> Result<(Value, Vec<StateWrite>)> {
+        let deadline = timeout.map(|limit| tokio::time::Instant::now() + limit);
+        let mut state_write_events = Vec::new();
+
+        loop {
+            let message = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(message) => message,
+                    Err(_) => {
+                        self.cancel_request(request_id).await;
+                        return Err(Error::Timeout(Box::new(crate::TimeoutInfo::bare(
+                            timeout.expect("deadline implies timeout"),
+                        ))));
+                    }
+                },
+                None => receiver.recv().await,
+            };
+
+            match message {
+                Some(TransportMessage::Event(event)) => {
+                    if let Some(write) = parse_state_write_event(&event) {
+                        state_write_events.push(write);
+                    }
+                }
+                Some(TransportMessage::Result(result)) => {
+                    if let Some(error_payload) = result.get("error") {
+                        return Err(error_from_payload(error_payload));
+                    }
+                    return Ok((result, state_write_events));
+                }
+                Some(TransportMessage::Closed(message)) => {
+                    self.invalidate_transport().await;
+                    return Err(Error::Transport(message));
+                }
+                None => {
+                    self.invalidate_transport().await;
+                    return Err(Error::Transport("live transport disconnected".to_string()));
+                }
+            }
+        }
+    }
+
+    async fn cancel_request(&self, request_id: u64) {
+        let mut guard = self.inner.transport.lock().await;
+        if let Some(transport) = guard.as_mut() {
+            let _ = transport
+                .send_json(&json!({
+                    "method": "cancel",
+                    "id": request_id
+                }))
+                .await;
+            transport.remove_request(request_id);
+        }
+    }
+
+    async fn invalidate_transport(&self) {
+        let mut guard = self.inner.transport.lock().await;
+        if let Some(mut transport) = guard.take() {
+            transport.shutdown().await;
+        }
+    }
+}
+
+impl Default for AsyncClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-flight async process request. Await it (directly or via `join!`)
+/// to get the rendered output.
+pub struct AsyncProcessHandle {
+    client: AsyncClient,
+    request_id: u64,
+    receiver: UnboundedReceiver<TransportMessage>,
+    timeout: Option<Duration>,
+}
+
+impl AsyncProcessHandle {
+    /// Live request identifier.
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// Request graceful cancellation for this in-flight execution.
+    pub async fn cancel(&self) {
+        self.client.cancel_request(self.request_id).await;
+    }
+
+    /// Wait for completion and return output.
+    pub async fn result(self) -> Result<String> {
+        let (result, _) = self
+            .client
+            .await_request(self.request_id, self.receiver, self.timeout)
+            .await?;
+
+        if let Some(output) = result.get("output").or_else(|| result.get("value")) {
+            return Ok(match output {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            });
+        }
+
+        Ok(String::new())
+    }
+}
+
+impl IntoFuture for AsyncProcessHandle {
+    type Output = Result<String>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.result())
+    }
+}
+
+/// In-flight async execute request. Await it (directly or via `join!`)
+/// to get the structured result.
+pub struct AsyncExecuteHandle {
+    client: AsyncClient,
+    request_id: u64,
+    receiver: UnboundedReceiver<TransportMessage>,
+    timeout: Option<Duration>,
+    exports_schema: Option<Value>,
+}
+
+impl AsyncExecuteHandle {
+    /// Live request identifier.
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// Request graceful cancellation for this in-flight execution.
+    pub async fn cancel(&self) {
+        self.client.cancel_request(self.request_id).await;
+    }
+
+    /// Wait for completion and return structured output.
+    pub async fn result(self) -> Result<ExecuteResult> {
+        let (result, state_write_events) = self
+            .client
+            .await_request(self.request_id, self.receiver, self.timeout)
+            .await?;
+
+        parse_execute_result(
+            result,
+            state_write_events,
+            self.client.inner.config.result_parsing,
+            self.exports_schema.as_ref(),
+        )
+    }
+}
+
+impl IntoFuture for AsyncExecuteHandle {
+    type Output = Result<ExecuteResult>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Result<ExecuteResult>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.result())
+    }
+}
+
+type AsyncPending = Arc<Mutex<HashMap<u64, UnboundedSender<TransportMessage>>>>;
+
+struct AsyncTransport {
+    child: Child,
+    stdin: ChildStdin,
+    pending: AsyncPending,
+}
+
+impl AsyncTransport {
+    async fn spawn(client: &Client) -> Result<Self> {
+        let mut args = client.command_args.to_vec();
+        args.push("live".to_string());
+        args.push("--stdio".to_string());
+        let args_for_error = args.clone();
+
+        let mut cmd = Command::new(&client.command);
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+
+        if let Some(dir) = &client.working_dir {
+            cmd.current_dir(dir);
+        }
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|error| spawn_error(&client.command, &args_for_error, error))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::Transport("live process stdin is unavailable".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Transport("live process stdout is unavailable".to_string()))?;
+
+        let pending: AsyncPending = Arc::new(Mutex::new(HashMap::new()));
+        let max_frame_bytes = client.max_frame_bytes;
+
+        tokio::spawn(read_stdout(stdout, Arc::clone(&pending), max_frame_bytes));
+
+        Ok(Self {
+            child,
+            stdin,
+            pending,
+        })
+    }
+
+    fn register_request(&mut self, request_id: u64) -> UnboundedReceiver<TransportMessage> {
+        let (sender, receiver) = unbounded_channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(request_id, sender);
+        }
+        receiver
+    }
+
+    fn remove_request(&mut self, request_id: u64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&request_id);
+        }
+    }
+
+    async fn send_json(&mut self, payload: &Value) -> Result<()> {
+        let mut line = serde_json::to_string(payload)?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) {
+        let _ = self.child.kill().await;
+    }
+}
+
+async fn read_stdout(
+    stdout: tokio::process::ChildStdout,
+    pending: AsyncPending,
+    max_frame_bytes: usize,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(error) => {
+                notify_all_pending(&pending, format!("live transport read error: {error}"));
+                return;
+            }
+        };
+
+        if line.len() > max_frame_bytes {
+            notify_all_pending(
+                &pending,
+                format!(
+                    "live frame exceeded {max_frame_bytes} byte limit ({} bytes read); frame dropped",
+                    line.len()
+                ),
+            );
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        match parse_envelope(trimmed) {
+            Ok(Envelope::Event(event)) => dispatch_event(&pending, event),
+            Ok(Envelope::Result(result)) => dispatch_result(&pending, result),
+            Err(error) => {
+                notify_all_pending(&pending, error);
+                continue;
+            }
+        }
+    }
+
+    notify_all_pending(&pending, "live transport closed".to_string());
+}
+
+fn dispatch_event(pending: &AsyncPending, event: Value) {
+    let Some(request_id) = event.get("id").and_then(value_to_request_id) else {
+        return;
+    };
+
+    let sender = pending
+        .lock()
+        .ok()
+        .and_then(|map| map.get(&request_id).cloned());
+
+    if let Some(sender) = sender {
+        let _ = sender.send(TransportMessage::Event(event));
+    }
+}
+
+fn dispatch_result(pending: &AsyncPending, result: Value) {
+    let Some(request_id) = result.get("id").and_then(value_to_request_id) else {
+        return;
+    };
+
+    let sender = pending
+        .lock()
+        .ok()
+        .and_then(|mut map| map.remove(&request_id));
+
+    if let Some(sender) = sender {
+        let _ = sender.send(TransportMessage::Result(result));
+    }
+}
+
+fn notify_all_pending(pending: &AsyncPending, message: String) {
+    let senders = pending
+        .lock()
+        .map(|mut map| map.drain().map(|(_, sender)| sender).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    for sender in senders {
+        let _ = sender.send(TransportMessage::Closed(message.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_from_client_reuses_configuration() {
+        let client = AsyncClient::from_client(
+            Client::new()
+                .with_command("node")
+                .with_timeout(Duration::from_secs(5)),
+        );
+        assert_eq!(client.inner.config.command, "node");
+        assert_eq!(client.inner.config.timeout, Some(Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,109 @@
+//! Generate Rust source from [`AnalyzeResult`] data, intended for
+//! `build.rs` usage so module/host contracts stay in sync at compile
+//! time:
+//!
+//! ```no_run
+//! let client = mlld::Client::new();
+//! let analysis = client.analyze("module.mld.md")?;
+//! let source = mlld::codegen::generate(&analysis);
+//! std::fs::write("src/generated.rs", source)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::AnalyzeResult;
+
+/// Emit Rust structs and constants describing a module's exports and
+/// executables. Exports are typed as `serde_json::Value` since analyze
+/// output carries names, not value shapes.
+pub fn generate(analysis: &AnalyzeResult) -> String {
+    let mut out = String::new();
+
+    out.push_str(&format!(
+        "// Generated from mlld analyze output for {}. Do not edit.\n\n",
+        analysis.filepath
+    ));
+
+    out.push_str("/// Exports of the analyzed mlld module.\n");
+    out.push_str("#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]\n");
+    out.push_str("pub struct ModuleExports {\n");
+    for export in &analysis.exports {
+        let field = rust_identifier(export);
+        if field != *export {
+            out.push_str(&format!("    #[serde(rename = \"{export}\")]\n"));
+        }
+        out.push_str(&format!("    pub {field}: serde_json::Value,\n"));
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("/// Executables defined by the analyzed mlld module.\n");
+    out.push_str("pub mod executables {\n");
+    for executable in &analysis.executables {
+        let constant = rust_identifier(&executable.name).to_uppercase();
+        out.push_str(&format!(
+            "    /// `{}({})`\n",
+            executable.name,
+            executable.params.join(", ")
+        ));
+        out.push_str(&format!(
+            "    pub const {constant}: &str = \"{}\";\n",
+            executable.name
+        ));
+    }
+    out.push_str("}\n");
+
+    out
+}
+
+/// Convert an mlld export/executable name into a valid Rust identifier.
+fn rust_identifier(name: &str) -> String {
+    let mut identifier = String::with_capacity(name.len());
+
+    for (index, character) in name.chars().enumerate() {
+        if character.is_ascii_alphanumeric() {
+            if index == 0 && character.is_ascii_digit() {
+                identifier.push('_');
+            }
+            identifier.push(character.to_ascii_lowercase());
+        } else {
+            identifier.push('_');
+        }
+    }
+
+    if identifier.is_empty() {
+        identifier.push('_');
+    }
+
+    identifier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Executable;
+
+    #[test]
+    fn test_generate_emits_exports_struct_and_executable_consts() {
+        let analysis = AnalyzeResult {
+            filepath: "module.mld.md".to_string(),
+            valid: true,
+            errors: Vec::new(),
+            executables: vec![Executable {
+                name: "format-name".to_string(),
+                params: vec!["first".to_string(), "last".to_string()],
+                labels: Vec::new(),
+            }],
+            exports: vec!["config".to_string(), "api-key".to_string()],
+            imports: Vec::new(),
+            guards: Vec::new(),
+            needs: None,
+        };
+
+        let source = generate(&analysis);
+        assert!(source.contains("pub struct ModuleExports"));
+        assert!(source.contains("pub config: serde_json::Value,"));
+        assert!(source.contains("#[serde(rename = \"api-key\")]"));
+        assert!(source.contains("pub api_key: serde_json::Value,"));
+        assert!(source.contains("pub const FORMAT_NAME: &str = \"format-name\";"));
+        assert!(source.contains("`format-name(first, last)`"));
+    }
+}
@@ -0,0 +1,292 @@
+//! Line-based diff of rendered mlld output between runs.
+//!
+//! Backs "what changed since the last run" review surfaces without
+//! pulling in a general diff crate: computes a longest-common-subsequence
+//! diff over lines and groups the changes into unified-style hunks.
+//! Optional markdown-aware normalization ignores cosmetic differences
+//! (trailing whitespace, repeated blank lines, bullet marker style).
+
+/// Options controlling how two outputs are compared.
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Unchanged lines to include around each change, unified-diff style.
+    pub context: usize,
+
+    /// Normalize markdown cosmetics before comparing: trim trailing
+    /// whitespace, collapse runs of blank lines, and unify `*`/`+` bullet
+    /// markers to `-`.
+    pub normalize_markdown: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            context: 3,
+            normalize_markdown: false,
+        }
+    }
+}
+
+/// How a line participates in a hunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    Context,
+    Removed,
+    Added,
+}
+
+/// A single line within a hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub content: String,
+}
+
+/// A contiguous group of changes with surrounding context, using
+/// 1-based line numbers like unified diff headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// First line of the hunk in the old output.
+    pub old_start: usize,
+
+    /// Lines the hunk spans in the old output.
+    pub old_lines: usize,
+
+    /// First line of the hunk in the new output.
+    pub new_start: usize,
+
+    /// Lines the hunk spans in the new output.
+    pub new_lines: usize,
+
+    pub lines: Vec<DiffLine>,
+}
+
+/// Diff `old` against `new` and return the changed hunks. An empty
+/// result means the outputs are identical under the given options.
+pub fn diff(old: &str, new: &str, opts: &DiffOptions) -> Vec<Hunk> {
+    let old_lines = split_lines(old, opts);
+    let new_lines = split_lines(new, opts);
+
+    let edits = edit_script(&old_lines, &new_lines);
+    group_hunks(&edits, opts.context)
+}
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Keep(String),
+    Remove(String),
+    Add(String),
+}
+
+fn split_lines(text: &str, opts: &DiffOptions) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in text.lines() {
+        let line = if opts.normalize_markdown {
+            normalize_markdown_line(line)
+        } else {
+            line.to_string()
+        };
+
+        if opts.normalize_markdown && line.is_empty() && lines.last().is_some_and(String::is_empty)
+        {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    if opts.normalize_markdown {
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+    }
+
+    lines
+}
+
+fn normalize_markdown_line(line: &str) -> String {
+    let trimmed = line.trim_end();
+
+    let indent_len = trimmed.len() - trimmed.trim_start().len();
+    let (indent, rest) = trimmed.split_at(indent_len);
+    for marker in ["* ", "+ "] {
+        if let Some(item) = rest.strip_prefix(marker) {
+            return format!("{indent}- {item}");
+        }
+    }
+
+    trimmed.to_string()
+}
+
+/// Classic LCS dynamic program over lines; rendered outputs are small
+/// enough that the quadratic table is fine.
+fn edit_script(old: &[String], new: &[String]) -> Vec<Edit> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            edits.push(Edit::Keep(old[i].clone()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            edits.push(Edit::Remove(old[i].clone()));
+            i += 1;
+        } else {
+            edits.push(Edit::Add(new[j].clone()));
+            j += 1;
+        }
+    }
+    edits.extend(old[i..].iter().cloned().map(Edit::Remove));
+    edits.extend(new[j..].iter().cloned().map(Edit::Add));
+    edits
+}
+
+fn group_hunks(edits: &[Edit], context: usize) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut current: Option<(usize, usize, Vec<DiffLine>)> = None;
+    let mut trailing_context = 0usize;
+
+    let (mut old_line, mut new_line) = (1usize, 1usize);
+    let mut recent_context: Vec<String> = Vec::new();
+
+    for edit in edits {
+        match edit {
+            Edit::Keep(content) => {
+                if let Some((_, _, lines)) = current.as_mut() {
+                    if trailing_context < context {
+                        lines.push(DiffLine {
+                            kind: DiffLineKind::Context,
+                            content: content.clone(),
+                        });
+                        trailing_context += 1;
+                    } else {
+                        hunks.push(finish_hunk(current.take().unwrap()));
+                        recent_context.clear();
+                        recent_context.push(content.clone());
+                    }
+                } else {
+                    recent_context.push(content.clone());
+                    if recent_context.len() > context {
+                        recent_context.remove(0);
+                    }
+                }
+                old_line += 1;
+                new_line += 1;
+            }
+            Edit::Remove(content) | Edit::Add(content) => {
+                let kind = match edit {
+                    Edit::Remove(_) => DiffLineKind::Removed,
+                    _ => DiffLineKind::Added,
+                };
+
+                if current.is_none() {
+                    let leading = recent_context.len();
+                    let mut lines: Vec<DiffLine> = recent_context
+                        .drain(..)
+                        .map(|context_line| DiffLine {
+                            kind: DiffLineKind::Context,
+                            content: context_line,
+                        })
+                        .collect();
+                    lines.push(DiffLine {
+                        kind,
+                        content: content.clone(),
+                    });
+                    current = Some((old_line - leading, new_line - leading, lines));
+                } else if let Some((_, _, lines)) = current.as_mut() {
+                    lines.push(DiffLine {
+                        kind,
+                        content: content.clone(),
+                    });
+                }
+                trailing_context = 0;
+
+                match kind {
+                    DiffLineKind::Removed => old_line += 1,
+                    _ => new_line += 1,
+                }
+            }
+        }
+    }
+
+    if let Some(hunk) = current.take() {
+        hunks.push(finish_hunk(hunk));
+    }
+
+    hunks
+}
+
+fn finish_hunk((old_start, new_start, lines): (usize, usize, Vec<DiffLine>)) -> Hunk {
+    let old_lines = lines
+        .iter()
+        .filter(|line| line.kind != DiffLineKind::Added)
+        .count();
+    let new_lines = lines
+        .iter()
+        .filter(|line| line.kind != DiffLineKind::Removed)
+        .count();
+
+    Hunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_groups_changes_into_hunks_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\n";
+
+        let hunks = diff(old, new, &DiffOptions::default());
+        assert_eq!(hunks.len(), 1);
+
+        let hunk = &hunks[0];
+        assert_eq!(hunk.old_start, 1);
+        assert_eq!(hunk.old_lines, 7);
+        assert_eq!(hunk.new_lines, 7);
+        assert!(hunk
+            .lines
+            .iter()
+            .any(|line| { line.kind == DiffLineKind::Removed && line.content == "d" }));
+        assert!(hunk
+            .lines
+            .iter()
+            .any(|line| { line.kind == DiffLineKind::Added && line.content == "D" }));
+    }
+
+    #[test]
+    fn test_diff_identical_outputs_yields_no_hunks() {
+        assert!(diff("same\n", "same\n", &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_markdown_normalization_ignores_cosmetic_changes() {
+        let old = "# Title\n\n* item one  \n* item two\n";
+        let new = "# Title\n\n\n- item one\n- item two\n\n";
+
+        let opts = DiffOptions {
+            normalize_markdown: true,
+            ..DiffOptions::default()
+        };
+        assert!(diff(old, new, &opts).is_empty());
+        assert!(!diff(old, new, &DiffOptions::default()).is_empty());
+    }
+}
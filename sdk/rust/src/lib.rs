@@ -11,17 +11,64 @@
 //! assert_eq!(output.trim(), "Hello, World!");
 //! # Ok::<(), mlld::Error>(())
 //! ```
+//!
+//! The process-spawning client lives behind the default `client`
+//! feature. Disable default features to get just the protocol types
+//! ([`ExecuteResult`], [`StateWrite`], [`AnalyzeResult`], [`Error`])
+//! for services that only deserialize mlld results received from
+//! elsewhere.
+
+#[cfg(feature = "derive")]
+extern crate self as mlld;
+
+#[cfg(feature = "tokio")]
+pub mod async_client;
+pub mod codegen;
+pub mod diff;
+#[cfg(feature = "client")]
+pub mod lsp;
+#[cfg(feature = "client")]
+pub mod pool;
+#[cfg(feature = "prometheus")]
+mod prom;
+pub mod schema;
+#[cfg(feature = "client")]
+pub mod state;
+
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncClient;
+pub use schema::SchemaViolation;
+
+#[cfg(feature = "derive")]
+pub use mlld_derive::{MlldExports, MlldPayload};
+
+// Re-exported for use by the derive macros' generated code.
+#[doc(hidden)]
+pub use serde_json;
 
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(feature = "client")]
+use std::collections::{HashSet, VecDeque};
+#[cfg(feature = "client")]
 use std::io::{BufRead, BufReader, Write};
-use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio};
-use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "client")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "client")]
+use std::process::{Child, ChildStderr, Command, Stdio};
+#[cfg(feature = "client")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "client")]
 use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
-use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(feature = "client")]
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+#[cfg(feature = "client")]
 use std::thread;
-use std::time::{Duration, Instant};
+#[cfg(feature = "client")]
+use std::time::{Instant, SystemTime};
 
 /// Error type for mlld operations.
 #[derive(Debug, thiserror::Error)]
@@ -29,7 +76,12 @@ pub enum Error {
     #[error("mlld error: {message}")]
     Mlld {
         message: String,
-        code: Option<String>,
+        code: Option<ErrorCode>,
+
+        /// Structured location, excerpt, and hint parsed from the
+        /// error payload, when the server provided them. Boxed to keep
+        /// the common error variants small.
+        diagnostic: Option<Box<MlldDiagnostic>>,
     },
 
     #[error("transport error: {0}")]
@@ -41,8 +93,66 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
-    #[error("timeout after {0:?}")]
-    Timeout(Duration),
+    #[error("result deserialization failed: {0}")]
+    ResultParse(String),
+
+    #[error("exports failed schema validation ({} violations)", .0.len())]
+    SchemaViolation(Vec<SchemaViolation>),
+
+    #[error("result exceeded {what} limit: {actual} > {limit}")]
+    ResultTooLarge {
+        what: &'static str,
+        actual: usize,
+        limit: usize,
+    },
+
+    #[error("{} timeout after {:?}", .0.phase, .0.limit)]
+    Timeout(Box<TimeoutInfo>),
+
+    #[error(
+        "client overloaded: interpreter RSS {rss_bytes} bytes exceeds {limit_bytes} byte limit"
+    )]
+    Overloaded { rss_bytes: u64, limit_bytes: u64 },
+
+    #[error("rate limited: next token in {retry_after:?}")]
+    RateLimited {
+        /// Time until the next token becomes available.
+        retry_after: Duration,
+    },
+
+    /// The circuit breaker is open after repeated transport spawn
+    /// failures; see [`Client::with_circuit_breaker`].
+    #[error("circuit open after repeated transport failures; retry in {retry_after:?}")]
+    CircuitOpen {
+        /// Time until spawns are attempted again.
+        retry_after: Duration,
+    },
+
+    /// The heartbeat monitor declared the transport dead after missed
+    /// keepalives and restarted it; see [`Client::with_heartbeat`].
+    #[error("live transport missed heartbeats and was restarted")]
+    HeartbeatLost,
+
+    #[error("request cancelled{}", .reason.as_deref().map(|r| format!(": {r}")).unwrap_or_default())]
+    Cancelled { reason: Option<String> },
+
+    #[error("live transport failed to start: {stderr}")]
+    StartupFailed { stderr: String },
+
+    #[error("method {method} is not supported by this mlld CLI{}", .cli_version.as_deref().map(|v| format!(" (version {v})")).unwrap_or_default())]
+    UnsupportedMethod {
+        method: String,
+        cli_version: Option<String>,
+    },
+
+    #[error("failed to spawn `{command_line}` ({source}); PATH={path_env}{hint}")]
+    SpawnFailed {
+        command_line: String,
+        path_env: String,
+        hint: String,
+        #[source]
+        source: std::io::Error,
+    },
 }
 
 /// Result type alias for mlld operations.
@@ -50,6 +160,7 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 /// Wrapper around the mlld CLI.
 #[derive(Clone)]
+#[cfg(feature = "client")]
 pub struct Client {
     /// The mlld command to invoke.
     pub command: String,
@@ -64,30 +175,310 @@ pub struct Client {
     /// Working directory for script execution.
     pub working_dir: Option<String>,
 
+    /// How child stderr is handled. Defaults to [`StderrMode::Capture`].
+    pub stderr_mode: StderrMode,
+
+    /// Maximum bytes of captured stderr retained for error reporting;
+    /// older lines are evicted first.
+    pub stderr_buffer_limit: usize,
+
+    /// Maximum bytes for a single stdout protocol frame; longer lines are
+    /// discarded and reading resynchronizes at the next newline.
+    pub max_frame_bytes: usize,
+
+    /// Whether result envelopes that fail to deserialize are surfaced as
+    /// errors (default) or stuffed into `output` verbatim.
+    pub result_parsing: ResultParsing,
+
+    /// Keep a warm spare child process to promote instantly when the
+    /// active transport dies, hiding interpreter startup latency.
+    pub warm_standby: bool,
+
+    /// Bounded wait for the live server to answer a readiness probe after
+    /// spawn; early child exit surfaces [`Error::StartupFailed`].
+    pub startup_probe_timeout: Duration,
+
     transport: Arc<Mutex<Option<LiveTransport>>>,
+    standby: Arc<Mutex<Option<LiveTransport>>>,
     next_request_id: Arc<AtomicU64>,
+    latency: Arc<Mutex<HashMap<String, LatencyWindow>>>,
+    transport_spawns: Arc<AtomicU64>,
+
+    /// Reject new low-priority requests when the interpreter's resident
+    /// set exceeds this many bytes; see [`Client::with_max_rss_bytes`].
+    pub max_rss_bytes: Option<u64>,
+
+    /// Cap on evaluation requests in flight at once; calls beyond the
+    /// limit wait in a queue for a slot instead of all being written to
+    /// the child immediately. See
+    /// [`Client::with_max_concurrent_requests`].
+    pub max_concurrent_requests: Option<usize>,
+
+    /// Bound on how long a call waits for an in-flight slot when the
+    /// client is at [`Client::max_concurrent_requests`]; `None` waits
+    /// indefinitely.
+    pub queue_timeout: Option<Duration>,
+    request_gate: ConcurrencyGate,
+
+    /// Token-bucket rate limit on evaluation request starts; see
+    /// [`Client::with_rate_limit`].
+    pub rate_limit: Option<RateLimit>,
+    rate_bucket: TokenBucket,
+
+    /// Retry policy for transient transport failures on replay-safe
+    /// requests; see [`Client::with_retry`].
+    pub retry: Option<RetryPolicy>,
+
+    /// Fast-fail window after repeated transport spawn failures; see
+    /// [`Client::with_circuit_breaker`].
+    pub circuit_breaker: Option<CircuitBreaker>,
+    breaker: Arc<Mutex<BreakerState>>,
+    closing: Arc<AtomicBool>,
+
+    /// Shut the child down after this long without requests; see
+    /// [`Client::with_idle_timeout`].
+    pub idle_timeout: Option<Duration>,
+
+    /// Keepalive schedule for the live transport; see
+    /// [`Client::with_heartbeat`].
+    pub heartbeat: Option<Heartbeat>,
+    last_activity: Arc<Mutex<Instant>>,
+
+    /// Worker profiles for label-routed requests; see
+    /// [`Client::with_worker_profile`].
+    pub worker_profiles: Vec<WorkerProfile>,
+    workers: Arc<Mutex<HashMap<usize, LiveTransport>>>,
+    raw_subscribers: Arc<Mutex<Vec<Sender<Value>>>>,
+
+    /// Log frames that reference a request id this client no longer
+    /// tracks instead of dropping them silently; see
+    /// [`Client::with_orphan_logging`].
+    pub log_orphan_frames: bool,
+    orphan_sink: Option<Arc<OrphanCallback>>,
+    orphan_frames: Arc<AtomicU64>,
+
+    /// Keep the last this-many requests in an in-memory trace ring for
+    /// [`Client::recent_requests`]; 0 disables tracing. See
+    /// [`Client::with_trace_limit`].
+    pub trace_limit: usize,
+    trace_pending: Arc<Mutex<HashMap<u64, PendingTrace>>>,
+    traces: Arc<Mutex<VecDeque<RequestTrace>>>,
+
+    /// Entries older than this are treated as misses by the result
+    /// cache; `None` keeps entries indefinitely.
+    pub result_cache_ttl: Option<Duration>,
+    result_cache: Option<Arc<dyn ResultCacheStore>>,
+
+    /// Fault injection for transport-failure testing; see
+    /// [`Client::with_chaos`].
+    pub chaos: Option<ChaosOptions>,
+
+    /// Polling schedule for eventually-ready operations such as state
+    /// reads and writes against a request that is still starting; see
+    /// [`Client::with_retry_backoff`].
+    pub retry_backoff: Backoff,
+
+    /// Wire-level transport settings such as frame encoding; see
+    /// [`Client::with_transport_options`].
+    pub transport_options: TransportOptions,
+
+    /// Run each request as a plain one-shot subprocess instead of over
+    /// a persistent live transport; see [`Client::with_oneshot`].
+    pub oneshot: bool,
+
+    /// Connect to a remote live server instead of spawning a local
+    /// child; see [`Client::with_remote`].
+    pub remote: Option<RemoteOptions>,
+
+    #[cfg(feature = "prometheus")]
+    prom: Arc<prom::PromMetrics>,
 }
 
+#[cfg(feature = "client")]
 struct RequestHandle {
     client: Client,
+    method: &'static str,
     request_id: u64,
     receiver: Option<Receiver<TransportMessage>>,
     timeout: Option<Duration>,
+    started: Instant,
+    limits: Option<ResultLimits>,
+    worker: Option<usize>,
+    scratch_dir: Option<PathBuf>,
+    retain_scratch_on_failure: bool,
+    queue_events: Vec<QueueEvent>,
+    warnings: Vec<Warning>,
+    partials: Vec<Value>,
+    on_event: Option<Arc<EventCallback>>,
+    effects_sender: Option<Sender<Effect>>,
+    state_watchers: Vec<StateWatcher>,
     cached_result: Option<(Value, Vec<StateWrite>)>,
+    detached: bool,
+}
+
+/// A registered state-path watcher: pattern plus callback.
+#[cfg(feature = "client")]
+type StateWatcher = (String, Arc<StateWatchCallback>);
+
+/// Callback invoked for each state write matching a watched pattern;
+/// see `watch_state` on [`ProcessHandle`] and [`ExecuteHandle`].
+#[cfg(feature = "client")]
+pub type StateWatchCallback = dyn Fn(&StateWrite) + Send + Sync;
+
+/// A handle dropped without being awaited cancels its server-side
+/// request, so abandoned executions do not run forever; `detach`
+/// opts out for fire-and-forget cases. An un-awaited drop also frees
+/// the handle's concurrency slot.
+#[cfg(feature = "client")]
+impl Drop for RequestHandle {
+    fn drop(&mut self) {
+        if self.detached || self.receiver.is_none() {
+            return;
+        }
+        self.cancel();
+        self.client
+            .finish_trace(self.request_id, false, "dropped without awaiting");
+        self.client.release_request_slot(self.method);
+        // record_latency never runs for an un-awaited drop, so settle
+        // the in-flight gauge here instead.
+        #[cfg(feature = "prometheus")]
+        self.client.prom.in_flight.dec();
+    }
+}
+
+/// Callback invoked for every live event observed for a request.
+#[cfg(feature = "client")]
+pub type EventCallback = dyn Fn(&LiveEvent) + Send + Sync;
+
+/// A live protocol event observed for an in-flight request.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct LiveEvent {
+    /// Event type string (`state:write`, `effect`, `queue:started`, ...).
+    pub event_type: String,
+
+    /// Full event payload as received from the server.
+    pub payload: Value,
+}
+
+#[cfg(feature = "client")]
+impl LiveEvent {
+    fn from_payload(payload: Value) -> Self {
+        let event_type = payload
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            event_type,
+            payload,
+        }
+    }
 }
 
+#[cfg(feature = "client")]
 impl RequestHandle {
     fn request_id(&self) -> u64 {
         self.request_id
     }
 
     fn cancel(&self) {
-        self.client.cancel_request(self.request_id);
+        self.client.cancel_request(self.request_id, self.worker);
+    }
+
+    fn detach(&mut self) {
+        self.detached = true;
+    }
+
+    fn cancel_with_deadline(&self, deadline: Duration) {
+        self.cancel();
+
+        let cutoff = Instant::now() + deadline;
+        while Instant::now() < cutoff {
+            if !self.client.request_is_pending(self.request_id, self.worker) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        self.client.kill_transport(self.worker);
+    }
+
+    fn watch_state(&mut self, pattern: String, callback: Arc<StateWatchCallback>) {
+        self.state_watchers.push((pattern, callback));
+    }
+
+    fn suspend(&self) -> Result<ExecutionSnapshot> {
+        let mut params = serde_json::Map::new();
+        params.insert("requestId".to_string(), json!(self.request_id));
+
+        let (result, _) =
+            self.client
+                .request_on("snapshot", Value::Object(params), self.timeout, self.worker)?;
+
+        let snapshot = result.get("snapshot").cloned().ok_or_else(|| {
+            Error::ResultParse("snapshot result is missing the snapshot field".to_string())
+        })?;
+        deserialize_with_path::<ExecutionSnapshot>(snapshot.clone()).map_err(|error| {
+            Error::ResultParse(format!(
+                "{error} (result: {})",
+                frame_preview(&snapshot.to_string())
+            ))
+        })
+    }
+
+    fn set_loop_interval(&self, interval: Duration) -> Result<()> {
+        self.client
+            .loop_interval_request(self.request_id, interval, self.timeout, self.worker)
+    }
+
+    fn cancel_with_reason(&self, reason: &CancelReason) {
+        self.client
+            .cancel_request_with_reason(self.request_id, self.worker, Some(reason));
     }
 
     fn update_state(&self, path: &str, value: Value) -> Result<()> {
+        self.update_state_with_op(path, value, StateOp::Set)
+    }
+
+    fn update_state_with_op(&self, path: &str, value: Value, op: StateOp) -> Result<()> {
+        self.client.update_state_request(
+            self.request_id,
+            StateUpdate {
+                path,
+                value,
+                op,
+                labels: None,
+            },
+            self.timeout,
+            self.worker,
+        )
+    }
+
+    fn update_states(&self, updates: Vec<(String, Value, StateOp)>) -> Result<()> {
+        self.client.update_states_request(
+            self.request_id,
+            updates,
+            false,
+            self.timeout,
+            self.worker,
+        )
+    }
+
+    /// Remove the scratch directory once the request has settled,
+    /// unless a failed request asked to retain it.
+    fn cleanup_scratch(&mut self, success: bool) {
+        if !success && self.retain_scratch_on_failure {
+            return;
+        }
+        if let Some(dir) = self.scratch_dir.take() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    fn read_state(&self, path: &str) -> Result<Value> {
         self.client
-            .update_state_request(self.request_id, path, value, self.timeout, None)
+            .read_state_request(self.request_id, path, self.timeout, self.worker)
     }
 
     fn update_state_with_labels(
@@ -96,8 +487,17 @@ impl RequestHandle {
         value: Value,
         labels: Vec<String>,
     ) -> Result<()> {
-        self.client
-            .update_state_request(self.request_id, path, value, self.timeout, Some(labels))
+        self.client.update_state_request(
+            self.request_id,
+            StateUpdate {
+                path,
+                value,
+                op: StateOp::Set,
+                labels: Some(labels),
+            },
+            self.timeout,
+            self.worker,
+        )
     }
 
     fn wait_raw(&mut self) -> Result<(Value, Vec<StateWrite>)> {
@@ -110,20 +510,101 @@ impl RequestHandle {
             .take()
             .ok_or_else(|| Error::Transport("request handle already awaited".to_string()))?;
 
-        let (result, state_writes) =
-            self.client
-                .await_request(self.request_id, receiver, self.timeout)?;
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "mlld_request",
+            method = self.method,
+            request_id = self.request_id,
+            outcome = tracing::field::Empty
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let outcome = self.client.await_request(
+            self.request_id,
+            receiver,
+            self.timeout,
+            self.worker,
+            RequestObservers {
+                queue_events: &mut self.queue_events,
+                warnings: &mut self.warnings,
+                partials: &mut self.partials,
+                observer: self.on_event.as_deref(),
+                effects: self.effects_sender.as_ref(),
+                state_watchers: &self.state_watchers,
+            },
+        );
+        self.effects_sender = None;
+        self.cleanup_scratch(outcome.is_ok());
+        #[cfg(feature = "tracing")]
+        span.record("outcome", if outcome.is_ok() { "ok" } else { "error" });
+        self.client
+            .record_latency(self.method, self.started.elapsed(), outcome.is_ok());
+        match &outcome {
+            Ok((result, _)) => self
+                .client
+                .finish_trace(self.request_id, true, &result.to_string()),
+            Err(error) => self
+                .client
+                .finish_trace(self.request_id, false, &error.to_string()),
+        }
+        self.client.release_request_slot(self.method);
+        let (result, state_writes) = outcome?;
+
+        if let Some(limits) = &self.limits {
+            limits.check(&result, &state_writes)?;
+        }
 
         self.cached_result = Some((result.clone(), state_writes.clone()));
         Ok((result, state_writes))
     }
+
+    fn set_on_event(&mut self, callback: impl Fn(&LiveEvent) + Send + Sync + 'static) {
+        self.on_event = Some(Arc::new(callback));
+    }
+
+    fn effects(&mut self) -> Receiver<Effect> {
+        let (sender, receiver) = mpsc::channel();
+        self.effects_sender = Some(sender);
+        receiver
+    }
+
+    fn stream(&mut self) -> Result<OutputStream<'_>> {
+        let receiver = self
+            .receiver
+            .take()
+            .ok_or_else(|| Error::Transport("request handle already awaited".to_string()))?;
+        let deadline = self.timeout.map(|limit| Instant::now() + limit);
+
+        Ok(OutputStream {
+            receiver,
+            state_writes: Vec::new(),
+            finished: false,
+            deadline,
+            request: self,
+        })
+    }
+
+    fn queue_wait(&self) -> Option<Duration> {
+        let queued = self
+            .queue_events
+            .iter()
+            .find(|event| event.phase == QueuePhase::Queued)?;
+        let started = self
+            .queue_events
+            .iter()
+            .find(|event| event.phase == QueuePhase::Started)?;
+        started.received.checked_duration_since(queued.received)
+    }
 }
 
 /// In-flight process request handle.
+#[cfg(feature = "client")]
 pub struct ProcessHandle {
     request: RequestHandle,
 }
 
+#[cfg(feature = "client")]
 impl ProcessHandle {
     /// Live request identifier.
     pub fn request_id(&self) -> u64 {
@@ -135,12 +616,114 @@ impl ProcessHandle {
         self.request.cancel();
     }
 
+    /// Let the server-side request keep running when this handle is
+    /// dropped without being awaited, instead of the default
+    /// cancel-on-drop. For fire-and-forget executions.
+    pub fn detach(&mut self) {
+        self.request.detach();
+    }
+
+    /// Request graceful cancellation, recording why. The reason is
+    /// forwarded to the interpreter and echoed in audit events and the
+    /// resulting [`Error::Cancelled`].
+    pub fn cancel_with_reason(&self, reason: CancelReason) {
+        self.request.cancel_with_reason(&reason);
+    }
+
+    /// Request graceful cancellation and escalate to killing the child
+    /// process if the request has not settled within `deadline`, so a
+    /// script stuck in a blocking shell command cannot wedge the
+    /// client. The transport respawns transparently on the next
+    /// request.
+    pub fn cancel_with_deadline(&self, deadline: Duration) {
+        self.request.cancel_with_deadline(deadline);
+    }
+
+    /// Invoke `callback` for each `state:write` event whose path
+    /// matches `pattern` while the run is awaited, instead of
+    /// collecting every write and filtering after completion. `*`
+    /// matches one dot-separated segment (`agents.*.status`).
+    /// Callbacks run on the awaiting thread; register before awaiting
+    /// the handle.
+    pub fn watch_state(
+        &mut self,
+        pattern: impl Into<String>,
+        callback: impl Fn(&StateWrite) + Send + Sync + 'static,
+    ) {
+        self.request.watch_state(pattern.into(), Arc::new(callback));
+    }
+
+    /// Adjust the delay between iterations of this request's running
+    /// loop, so hosts can throttle agent loops under load without
+    /// cancelling and restarting them.
+    pub fn set_loop_interval(&self, interval: Duration) -> Result<()> {
+        self.request.set_loop_interval(interval)
+    }
+
+    /// Receive effects as they arrive over the live transport, instead
+    /// of only after completion. The channel ends once the request
+    /// finishes; effects still appear in the final result as usual.
+    pub fn effects(&mut self) -> Receiver<Effect> {
+        self.request.effects()
+    }
+
+    /// Suspend this execution and capture its state — loop counters,
+    /// `@state`, environment — as a serializable snapshot. The request
+    /// ends; pass the snapshot to [`Client::resume`] to continue it
+    /// later, possibly on a different transport.
+    pub fn suspend(&self) -> Result<ExecutionSnapshot> {
+        self.request.suspend()
+    }
+
     /// Send a state:update request for this in-flight execution.
     pub fn update_state<V: Serialize>(&self, path: &str, value: V) -> Result<()> {
         self.request
             .update_state(path, serde_json::to_value(value)?)
     }
 
+    /// Send a state:update with an explicit operation: [`StateOp::MergePatch`]
+    /// merges `value` into the object at `path` without re-sending the
+    /// whole subtree, [`StateOp::Delete`] removes the path (the value is
+    /// ignored), and [`StateOp::Append`] pushes onto the array at `path`.
+    pub fn update_state_with_op<V: Serialize>(
+        &self,
+        path: &str,
+        value: V,
+        op: StateOp,
+    ) -> Result<()> {
+        self.request
+            .update_state_with_op(path, serde_json::to_value(value)?, op)
+    }
+
+    /// Send several state writes in a single state:update round trip,
+    /// instead of paying a full round trip (and its startup retry
+    /// loop) per key.
+    pub fn update_states<V: Serialize>(&self, updates: &[(&str, V)]) -> Result<()> {
+        let updates = updates
+            .iter()
+            .map(|(path, value)| Ok((path.to_string(), serde_json::to_value(value)?, StateOp::Set)))
+            .collect::<Result<Vec<_>>>()?;
+        self.request.update_states(updates)
+    }
+
+    /// Start a [`StateTransaction`] that buffers writes locally and
+    /// applies them in one atomic state:update on commit, so a partly
+    /// applied configuration change can't leave the running script in
+    /// an inconsistent state.
+    pub fn state_transaction(&self) -> StateTransaction<'_> {
+        StateTransaction {
+            request: &self.request,
+            updates: Vec::new(),
+        }
+    }
+
+    /// Read the interpreter's current state at `path` mid-run via a
+    /// state:get request, without waiting for completion. Returns
+    /// `Value::Null` when nothing is set at that path.
+    pub fn read_state(&self, path: &str) -> Result<Value> {
+        self.request.read_state(path)
+    }
+
     /// Send a labeled state:update request for this in-flight execution.
     pub fn update_state_with_labels<V, I, S>(&self, path: &str, value: V, labels: I) -> Result<()>
     where
@@ -155,6 +738,52 @@ impl ProcessHandle {
         )
     }
 
+    /// Queue lifecycle events observed for this request, in arrival
+    /// order. Populated once the request has been awaited.
+    pub fn queue_events(&self) -> &[QueueEvent] {
+        &self.request.queue_events
+    }
+
+    /// Warnings observed for this request so far, in arrival order.
+    /// Populated once the request has been awaited; also merged into
+    /// the result's `warnings` field.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.request.warnings
+    }
+
+    /// Partial results — checkpoints of long runs the server emitted
+    /// before the final result — observed so far, oldest first.
+    pub fn partials(&self) -> &[Value] {
+        &self.request.partials
+    }
+
+    /// Path of the provisioned scratch directory. `Some` before the
+    /// request is awaited, and afterwards only when a failed request
+    /// retained it for debugging.
+    pub fn scratch_dir(&self) -> Option<&Path> {
+        self.request.scratch_dir.as_deref()
+    }
+
+    /// Time the request spent queued before the server started it, when
+    /// the server reported queue events.
+    pub fn queue_wait(&self) -> Option<Duration> {
+        self.request.queue_wait()
+    }
+
+    /// Observe every live event for this request — progress, state
+    /// writes, effects — as it arrives, while the request is awaited.
+    pub fn on_event(&mut self, callback: impl Fn(&LiveEvent) + Send + Sync + 'static) {
+        self.request.set_on_event(callback);
+    }
+
+    /// Stream incremental output chunks (show/doc effects) as the server
+    /// emits them, ahead of the final result. After the stream ends
+    /// without error, [`result`](Self::result) returns the full output
+    /// without blocking further.
+    pub fn stream(&mut self) -> Result<OutputStream<'_>> {
+        self.request.stream()
+    }
+
     /// Wait for completion and return output.
     pub fn wait(&mut self) -> Result<String> {
         self.result()
@@ -173,13 +802,196 @@ impl ProcessHandle {
 
         Ok(String::new())
     }
+
+    /// Wait for completion and return the full structured result —
+    /// state writes, effects, exports, metrics — instead of just the
+    /// rendered output.
+    pub fn result_full(&mut self) -> Result<ProcessResult> {
+        let (result, state_write_events) = self.request.wait_raw()?;
+        let mut parsed = parse_execute_result(
+            result,
+            state_write_events,
+            self.request.client.result_parsing,
+            None,
+        )?;
+        merge_warnings(&mut parsed.warnings, &self.request.warnings);
+        Ok(parsed)
+    }
+}
+
+/// One incremental output chunk emitted while a request runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg(feature = "client")]
+pub struct OutputChunk {
+    /// Effect channel the chunk came from (`show` or `doc`).
+    pub effect_type: String,
+
+    /// Chunk content, in emission order.
+    pub content: String,
+}
+
+/// Iterator over incremental output chunks for an in-flight request.
+///
+/// Ends when the final result arrives; other events observed along the
+/// way (state writes, queue events) are still recorded on the handle.
+#[cfg(feature = "client")]
+pub struct OutputStream<'a> {
+    request: &'a mut RequestHandle,
+    receiver: Receiver<TransportMessage>,
+    state_writes: Vec<StateWrite>,
+    finished: bool,
+    deadline: Option<Instant>,
+}
+
+#[cfg(feature = "client")]
+impl Iterator for OutputStream<'_> {
+    type Item = Result<OutputChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let message = match self.deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Some(Err(self.fail_timeout()));
+                    }
+                    match self.receiver.recv_timeout(deadline - now) {
+                        Ok(message) => message,
+                        Err(RecvTimeoutError::Timeout) => {
+                            return Some(Err(self.fail_timeout()));
+                        }
+                        Err(RecvTimeoutError::Disconnected) => {
+                            return Some(Err(self.fail_disconnected()));
+                        }
+                    }
+                }
+                None => match self.receiver.recv() {
+                    Ok(message) => message,
+                    Err(_) => return Some(Err(self.fail_disconnected())),
+                },
+            };
+
+            match message {
+                TransportMessage::Event(event) => {
+                    if let Some(write) = parse_state_write_event(&event) {
+                        for (pattern, callback) in &self.request.state_watchers {
+                            if state_path_matches(pattern, &write.path) {
+                                callback(&write);
+                            }
+                        }
+                        self.state_writes.push(write);
+                    }
+                    if let Some(queue_event) = parse_queue_event(&event) {
+                        self.request.queue_events.push(queue_event);
+                    }
+                    if let Some(warning) = parse_warning_event(&event) {
+                        self.request.warnings.push(warning);
+                    }
+                    if let Some(partial) = parse_partial_result_event(&event) {
+                        self.request.partials.push(partial);
+                    }
+                    if let Some(sender) = &self.request.effects_sender {
+                        if let Some(effect) = parse_effect_event(&event) {
+                            let _ = sender.send(effect);
+                        }
+                    }
+                    if let Some(observer) = self.request.on_event.clone() {
+                        observer(&LiveEvent::from_payload(event.clone()));
+                    }
+                    if let Some(chunk) = parse_output_chunk_event(&event) {
+                        return Some(Ok(chunk));
+                    }
+                }
+                TransportMessage::Result(result) => {
+                    self.finished = true;
+                    self.request
+                        .client
+                        .release_request_slot(self.request.method);
+
+                    if let Some(error_payload) = result.get("error") {
+                        return Some(Err(error_from_payload(error_payload)));
+                    }
+
+                    self.request.client.record_latency(
+                        self.request.method,
+                        self.request.started.elapsed(),
+                        true,
+                    );
+                    self.request.client.finish_trace(
+                        self.request.request_id,
+                        true,
+                        &result.to_string(),
+                    );
+
+                    if let Some(limits) = &self.request.limits {
+                        if let Err(error) = limits.check(&result, &self.state_writes) {
+                            return Some(Err(error));
+                        }
+                    }
+
+                    self.request.cached_result =
+                        Some((result, std::mem::take(&mut self.state_writes)));
+                    return None;
+                }
+                TransportMessage::Closed(message) => {
+                    self.finished = true;
+                    self.request
+                        .client
+                        .release_request_slot(self.request.method);
+                    self.request.client.invalidate_worker(self.request.worker);
+                    return Some(Err(closed_error(message)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl OutputStream<'_> {
+    fn fail_timeout(&mut self) -> Error {
+        self.finished = true;
+        self.request
+            .client
+            .release_request_slot(self.request.method);
+        let limit = self.request.timeout.expect("deadline implies timeout");
+        self.request.client.cancel_request_with_reason(
+            self.request.request_id,
+            self.request.worker,
+            Some(&CancelReason::TimeoutEscalation),
+        );
+        self.request
+            .client
+            .remove_pending_request(self.request.request_id, self.request.worker);
+        Error::Timeout(Box::new(TimeoutInfo {
+            phase: TimeoutPhase::Execution,
+            limit,
+            state_writes: std::mem::take(&mut self.state_writes),
+            partials: self.request.partials.clone(),
+        }))
+    }
+
+    fn fail_disconnected(&mut self) -> Error {
+        self.finished = true;
+        self.request
+            .client
+            .release_request_slot(self.request.method);
+        self.request.client.invalidate_worker(self.request.worker);
+        Error::Transport("live transport disconnected".to_string())
+    }
 }
 
 /// In-flight execute request handle.
+#[cfg(feature = "client")]
 pub struct ExecuteHandle {
     request: RequestHandle,
+    exports_schema: Option<Value>,
 }
 
+#[cfg(feature = "client")]
 impl ExecuteHandle {
     /// Live request identifier.
     pub fn request_id(&self) -> u64 {
@@ -191,12 +1003,114 @@ impl ExecuteHandle {
         self.request.cancel();
     }
 
+    /// Let the server-side request keep running when this handle is
+    /// dropped without being awaited, instead of the default
+    /// cancel-on-drop. For fire-and-forget executions.
+    pub fn detach(&mut self) {
+        self.request.detach();
+    }
+
+    /// Request graceful cancellation, recording why. The reason is
+    /// forwarded to the interpreter and echoed in audit events and the
+    /// resulting [`Error::Cancelled`].
+    pub fn cancel_with_reason(&self, reason: CancelReason) {
+        self.request.cancel_with_reason(&reason);
+    }
+
+    /// Request graceful cancellation and escalate to killing the child
+    /// process if the request has not settled within `deadline`, so a
+    /// script stuck in a blocking shell command cannot wedge the
+    /// client. The transport respawns transparently on the next
+    /// request.
+    pub fn cancel_with_deadline(&self, deadline: Duration) {
+        self.request.cancel_with_deadline(deadline);
+    }
+
+    /// Invoke `callback` for each `state:write` event whose path
+    /// matches `pattern` while the run is awaited, instead of
+    /// collecting every write and filtering after completion. `*`
+    /// matches one dot-separated segment (`agents.*.status`).
+    /// Callbacks run on the awaiting thread; register before awaiting
+    /// the handle.
+    pub fn watch_state(
+        &mut self,
+        pattern: impl Into<String>,
+        callback: impl Fn(&StateWrite) + Send + Sync + 'static,
+    ) {
+        self.request.watch_state(pattern.into(), Arc::new(callback));
+    }
+
+    /// Adjust the delay between iterations of this request's running
+    /// loop, so hosts can throttle agent loops under load without
+    /// cancelling and restarting them.
+    pub fn set_loop_interval(&self, interval: Duration) -> Result<()> {
+        self.request.set_loop_interval(interval)
+    }
+
+    /// Receive effects as they arrive over the live transport, instead
+    /// of only after completion. The channel ends once the request
+    /// finishes; effects still appear in the final result as usual.
+    pub fn effects(&mut self) -> Receiver<Effect> {
+        self.request.effects()
+    }
+
+    /// Suspend this execution and capture its state — loop counters,
+    /// `@state`, environment — as a serializable snapshot. The request
+    /// ends; pass the snapshot to [`Client::resume`] to continue it
+    /// later, possibly on a different transport.
+    pub fn suspend(&self) -> Result<ExecutionSnapshot> {
+        self.request.suspend()
+    }
+
     /// Send a state:update request for this in-flight execution.
     pub fn update_state<V: Serialize>(&self, path: &str, value: V) -> Result<()> {
         self.request
             .update_state(path, serde_json::to_value(value)?)
     }
 
+    /// Send a state:update with an explicit operation: [`StateOp::MergePatch`]
+    /// merges `value` into the object at `path` without re-sending the
+    /// whole subtree, [`StateOp::Delete`] removes the path (the value is
+    /// ignored), and [`StateOp::Append`] pushes onto the array at `path`.
+    pub fn update_state_with_op<V: Serialize>(
+        &self,
+        path: &str,
+        value: V,
+        op: StateOp,
+    ) -> Result<()> {
+        self.request
+            .update_state_with_op(path, serde_json::to_value(value)?, op)
+    }
+
+    /// Send several state writes in a single state:update round trip,
+    /// instead of paying a full round trip (and its startup retry
+    /// loop) per key.
+    pub fn update_states<V: Serialize>(&self, updates: &[(&str, V)]) -> Result<()> {
+        let updates = updates
+            .iter()
+            .map(|(path, value)| Ok((path.to_string(), serde_json::to_value(value)?, StateOp::Set)))
+            .collect::<Result<Vec<_>>>()?;
+        self.request.update_states(updates)
+    }
+
+    /// Start a [`StateTransaction`] that buffers writes locally and
+    /// applies them in one atomic state:update on commit, so a partly
+    /// applied configuration change can't leave the running script in
+    /// an inconsistent state.
+    pub fn state_transaction(&self) -> StateTransaction<'_> {
+        StateTransaction {
+            request: &self.request,
+            updates: Vec::new(),
+        }
+    }
+
+    /// Read the interpreter's current state at `path` mid-run via a
+    /// state:get request, without waiting for completion. Returns
+    /// `Value::Null` when nothing is set at that path.
+    pub fn read_state(&self, path: &str) -> Result<Value> {
+        self.request.read_state(path)
+    }
+
     /// Send a labeled state:update request for this in-flight execution.
     pub fn update_state_with_labels<V, I, S>(&self, path: &str, value: V, labels: I) -> Result<()>
     where
@@ -211,6 +1125,44 @@ impl ExecuteHandle {
         )
     }
 
+    /// Queue lifecycle events observed for this request, in arrival
+    /// order. Populated once the request has been awaited.
+    pub fn queue_events(&self) -> &[QueueEvent] {
+        &self.request.queue_events
+    }
+
+    /// Warnings observed for this request so far, in arrival order.
+    /// Populated once the request has been awaited; also merged into
+    /// the result's `warnings` field.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.request.warnings
+    }
+
+    /// Partial results — checkpoints of long runs the server emitted
+    /// before the final result — observed so far, oldest first.
+    pub fn partials(&self) -> &[Value] {
+        &self.request.partials
+    }
+
+    /// Path of the provisioned scratch directory. `Some` before the
+    /// request is awaited, and afterwards only when a failed request
+    /// retained it for debugging.
+    pub fn scratch_dir(&self) -> Option<&Path> {
+        self.request.scratch_dir.as_deref()
+    }
+
+    /// Time the request spent queued before the server started it, when
+    /// the server reported queue events.
+    pub fn queue_wait(&self) -> Option<Duration> {
+        self.request.queue_wait()
+    }
+
+    /// Observe every live event for this request — progress, state
+    /// writes, effects — as it arrives, while the request is awaited.
+    pub fn on_event(&mut self, callback: impl Fn(&LiveEvent) + Send + Sync + 'static) {
+        self.request.set_on_event(callback);
+    }
+
     /// Wait for completion and return structured output.
     pub fn wait(&mut self) -> Result<ExecuteResult> {
         self.result()
@@ -218,32 +1170,26 @@ impl ExecuteHandle {
 
     /// Wait for completion and return structured output.
     pub fn result(&mut self) -> Result<ExecuteResult> {
-        let (mut result, state_write_events) = self.request.wait_raw()?;
-
-        if let Value::Object(map) = &mut result {
-            map.remove("id");
-        }
-
-        let mut execute_result = match serde_json::from_value::<ExecuteResult>(result.clone()) {
-            Ok(parsed) => parsed,
-            Err(_) => ExecuteResult {
-                output: result.to_string(),
-                ..Default::default()
-            },
-        };
-
-        execute_result.state_writes =
-            merge_state_writes(execute_result.state_writes, state_write_events);
-        Ok(execute_result)
+        let (result, state_write_events) = self.request.wait_raw()?;
+        let mut parsed = parse_execute_result(
+            result,
+            state_write_events,
+            self.request.client.result_parsing,
+            self.exports_schema.as_ref(),
+        )?;
+        merge_warnings(&mut parsed.warnings, &self.request.warnings);
+        Ok(parsed)
     }
 }
 
+#[cfg(feature = "client")]
 impl Default for Client {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "client")]
 impl Client {
     /// Create a new Client with default settings.
     pub fn new() -> Self {
@@ -252,8 +1198,49 @@ impl Client {
             command_args: Vec::new(),
             timeout: Some(Duration::from_secs(30)),
             working_dir: None,
+            stderr_mode: StderrMode::Capture,
+            stderr_buffer_limit: DEFAULT_STDERR_BUFFER_LIMIT,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            result_parsing: ResultParsing::Strict,
+            warm_standby: false,
+            startup_probe_timeout: Duration::from_secs(2),
             transport: Arc::new(Mutex::new(None)),
+            standby: Arc::new(Mutex::new(None)),
             next_request_id: Arc::new(AtomicU64::new(1)),
+            raw_subscribers: Arc::new(Mutex::new(Vec::new())),
+            log_orphan_frames: false,
+            orphan_sink: None,
+            orphan_frames: Arc::new(AtomicU64::new(0)),
+            trace_limit: 0,
+            trace_pending: Arc::new(Mutex::new(HashMap::new())),
+            traces: Arc::new(Mutex::new(VecDeque::new())),
+            latency: Arc::new(Mutex::new(HashMap::new())),
+            transport_spawns: Arc::new(AtomicU64::new(0)),
+            max_rss_bytes: None,
+            max_concurrent_requests: None,
+            queue_timeout: None,
+            request_gate: ConcurrencyGate::default(),
+            rate_limit: None,
+            rate_bucket: TokenBucket::default(),
+            retry: None,
+            circuit_breaker: None,
+            breaker: Arc::default(),
+            closing: Arc::new(AtomicBool::new(false)),
+            idle_timeout: None,
+            heartbeat: None,
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            worker_profiles: Vec::new(),
+            workers: Arc::new(Mutex::new(HashMap::new())),
+            result_cache_ttl: None,
+            result_cache: None,
+            chaos: None,
+            retry_backoff: Backoff::default(),
+            transport_options: TransportOptions::default(),
+            oneshot: false,
+            remote: None,
+
+            #[cfg(feature = "prometheus")]
+            prom: Arc::new(prom::PromMetrics::new()),
         }
     }
 
@@ -285,783 +1272,6747 @@ impl Client {
         self
     }
 
-    /// Close the persistent live transport process.
-    pub fn close(&self) {
-        if let Ok(mut guard) = self.transport.lock() {
-            *guard = None;
-        }
+    /// Set how child stderr output is handled.
+    pub fn with_stderr_mode(mut self, mode: StderrMode) -> Self {
+        self.stderr_mode = mode;
+        self
     }
 
-    /// Execute an mlld script string and return the output.
-    pub fn process(&self, script: &str, opts: Option<ProcessOptions>) -> Result<String> {
-        let mut handle = self.process_async(script, opts)?;
-        handle.result()
+    /// Parse the child's stderr into structured [`LogLine`]s — level,
+    /// message, request id when present — and forward each one to
+    /// `handler` in real time, instead of only surfacing stderr when
+    /// the transport dies. Shorthand for
+    /// [`StderrMode::Structured`].
+    pub fn with_stderr_handler(
+        mut self,
+        handler: impl Fn(&LogLine) + Send + Sync + 'static,
+    ) -> Self {
+        self.stderr_mode = StderrMode::Structured(Arc::new(handler));
+        self
     }
 
-    /// Start an mlld script execution and return an in-flight request handle.
-    pub fn process_async(
-        &self,
-        script: &str,
-        opts: Option<ProcessOptions>,
-    ) -> Result<ProcessHandle> {
-        let opts = opts.unwrap_or_default();
+    /// Cap the captured stderr buffer at `bytes`; the most recent lines win.
+    pub fn with_stderr_buffer_limit(mut self, bytes: usize) -> Self {
+        self.stderr_buffer_limit = bytes;
+        self
+    }
 
-        let mut params = serde_json::Map::new();
-        params.insert("script".to_string(), Value::String(script.to_string()));
+    /// Cap a single stdout protocol frame at `bytes`.
+    pub fn with_max_frame_bytes(mut self, bytes: usize) -> Self {
+        self.max_frame_bytes = bytes;
+        self
+    }
 
-        if let Some(file_path) = opts.file_path {
-            params.insert("filePath".to_string(), Value::String(file_path));
-        }
-        if let Some(payload) = opts.payload {
-            params.insert("payload".to_string(), payload);
-        }
-        if let Some(payload_labels) = normalize_label_map(opts.payload_labels) {
-            params.insert(
-                "payloadLabels".to_string(),
-                serde_json::to_value(payload_labels)?,
-            );
-        }
-        if let Some(state) = opts.state {
-            params.insert("state".to_string(), state);
-        }
-        if let Some(dynamic_modules) = opts.dynamic_modules {
-            params.insert(
-                "dynamicModules".to_string(),
-                serde_json::to_value(dynamic_modules)?,
-            );
-        }
-        if let Some(source) = opts.dynamic_module_source {
-            params.insert("dynamicModuleSource".to_string(), Value::String(source));
-        }
-        if let Some(mode) = opts.mode {
-            params.insert("mode".to_string(), Value::String(mode));
-        }
-        if let Some(allow_absolute_paths) = opts.allow_absolute_paths {
-            params.insert(
-                "allowAbsolutePaths".to_string(),
-                Value::Bool(allow_absolute_paths),
-            );
-        }
+    /// Choose how result envelopes that fail to deserialize are handled.
+    pub fn with_result_parsing(mut self, mode: ResultParsing) -> Self {
+        self.result_parsing = mode;
+        self
+    }
 
-        let timeout = opts.timeout.or(self.timeout);
-        let (request_id, receiver) = self.start_request("process", Value::Object(params))?;
+    /// Keep a warm spare child process ready to promote on failure.
+    pub fn with_warm_standby(mut self, enabled: bool) -> Self {
+        self.warm_standby = enabled;
+        self
+    }
 
-        Ok(ProcessHandle {
-            request: RequestHandle {
-                client: self.clone(),
-                request_id,
-                receiver: Some(receiver),
-                timeout,
-                cached_result: None,
-            },
-        })
+    /// Bound the post-spawn readiness probe wait.
+    pub fn with_startup_probe_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_probe_timeout = timeout;
+        self
     }
 
-    /// Run an mlld file with a payload and optional state.
-    pub fn execute<P: Serialize>(
-        &self,
-        filepath: &str,
-        payload: Option<P>,
-        opts: Option<ExecuteOptions>,
-    ) -> Result<ExecuteResult> {
-        let mut handle = self.execute_async(filepath, payload, opts)?;
-        handle.result()
+    /// Shed load before the interpreter is OOM-killed: when the live
+    /// child's resident set exceeds `limit` bytes, new requests below
+    /// [`Priority::High`] fail fast with [`Error::Overloaded`] while
+    /// high-priority ones are still admitted. Only enforced on platforms
+    /// where RSS can be read (`/proc` on Linux).
+    pub fn with_max_rss_bytes(mut self, limit: u64) -> Self {
+        self.max_rss_bytes = Some(limit);
+        self
     }
 
-    /// Start an mlld file execution and return an in-flight request handle.
-    pub fn execute_async<P: Serialize>(
-        &self,
-        filepath: &str,
-        payload: Option<P>,
-        opts: Option<ExecuteOptions>,
-    ) -> Result<ExecuteHandle> {
-        let opts = opts.unwrap_or_default();
+    /// Bound how many evaluation requests run at once. Calls beyond
+    /// the limit wait in line for a slot — bounded by
+    /// [`Client::with_queue_timeout`] — so a burst cannot overwhelm
+    /// the interpreter and time everything out together.
+    pub fn with_max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = Some(limit);
+        self
+    }
 
-        let mut params = serde_json::Map::new();
-        params.insert("filepath".to_string(), Value::String(filepath.to_string()));
+    /// Bound how long a call waits in the queue for an in-flight slot
+    /// before failing with [`Error::Timeout`].
+    pub fn with_queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = Some(timeout);
+        self
+    }
 
-        if let Some(p) = payload {
-            params.insert("payload".to_string(), serde_json::to_value(p)?);
-        }
-        if let Some(payload_labels) = normalize_label_map(opts.payload_labels) {
-            params.insert(
-                "payloadLabels".to_string(),
-                serde_json::to_value(payload_labels)?,
-            );
-        }
-        if let Some(state) = opts.state {
-            params.insert("state".to_string(), state);
-        }
-        if let Some(dynamic_modules) = opts.dynamic_modules {
-            params.insert(
-                "dynamicModules".to_string(),
-                serde_json::to_value(dynamic_modules)?,
-            );
-        }
-        if let Some(source) = opts.dynamic_module_source {
-            params.insert("dynamicModuleSource".to_string(), Value::String(source));
-        }
-        if let Some(allow_absolute_paths) = opts.allow_absolute_paths {
-            params.insert(
-                "allowAbsolutePaths".to_string(),
-                Value::Bool(allow_absolute_paths),
-            );
-        }
-        if let Some(mode) = opts.mode {
-            params.insert("mode".to_string(), Value::String(mode));
-        }
+    /// Rate-limit evaluation request starts with a token bucket so a
+    /// misbehaving upstream cannot flood the live process. Excess
+    /// calls fail with [`Error::RateLimited`], or block for a token
+    /// when the limit sets a `max_wait`.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
 
-        let timeout = opts.timeout.or(self.timeout);
-        let (request_id, receiver) = self.start_request("execute", Value::Object(params))?;
+    /// Automatically respawn the transport and retry requests that are
+    /// safe to replay — analyze, ping, and process calls marked
+    /// `pure` — when the live transport disconnects mid-request, with
+    /// exponential backoff between attempts.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
 
-        Ok(ExecuteHandle {
-            request: RequestHandle {
-                client: self.clone(),
-                request_id,
-                receiver: Some(receiver),
-                timeout,
-                cached_result: None,
-            },
-        })
+    /// Fast-fail with [`Error::CircuitOpen`] for a cool-down period
+    /// after repeated consecutive transport spawn failures, instead of
+    /// burning a spawn attempt on every call while the child keeps
+    /// crashing on startup.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
     }
 
-    /// Perform static analysis on an mlld module without executing it.
-    pub fn analyze(&self, filepath: &str) -> Result<AnalyzeResult> {
-        let (mut result, _) = self.request("analyze", json!({ "filepath": filepath }), None)?;
+    /// Shut the persistent child process down after this long without
+    /// requests, respawning it transparently on the next call. Keeps
+    /// long-lived hosts from holding a resident node process while the
+    /// SDK sits unused.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
 
-        if let Value::Object(map) = &mut result {
-            map.remove("id");
-        }
+    /// Send periodic protocol pings over the live transport. After
+    /// `missed_threshold` consecutive missed pings the transport is
+    /// declared dead: pending requests fail immediately with
+    /// [`Error::HeartbeatLost`] instead of hanging until their own
+    /// timeouts, and the child respawns on the next request.
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
 
-        let parsed: AnalyzeResult = serde_json::from_value(result)?;
-        Ok(parsed)
+    /// Register a labeled worker profile. Requests whose
+    /// `worker_labels` all match a profile's labels run on a dedicated
+    /// child process spawned with that profile's overrides, so
+    /// heavyweight scripts get appropriately provisioned workers while
+    /// unlabeled requests share the default transport.
+    pub fn with_worker_profile(mut self, profile: WorkerProfile) -> Self {
+        self.worker_profiles.push(profile);
+        self
     }
 
-    fn request(
-        &self,
-        method: &str,
-        params: Value,
-        timeout: Option<Duration>,
-    ) -> Result<(Value, Vec<StateWrite>)> {
-        let (request_id, receiver) = self.start_request(method, params)?;
-        self.await_request(request_id, receiver, timeout)
+    /// Serve repeated deterministic runs from a cache instead of
+    /// re-executing them. Results are keyed by script (or file path),
+    /// payload, state, and pinned module versions; identical inputs hit
+    /// the cache. Applies to the blocking `process_full`/`execute`
+    /// paths; opt out per request with `bypass_cache`.
+    pub fn with_result_cache(mut self, store: impl ResultCacheStore + 'static) -> Self {
+        self.result_cache = Some(Arc::new(store));
+        self
     }
 
-    fn start_request(
-        &self,
-        method: &str,
-        params: Value,
-    ) -> Result<(u64, Receiver<TransportMessage>)> {
-        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+    /// Expire cached results after `ttl`.
+    pub fn with_result_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.result_cache_ttl = Some(ttl);
+        self
+    }
 
-        let receiver = {
-            let mut guard = self
-                .transport
-                .lock()
-                .map_err(|_| Error::Transport("transport lock poisoned".to_string()))?;
+    /// Inject transport faults — delayed sends, dropped or corrupted
+    /// frames, mid-request crashes — so applications can exercise their
+    /// retry and timeout handling against realistic failures. Driven by
+    /// a seeded deterministic generator; intended for tests only.
+    pub fn with_chaos(mut self, chaos: ChaosOptions) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
 
-            let transport = self.ensure_transport_locked(&mut guard)?;
-            let receiver = transport.register_request(request_id);
-            let request = json!({
-                "method": method,
-                "id": request_id,
-                "params": params
-            });
-            transport.send_json(&request)?;
-            receiver
-        };
+    /// Replace the polling schedule used for eventually-ready
+    /// operations such as state reads and writes.
+    pub fn with_retry_backoff(mut self, backoff: Backoff) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
 
-        Ok((request_id, receiver))
+    /// Replace the wire-level transport settings, such as negotiating
+    /// length-prefixed framing for large payloads.
+    pub fn with_transport_options(mut self, options: TransportOptions) -> Self {
+        self.transport_options = options;
+        self
     }
 
-    fn await_request(
-        &self,
-        request_id: u64,
-        receiver: Receiver<TransportMessage>,
-        timeout: Option<Duration>,
-    ) -> Result<(Value, Vec<StateWrite>)> {
-        let start = Instant::now();
-        let mut state_write_events = Vec::new();
+    /// Trace the last `limit` requests in an in-memory ring buffer so
+    /// recent SDK activity can be dumped when something goes wrong in
+    /// production, without external log correlation; read it back with
+    /// [`Client::recent_requests`].
+    pub fn with_trace_limit(mut self, limit: usize) -> Self {
+        self.trace_limit = limit;
+        self
+    }
 
-        loop {
-            let message = if let Some(limit) = timeout {
-                let elapsed = start.elapsed();
-                if elapsed >= limit {
-                    self.cancel_request(request_id);
-                    self.remove_pending_request(request_id);
-                    return Err(Error::Timeout(limit));
-                }
+    /// Log orphan frames — results or events referencing a request id
+    /// this client no longer tracks, such as a late result arriving
+    /// after its request timed out — to stderr as they arrive.
+    pub fn with_orphan_logging(mut self, enabled: bool) -> Self {
+        self.log_orphan_frames = enabled;
+        self
+    }
 
-                match receiver.recv_timeout(limit - elapsed) {
-                    Ok(message) => message,
-                    Err(RecvTimeoutError::Timeout) => {
-                        self.cancel_request(request_id);
-                        self.remove_pending_request(request_id);
-                        return Err(Error::Timeout(limit));
-                    }
-                    Err(RecvTimeoutError::Disconnected) => {
-                        self.invalidate_transport();
-                        return Err(Error::Transport("live transport disconnected".to_string()));
-                    }
-                }
-            } else {
-                match receiver.recv() {
-                    Ok(message) => message,
-                    Err(_) => {
-                        self.invalidate_transport();
-                        return Err(Error::Transport("live transport disconnected".to_string()));
-                    }
-                }
-            };
+    /// Receive every orphan frame instead of losing it. State writes
+    /// that land just after a request settles arrive here, so callers
+    /// can reconcile them rather than have them silently dropped.
+    pub fn with_orphan_sink(
+        mut self,
+        callback: impl Fn(&OrphanFrame) + Send + Sync + 'static,
+    ) -> Self {
+        self.orphan_sink = Some(Arc::new(callback));
+        self
+    }
 
-            match message {
-                TransportMessage::Event(event) => {
-                    if let Some(write) = parse_state_write_event(&event) {
-                        state_write_events.push(write);
-                    }
-                }
-                TransportMessage::Result(result) => {
-                    if let Some(error_payload) = result.get("error") {
-                        return Err(error_from_payload(error_payload));
-                    }
-                    return Ok((result, state_write_events));
-                }
-                TransportMessage::Closed(message) => {
-                    self.invalidate_transport();
-                    return Err(Error::Transport(message));
-                }
-            }
-        }
+    /// Run each blocking `process`/`execute` call as a one-shot
+    /// `mlld run` subprocess instead of keeping a `live --stdio` child
+    /// alive, for environments where a persistent process is
+    /// undesirable (CI sandboxes, short-lived lambdas). Results carry
+    /// the rendered output; live-only options such as state updates,
+    /// streaming, and worker routing do not apply, and the handle-based
+    /// `*_async` APIs still use the live transport.
+    pub fn with_oneshot(mut self) -> Self {
+        self.oneshot = true;
+        self
     }
 
-    fn cancel_request(&self, request_id: u64) {
-        if let Ok(mut guard) = self.transport.lock() {
-            if let Some(transport) = guard.as_mut() {
-                let _ = transport.send_json(&json!({
-                    "method": "cancel",
-                    "id": request_id
-                }));
+    /// Connect to an already-running remote live server over TCP
+    /// instead of spawning a local child, for centralized mlld
+    /// execution services. TLS requires the `rustls` feature; a bearer
+    /// token, when set, is presented in a handshake message before any
+    /// requests.
+    pub fn with_remote(mut self, remote: RemoteOptions) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    fn cached_result(&self, key: &str) -> Option<ExecuteResult> {
+        let store = self.result_cache.as_ref()?;
+        let entry = store.get(key)?;
+
+        if let Some(ttl) = self.result_cache_ttl {
+            if entry.stored_at.elapsed().unwrap_or_default() > ttl {
+                return None;
             }
         }
+        Some(entry.result)
     }
 
-    fn update_state_request(
+    /// Create a per-request scratch directory when the options ask for
+    /// one.
+    fn provision_scratch(
         &self,
-        request_id: u64,
-        path: &str,
-        value: Value,
-        timeout: Option<Duration>,
-        labels: Option<Vec<String>>,
-    ) -> Result<()> {
-        if path.trim().is_empty() {
-            return Err(Error::Transport(
-                "state update path is required".to_string(),
-            ));
+        scratch: bool,
+        retain_on_failure: bool,
+    ) -> Result<(Option<PathBuf>, bool)> {
+        if !scratch {
+            return Ok((None, false));
         }
 
-        let labels = labels.and_then(|entries| {
-            let normalized = normalize_labels(entries);
-            if normalized.is_empty() {
-                None
-            } else {
-                Some(normalized)
-            }
-        });
-
-        let max_wait = timeout.unwrap_or(Duration::from_secs(2));
-        let deadline = Instant::now() + max_wait;
-
-        loop {
-            let mut params = serde_json::Map::new();
-            params.insert("requestId".to_string(), json!(request_id));
-            params.insert("path".to_string(), json!(path));
-            params.insert("value".to_string(), value.clone());
-            if let Some(labels) = &labels {
-                params.insert("labels".to_string(), json!(labels));
-            }
-            match self.request("state:update", Value::Object(params), timeout) {
-                Ok(_) => return Ok(()),
-                Err(Error::Mlld {
-                    code: Some(code), ..
-                }) if code == "REQUEST_NOT_FOUND" => {
-                    if Instant::now() >= deadline {
-                        return Err(Error::Mlld {
-                            message: format!("No active request for id {request_id}"),
-                            code: Some(code),
-                        });
-                    }
-                    thread::sleep(Duration::from_millis(25));
-                }
-                Err(err) => return Err(err),
-            }
-        }
+        let path = std::env::temp_dir().join(format!(
+            "mlld-scratch-{}-{}",
+            std::process::id(),
+            self.next_request_id.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&path)?;
+        Ok((Some(path), retain_on_failure))
     }
 
-    fn remove_pending_request(&self, request_id: u64) {
-        if let Ok(mut guard) = self.transport.lock() {
-            if let Some(transport) = guard.as_mut() {
-                transport.remove_request(request_id);
-            }
+    fn store_cached_result(&self, key: Option<String>, result: &ExecuteResult) {
+        if let (Some(key), Some(store)) = (key, self.result_cache.as_ref()) {
+            store.put(
+                &key,
+                CachedResult {
+                    result: result.clone(),
+                    stored_at: SystemTime::now(),
+                },
+            );
         }
     }
 
-    fn invalidate_transport(&self) {
+    /// Close the persistent live transport process.
+    pub fn close(&self) {
         if let Ok(mut guard) = self.transport.lock() {
             *guard = None;
         }
+        if let Ok(mut guard) = self.standby.lock() {
+            *guard = None;
+        }
+        if let Ok(mut guard) = self.workers.lock() {
+            guard.clear();
+        }
     }
 
-    fn ensure_transport_locked<'a>(
-        &'a self,
-        slot: &'a mut Option<LiveTransport>,
-    ) -> Result<&'a mut LiveTransport> {
-        let needs_restart = match slot.as_mut() {
-            Some(transport) => !transport.is_running()?,
-            None => true,
-        };
+    /// Shut down without orphaning work: refuse new requests, wait up
+    /// to `deadline` for in-flight requests to finish, cancel whatever
+    /// is still running, then drop the child like [`Client::close`].
+    /// Requests started during the drain fail immediately; afterwards
+    /// the client behaves as after `close` and spawns a fresh
+    /// transport on the next call.
+    pub fn close_graceful(&self, deadline: Duration) {
+        self.closing.store(true, Ordering::SeqCst);
+        let cutoff = Instant::now() + deadline;
+
+        while !self.pending_request_ids().is_empty() && Instant::now() < cutoff {
+            thread::sleep(Duration::from_millis(10));
+        }
 
-        if needs_restart {
-            *slot = Some(LiveTransport::spawn(
-                &self.command,
-                &self.command_args,
-                self.working_dir.as_deref(),
-            )?);
+        for (worker, request_id) in self.pending_request_ids() {
+            self.cancel_request_with_reason(request_id, worker, Some(&CancelReason::DeployDrain));
         }
 
-        slot.as_mut()
-            .ok_or_else(|| Error::Transport("failed to initialize transport".to_string()))
+        self.close();
+        self.closing.store(false, Ordering::SeqCst);
     }
-}
 
-#[derive(Debug)]
-enum TransportMessage {
-    Event(Value),
-    Result(Value),
-    Closed(String),
-}
+    /// In-flight request ids across the default transport and every
+    /// labeled worker, excluding reserved handshake ids.
+    fn pending_request_ids(&self) -> Vec<(Option<usize>, u64)> {
+        let mut ids = Vec::new();
+
+        if let Ok(guard) = self.transport.lock() {
+            if let Some(transport) = guard.as_ref() {
+                if let Ok(pending) = transport.pending.lock() {
+                    ids.extend(
+                        pending
+                            .keys()
+                            .copied()
+                            .filter(|id| !is_reserved_request_id(*id))
+                            .map(|id| (None, id)),
+                    );
+                }
+            }
+        }
 
-#[derive(Debug)]
-struct LiveTransport {
-    child: Child,
-    stdin: ChildStdin,
-    pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
-    stdout_thread: Option<thread::JoinHandle<()>>,
-    stderr_thread: Option<thread::JoinHandle<()>>,
-}
+        if let Ok(guard) = self.workers.lock() {
+            for (index, transport) in guard.iter() {
+                if let Ok(pending) = transport.pending.lock() {
+                    ids.extend(
+                        pending
+                            .keys()
+                            .copied()
+                            .filter(|id| !is_reserved_request_id(*id))
+                            .map(|id| (Some(*index), id)),
+                    );
+                }
+            }
+        }
 
-impl LiveTransport {
-    fn spawn(command: &str, command_args: &[String], working_dir: Option<&str>) -> Result<Self> {
-        let mut args = command_args.to_vec();
-        args.push("live".to_string());
-        args.push("--stdio".to_string());
+        ids
+    }
 
-        let mut cmd = Command::new(command);
-        cmd.args(args)
-            .stdin(Stdio::piped())
+    /// Issue a lightweight protocol ping and report round-trip latency
+    /// plus child liveness, so load balancers and readiness probes can
+    /// detect a wedged interpreter before real requests time out.
+    /// Spawns the live transport first if needed.
+    pub fn ping(&self) -> Result<Ping> {
+        let started = Instant::now();
+        self.request("ping", Value::Object(serde_json::Map::new()), self.timeout)?;
+        Ok(Ping {
+            latency: started.elapsed(),
+            process_alive: self.transport_running(),
+        })
+    }
+
+    /// Start a supervisor thread that pings the live transport every
+    /// `options.interval`. A child that is alive but unresponsive —
+    /// which `ensure_transport_locked` cannot notice, since it only
+    /// checks for a fully exited process — is killed and respawned,
+    /// and each incident is reported through `options.on_respawn`.
+    /// The monitor stops when the returned handle is dropped.
+    pub fn start_health_monitor(&self, options: HealthMonitorOptions) -> HealthMonitor {
+        let stop = Arc::new(AtomicBool::new(false));
+        let client = self.clone();
+        let stop_flag = Arc::clone(&stop);
+
+        let thread = thread::spawn(move || {
+            while !stop_flag.load(Ordering::Relaxed) {
+                let mut waited = Duration::ZERO;
+                while waited < options.interval && !stop_flag.load(Ordering::Relaxed) {
+                    let step = Duration::from_millis(50).min(options.interval - waited);
+                    thread::sleep(step);
+                    waited += step;
+                }
+                if stop_flag.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let params = Value::Object(serde_json::Map::new());
+                if client
+                    .request("ping", params, Some(options.ping_timeout))
+                    .is_ok()
+                {
+                    continue;
+                }
+
+                let issue = if client.transport_running() {
+                    HealthIssue::Hung
+                } else {
+                    HealthIssue::Exited
+                };
+
+                if let Ok(mut guard) = client.transport.lock() {
+                    *guard = None;
+                }
+                let respawned = client.ensure_live().is_ok();
+
+                if let Some(callback) = &options.on_respawn {
+                    callback(&HealthEvent { issue, respawned });
+                }
+            }
+        });
+
+        HealthMonitor {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Execute an mlld script string and return the output.
+    pub fn process(&self, script: &str, opts: Option<ProcessOptions>) -> Result<String> {
+        if self.oneshot {
+            return Ok(self.process_full(script, opts)?.output);
+        }
+        let mut handle = self.process_async(script, opts)?;
+        handle.result()
+    }
+
+    /// Execute an mlld script string and return the full structured
+    /// result. The live protocol sends the same fields for process as
+    /// for execute; `process()` keeps only the output.
+    pub fn process_full(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessResult> {
+        let opts = opts.unwrap_or_default();
+        let cache_key = if self.result_cache.is_some() && !opts.bypass_cache {
+            Some(process_cache_key(script, &opts))
+        } else {
+            None
+        };
+        if let Some(key) = &cache_key {
+            if let Some(hit) = self.cached_result(key) {
+                return Ok(hit);
+            }
+        }
+
+        let result = if self.oneshot {
+            self.oneshot_process(script, &opts)?
+        } else if opts.pure && self.retry.is_some() {
+            self.retry_transient(|| {
+                let mut handle = self.process_async(script, Some(opts.clone()))?;
+                handle.result_full()
+            })?
+        } else {
+            let mut handle = self.process_async(script, Some(opts))?;
+            handle.result_full()?
+        };
+        self.store_cached_result(cache_key, &result);
+        Ok(result)
+    }
+
+    /// Execute an mlld script in JSON output mode and deserialize the
+    /// output into `T`. Fails with [`Error::ResultParse`] when the
+    /// script emits something that is not valid JSON, instead of
+    /// leaving callers to parse the returned string themselves.
+    pub fn process_json<T: serde::de::DeserializeOwned>(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<T> {
+        let mut opts = opts.unwrap_or_default();
+        if opts.mode.is_none() {
+            opts.mode = Some("json".to_string());
+        }
+
+        let output = self.process(script, Some(opts))?;
+        let value: Value = serde_json::from_str(&output).map_err(|error| {
+            Error::ResultParse(format!(
+                "script output is not valid JSON: {error} (output: {})",
+                frame_preview(&output)
+            ))
+        })?;
+
+        deserialize_with_path::<T>(value).map_err(|error| {
+            Error::ResultParse(format!("{error} (output: {})", frame_preview(&output)))
+        })
+    }
+
+    /// Upload a script to the server's content-addressed store and
+    /// return its hash. Stored scripts can be run repeatedly with
+    /// [`process_stored`](Self::process_stored) without resending the
+    /// source, and the server can cache the parse.
+    pub fn store_script(&self, source: &str) -> Result<ScriptHash> {
+        self.store_script_request(source, false)
+    }
+
+    /// Parse once, run many: store `source` on the server and instruct
+    /// it to cache the parsed AST keyed by the script's hash, so a hot
+    /// module executed thousands of times skips both resending and
+    /// re-parsing. Run it with [`PreparedScript::run`].
+    pub fn prepare(&self, source: &str) -> Result<PreparedScript> {
+        let hash = self.store_script_request(source, true)?;
+        Ok(PreparedScript {
+            client: self.clone(),
+            hash,
+        })
+    }
+
+    /// [`prepare`](Self::prepare) for a script file on disk.
+    pub fn prepare_file(&self, filepath: &str) -> Result<PreparedScript> {
+        let source = std::fs::read_to_string(filepath)?;
+        self.prepare(&source)
+    }
+
+    fn store_script_request(&self, source: &str, cache_ast: bool) -> Result<ScriptHash> {
+        let mut params = serde_json::Map::new();
+        params.insert("source".to_string(), Value::String(source.to_string()));
+        if cache_ast {
+            params.insert("cacheAst".to_string(), Value::Bool(true));
+        }
+
+        let (result, _) = self.request("script:store", Value::Object(params), self.timeout)?;
+
+        let hash = result.get("hash").and_then(Value::as_str).ok_or_else(|| {
+            Error::ResultParse("script:store result is missing the hash field".to_string())
+        })?;
+        Ok(ScriptHash(hash.to_string()))
+    }
+
+    /// Execute a previously stored script by hash and return the output.
+    pub fn process_stored(
+        &self,
+        hash: &ScriptHash,
+        opts: Option<ProcessOptions>,
+    ) -> Result<String> {
+        let mut handle = self.process_stored_async(hash, opts)?;
+        handle.result()
+    }
+
+    /// Start executing a previously stored script and return an
+    /// in-flight request handle.
+    pub fn process_stored_async(
+        &self,
+        hash: &ScriptHash,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessHandle> {
+        let opts = opts.unwrap_or_default();
+        let timeout = opts.timeout.or(self.timeout);
+        let limits = opts.limits;
+        let worker = self.worker_for_labels(&opts.worker_labels)?;
+        let priority = opts.priority;
+        self.check_memory_pressure(priority)?;
+
+        let (scratch_dir, retain_scratch_on_failure) =
+            self.provision_scratch(opts.scratch, opts.retain_scratch_on_failure)?;
+        let mut params = build_process_params("", opts)?;
+        params.remove("script");
+        params.insert("scriptHash".to_string(), Value::String(hash.0.clone()));
+        if let Some(dir) = &scratch_dir {
+            params.insert("scratchDir".to_string(), json!(dir.to_string_lossy()));
+        }
+
+        let (request_id, receiver) =
+            self.start_request_on("process", Value::Object(params), worker, priority)?;
+
+        Ok(ProcessHandle {
+            request: RequestHandle {
+                client: self.clone(),
+                method: "process",
+                request_id,
+                receiver: Some(receiver),
+                timeout,
+                started: Instant::now(),
+                limits,
+                worker,
+                scratch_dir,
+                retain_scratch_on_failure,
+                queue_events: Vec::new(),
+                partials: Vec::new(),
+                warnings: Vec::new(),
+                on_event: None,
+                effects_sender: None,
+                state_watchers: Vec::new(),
+                cached_result: None,
+                detached: false,
+            },
+        })
+    }
+
+    /// Execute an mlld script whose payload is pulled lazily from an
+    /// iterator of records, so large datasets never materialize as one
+    /// `Value`. Records are forwarded in payload:chunk frames as the
+    /// iterator yields them — pipe backpressure throttles the producer —
+    /// and the script consumes them incrementally via `@payload.stream`.
+    pub fn process_stream<I>(
+        &self,
+        script: &str,
+        records: I,
+        opts: Option<ProcessOptions>,
+    ) -> Result<String>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let mut handle = self.process_stream_async(script, records, opts)?;
+        handle.result()
+    }
+
+    /// Start a script execution with a streamed payload and return an
+    /// in-flight request handle. The iterator is drained before this
+    /// returns.
+    pub fn process_stream_async<I>(
+        &self,
+        script: &str,
+        records: I,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessHandle>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let opts = opts.unwrap_or_default();
+        let timeout = opts.timeout.or(self.timeout);
+        let limits = opts.limits;
+        let worker = self.worker_for_labels(&opts.worker_labels)?;
+        let priority = opts.priority;
+        self.check_memory_pressure(priority)?;
+
+        let (scratch_dir, retain_scratch_on_failure) =
+            self.provision_scratch(opts.scratch, opts.retain_scratch_on_failure)?;
+        let mut params = build_process_params(script, opts)?;
+        params.insert("payloadStream".to_string(), Value::Bool(true));
+        if let Some(dir) = &scratch_dir {
+            params.insert("scratchDir".to_string(), json!(dir.to_string_lossy()));
+        }
+        let (request_id, receiver) =
+            self.start_request_on("process", Value::Object(params), worker, priority)?;
+        self.stream_payload_records(request_id, worker, records)?;
+
+        Ok(ProcessHandle {
+            request: RequestHandle {
+                client: self.clone(),
+                method: "process",
+                request_id,
+                receiver: Some(receiver),
+                timeout,
+                started: Instant::now(),
+                limits,
+                worker,
+                scratch_dir,
+                retain_scratch_on_failure,
+                queue_events: Vec::new(),
+                partials: Vec::new(),
+                warnings: Vec::new(),
+                on_event: None,
+                effects_sender: None,
+                state_watchers: Vec::new(),
+                cached_result: None,
+                detached: false,
+            },
+        })
+    }
+
+    /// Start an mlld script execution and return an in-flight request handle.
+    pub fn process_async(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessHandle> {
+        let opts = opts.unwrap_or_default();
+        let timeout = opts.timeout.or(self.timeout);
+        let limits = opts.limits;
+        let worker = self.worker_for_labels(&opts.worker_labels)?;
+        let priority = opts.priority;
+        self.check_memory_pressure(priority)?;
+        let (scratch_dir, retain_scratch_on_failure) =
+            self.provision_scratch(opts.scratch, opts.retain_scratch_on_failure)?;
+        let mut params = build_process_params(script, opts)?;
+        if let Some(dir) = &scratch_dir {
+            params.insert("scratchDir".to_string(), json!(dir.to_string_lossy()));
+        }
+        let (request_id, receiver) =
+            self.start_request_on("process", Value::Object(params), worker, priority)?;
+
+        Ok(ProcessHandle {
+            request: RequestHandle {
+                client: self.clone(),
+                method: "process",
+                request_id,
+                receiver: Some(receiver),
+                timeout,
+                started: Instant::now(),
+                limits,
+                worker,
+                scratch_dir,
+                retain_scratch_on_failure,
+                queue_events: Vec::new(),
+                partials: Vec::new(),
+                warnings: Vec::new(),
+                on_event: None,
+                effects_sender: None,
+                state_watchers: Vec::new(),
+                cached_result: None,
+                detached: false,
+            },
+        })
+    }
+
+    /// Run an mlld file with a payload and optional state.
+    pub fn execute<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<ExecuteResult> {
+        let payload = payload.map(serde_json::to_value).transpose()?;
+        let opts = opts.unwrap_or_default();
+        let cache_key = if self.result_cache.is_some() && !opts.bypass_cache {
+            Some(execute_cache_key(filepath, payload.as_ref(), &opts))
+        } else {
+            None
+        };
+        if let Some(key) = &cache_key {
+            if let Some(hit) = self.cached_result(key) {
+                return Ok(hit);
+            }
+        }
+
+        let result = if self.oneshot {
+            self.oneshot_execute(filepath, payload.as_ref(), &opts.extra_flags)?
+        } else {
+            let mut handle = self.execute_async(filepath, payload, Some(opts))?;
+            handle.result()?
+        };
+        self.store_cached_result(cache_key, &result);
+        Ok(result)
+    }
+
+    /// Run an mlld file and deserialize its exports directly into `T`.
+    /// Use [`execute`](Self::execute) plus
+    /// [`ExecuteResult::exports_as`] when the rest of the result is
+    /// also needed.
+    pub fn execute_typed<P: Serialize, T: serde::de::DeserializeOwned>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<T> {
+        self.execute(filepath, payload, opts)?.exports_as()
+    }
+
+    /// Start an mlld file execution and return an in-flight request handle.
+    pub fn execute_async<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<ExecuteHandle> {
+        let payload = payload.map(serde_json::to_value).transpose()?;
+        self.execute_async_batched(filepath, payload, opts.unwrap_or_default(), None)
+    }
+
+    /// `execute_async` with an optional batch tag, so the server can
+    /// parse the module once and reuse it across a batch's runs.
+    fn execute_async_batched(
+        &self,
+        filepath: &str,
+        payload: Option<Value>,
+        opts: ExecuteOptions,
+        batch_id: Option<&str>,
+    ) -> Result<ExecuteHandle> {
+        let timeout = opts.timeout.or(self.timeout);
+        let exports_schema = opts.exports_schema.clone();
+        let limits = opts.limits;
+        let worker = self.worker_for_labels(&opts.worker_labels)?;
+        let priority = opts.priority;
+        self.check_memory_pressure(priority)?;
+        let (scratch_dir, retain_scratch_on_failure) =
+            self.provision_scratch(opts.scratch, opts.retain_scratch_on_failure)?;
+        let mut params = build_execute_params(filepath, payload, opts)?;
+        if let Some(dir) = &scratch_dir {
+            params.insert("scratchDir".to_string(), json!(dir.to_string_lossy()));
+        }
+        if let Some(batch_id) = batch_id {
+            params.insert("batchId".to_string(), Value::String(batch_id.to_string()));
+            params.insert("reuseModule".to_string(), Value::Bool(true));
+        }
+        let (request_id, receiver) =
+            self.start_request_on("execute", Value::Object(params), worker, priority)?;
+
+        Ok(ExecuteHandle {
+            request: RequestHandle {
+                client: self.clone(),
+                method: "execute",
+                request_id,
+                receiver: Some(receiver),
+                timeout,
+                started: Instant::now(),
+                limits,
+                worker,
+                scratch_dir,
+                retain_scratch_on_failure,
+                queue_events: Vec::new(),
+                partials: Vec::new(),
+                warnings: Vec::new(),
+                on_event: None,
+                effects_sender: None,
+                state_watchers: Vec::new(),
+                cached_result: None,
+                detached: false,
+            },
+            exports_schema,
+        })
+    }
+
+    /// Run one mlld file across many payloads, reusing a single
+    /// parsed module on the server instead of re-sending and
+    /// re-parsing the file per call. Runs are dispatched through a
+    /// sliding window of `opts.concurrency` in-flight requests;
+    /// results come back in payload order and a failed run occupies
+    /// its slot without aborting the batch.
+    pub fn execute_many<P: Serialize>(
+        &self,
+        filepath: &str,
+        payloads: Vec<P>,
+        opts: Option<ExecuteManyOptions>,
+    ) -> Vec<Result<ExecuteResult>> {
+        let opts = opts.unwrap_or_default();
+        let mut results: Vec<Option<Result<ExecuteResult>>> = Vec::new();
+        results.resize_with(payloads.len(), || None);
+
+        self.run_batch(filepath, payloads, &opts, |index, result| {
+            results[index] = Some(result);
+        });
+
+        results
+            .into_iter()
+            .map(|slot| slot.expect("every batch slot settled"))
+            .collect()
+    }
+
+    /// Like [`Client::execute_many`] but delivers `(index, result)`
+    /// pairs over a channel as runs settle, so a large batch can be
+    /// consumed without holding every result in memory.
+    pub fn execute_many_stream<P: Serialize + Send + 'static>(
+        &self,
+        filepath: &str,
+        payloads: Vec<P>,
+        opts: Option<ExecuteManyOptions>,
+    ) -> Receiver<(usize, Result<ExecuteResult>)> {
+        let (sender, receiver) = mpsc::channel();
+        let client = self.clone();
+        let filepath = filepath.to_string();
+
+        thread::spawn(move || {
+            let opts = opts.unwrap_or_default();
+            client.run_batch(&filepath, payloads, &opts, |index, result| {
+                let _ = sender.send((index, result));
+            });
+        });
+
+        receiver
+    }
+
+    /// Dispatch a batch with a sliding window of in-flight runs,
+    /// handing each settled run to `deliver`.
+    fn run_batch<P: Serialize>(
+        &self,
+        filepath: &str,
+        payloads: Vec<P>,
+        opts: &ExecuteManyOptions,
+        mut deliver: impl FnMut(usize, Result<ExecuteResult>),
+    ) {
+        let window = opts.concurrency.max(1);
+        let batch_id = format!("batch-{}", jitter_seed());
+        let mut in_flight: VecDeque<(usize, ExecuteHandle)> = VecDeque::new();
+
+        for (index, payload) in payloads.into_iter().enumerate() {
+            let payload = match serde_json::to_value(payload) {
+                Ok(value) => Some(value),
+                Err(error) => {
+                    deliver(index, Err(Error::Json(error)));
+                    continue;
+                }
+            };
+
+            while in_flight.len() >= window {
+                let (done, mut handle) = in_flight.pop_front().expect("window is non-empty");
+                deliver(done, handle.result());
+            }
+
+            let started = self.execute_async_batched(
+                filepath,
+                payload,
+                opts.execute.clone(),
+                Some(&batch_id),
+            );
+            match started {
+                Ok(handle) => in_flight.push_back((index, handle)),
+                Err(error) => deliver(index, Err(error)),
+            }
+        }
+
+        while let Some((done, mut handle)) = in_flight.pop_front() {
+            deliver(done, handle.result());
+        }
+    }
+
+    /// Run an arbitrary mlld CLI subcommand (`setup`, `registry`, `clean`,
+    /// ...) as a plain subprocess and return its exit status and output.
+    /// When stdout parses as JSON it is also surfaced as `json`. Honors the
+    /// client's command, working directory, and default timeout.
+    pub fn cli<I, S>(&self, args: I) -> Result<CliOutput>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut full_args = self.command_args.to_vec();
+        full_args.extend(args.into_iter().map(Into::into));
+
+        let mut cmd = Command::new(&self.command);
+        cmd.args(&full_args)
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        if let Some(dir) = working_dir {
+        if let Some(dir) = &self.working_dir {
             cmd.current_dir(dir);
         }
 
-        let mut child = cmd.spawn()?;
+        let mut child = cmd
+            .spawn()
+            .map_err(|error| spawn_error(&self.command, &full_args, error))?;
 
-        let stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| Error::Transport("live transport stdin is unavailable".to_string()))?;
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| Error::Transport("live transport stdout is unavailable".to_string()))?;
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| Error::Transport("live transport stderr is unavailable".to_string()))?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let stdout_thread = thread::spawn(move || read_to_string_lossy(stdout));
+        let stderr_thread = thread::spawn(move || read_to_string_lossy(stderr));
 
-        let pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>> =
-            Arc::new(Mutex::new(HashMap::new()));
-        let stderr_buffer = Arc::new(Mutex::new(String::new()));
+        let deadline = self.timeout.map(|limit| Instant::now() + limit);
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Error::Timeout(Box::new(TimeoutInfo::bare(
+                        self.timeout.unwrap_or_default(),
+                    ))));
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
 
-        let stderr_thread = Some(start_stderr_thread(stderr, Arc::clone(&stderr_buffer)));
-        let stdout_thread = Some(start_stdout_thread(
-            stdout,
-            Arc::clone(&pending),
-            Arc::clone(&stderr_buffer),
-        ));
+        let stdout = stdout_thread.join().unwrap_or_default();
+        let stderr = stderr_thread.join().unwrap_or_default();
+        let json = serde_json::from_str::<Value>(stdout.trim()).ok();
 
-        Ok(Self {
-            child,
-            stdin,
-            pending,
-            stdout_thread,
-            stderr_thread,
+        Ok(CliOutput {
+            status: status.code(),
+            success: status.success(),
+            stdout,
+            stderr,
+            json,
         })
     }
 
-    fn register_request(&mut self, request_id: u64) -> Receiver<TransportMessage> {
-        let (sender, receiver) = mpsc::channel();
-        if let Ok(mut pending) = self.pending.lock() {
-            pending.insert(request_id, sender);
+    /// Spawn an mlld language server proxy for diagnostics and completions.
+    pub fn lsp(&self) -> Result<lsp::Lsp> {
+        lsp::Lsp::spawn(self)
+    }
+
+    /// Build a pool of live transports that dispatches process/execute
+    /// calls across several child processes; see [`pool::Pool`].
+    pub fn pool(&self, options: pool::PoolOptions) -> Result<pool::Pool> {
+        pool::Pool::new(self.clone(), options)
+    }
+
+    /// Open a sticky session: every request it issues carries one
+    /// server-side session id and runs on the same worker, so
+    /// interpreter state and warmed module caches persist across
+    /// calls — the shape multi-turn agent conversations need.
+    pub fn session(&self) -> Session {
+        Session {
+            client: self.clone(),
+            id: format!("session-{}", jitter_seed()),
+            worker_labels: Vec::new(),
+        }
+    }
+
+    /// [`session`](Self::session) pinned to the dedicated worker
+    /// profile serving `labels`.
+    pub fn session_for_labels<I, S>(&self, labels: I) -> Result<Session>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let labels: Vec<String> = labels.into_iter().map(Into::into).collect();
+        self.worker_for_labels(&labels)?;
+        Ok(Session {
+            client: self.clone(),
+            id: format!("session-{}", jitter_seed()),
+            worker_labels: labels,
+        })
+    }
+
+    /// One-shot `mlld run` for a script string: the source is written to
+    /// a temporary file since the run subcommand takes a file path.
+    fn oneshot_process(&self, script: &str, opts: &ProcessOptions) -> Result<ExecuteResult> {
+        let path = std::env::temp_dir().join(format!(
+            "mlld-oneshot-{}-{}.mld",
+            std::process::id(),
+            self.next_request_id.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::write(&path, script)?;
+
+        let result = self.oneshot_execute(
+            &path.to_string_lossy(),
+            opts.payload.as_ref(),
+            &opts.extra_flags,
+        );
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// Run `mlld run <file>` as a plain subprocess and shape its output
+    /// like an execute result.
+    fn oneshot_execute(
+        &self,
+        filepath: &str,
+        payload: Option<&Value>,
+        extra_flags: &[String],
+    ) -> Result<ExecuteResult> {
+        let mut args: Vec<String> = vec!["run".to_string(), filepath.to_string()];
+        if let Some(payload) = payload {
+            args.push("--payload".to_string());
+            args.push(payload.to_string());
+        }
+        args.extend(extra_flags.iter().cloned());
+
+        let output = self.cli(args)?;
+        if !output.success {
+            let stderr = output.stderr.trim();
+            return Err(Error::Mlld {
+                message: if stderr.is_empty() {
+                    format!(
+                        "mlld run exited with status {}",
+                        output.status.unwrap_or(-1)
+                    )
+                } else {
+                    stderr.to_string()
+                },
+                code: None,
+                diagnostic: None,
+            });
+        }
+
+        Ok(ExecuteResult {
+            output: output.stdout,
+            ..Default::default()
+        })
+    }
+
+    /// Perform static analysis on an mlld module without executing it.
+    pub fn analyze(&self, filepath: &str) -> Result<AnalyzeResult> {
+        let (result, _) = self.request("analyze", json!({ "filepath": filepath }), None)?;
+        parse_analyze_result(result)
+    }
+
+    /// Report which syntax and features used by `source` are
+    /// unsupported by mlld CLI `version`, combining analyze data with a
+    /// feature matrix, so user-submitted scripts can be validated
+    /// before rolling them out to fleets running older CLIs.
+    pub fn check_compat(&self, source: &str, version: &str) -> Result<CompatReport> {
+        let target = SemVersion::parse(version)
+            .ok_or_else(|| Error::Transport(format!("invalid target version: {version}")))?;
+
+        let path = std::env::temp_dir().join(format!(
+            "mlld-compat-{}-{}.mld",
+            std::process::id(),
+            self.next_request_id.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::write(&path, source)?;
+        let analysis = self.analyze(&path.to_string_lossy());
+        let _ = std::fs::remove_file(&path);
+
+        Ok(CompatReport {
+            target_version: version.to_string(),
+            issues: compat_issues(source, &analysis?, &target),
+        })
+    }
+
+    /// Validate a project, then run its entry file: analyze the entry and
+    /// every local import reachable from it, preview import resolution
+    /// against the lockfile, and execute only when everything is valid —
+    /// a one-call deploy-and-run for project-shaped workloads.
+    pub fn execute_project<P: Serialize>(
+        &self,
+        root: &str,
+        entry: &str,
+        payload: Option<P>,
+    ) -> Result<ProjectRun> {
+        let entry_path = Path::new(root).join(entry);
+        let entry_display = entry_path.to_string_lossy().to_string();
+
+        let mut analyses = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut queue: Vec<PathBuf> = vec![entry_path];
+
+        while let Some(path) = queue.pop() {
+            let display = path.to_string_lossy().to_string();
+            if !seen.insert(display.clone()) {
+                continue;
+            }
+
+            let analysis = self.analyze(&display)?;
+            for import in &analysis.imports {
+                if import.from.starts_with('.') {
+                    if let Some(dir) = path.parent() {
+                        queue.push(dir.join(&import.from));
+                    }
+                }
+            }
+            analyses.push(analysis);
+        }
+
+        let imports = self.resolve_imports(&entry_display)?;
+
+        if analyses.iter().any(|analysis| !analysis.valid) {
+            return Ok(ProjectRun {
+                analyses,
+                imports,
+                result: None,
+            });
+        }
+
+        let result = self.execute(&entry_display, payload, None)?;
+        Ok(ProjectRun {
+            analyses,
+            imports,
+            result: Some(result),
+        })
+    }
+
+    /// Resume a suspended execution from a snapshot taken with
+    /// `suspend()`, continuing where it left off.
+    pub fn resume(&self, snapshot: &ExecutionSnapshot) -> Result<ExecuteResult> {
+        let mut handle = self.resume_async(snapshot)?;
+        handle.result()
+    }
+
+    /// Start resuming a suspended execution and return an in-flight
+    /// request handle.
+    pub fn resume_async(&self, snapshot: &ExecutionSnapshot) -> Result<ExecuteHandle> {
+        let mut params = serde_json::Map::new();
+        params.insert("snapshot".to_string(), serde_json::to_value(snapshot)?);
+
+        let (request_id, receiver) = self.start_request("resume", Value::Object(params))?;
+
+        Ok(ExecuteHandle {
+            request: RequestHandle {
+                client: self.clone(),
+                method: "resume",
+                request_id,
+                receiver: Some(receiver),
+                timeout: self.timeout,
+                started: Instant::now(),
+                limits: None,
+                worker: None,
+                scratch_dir: None,
+                retain_scratch_on_failure: false,
+                queue_events: Vec::new(),
+                partials: Vec::new(),
+                warnings: Vec::new(),
+                on_event: None,
+                effects_sender: None,
+                state_watchers: Vec::new(),
+                cached_result: None,
+                detached: false,
+            },
+            exports_schema: None,
+        })
+    }
+
+    /// Re-run a previous execution from its provenance manifest, pinning
+    /// exactly the recorded script content hash and module versions. The
+    /// server verifies the hashes and errors if any pinned content is no
+    /// longer available, enabling bit-for-bit reproduction of past runs.
+    pub fn replay<P: Serialize>(
+        &self,
+        filepath: &str,
+        manifest: &Provenance,
+        payload: Option<P>,
+    ) -> Result<ExecuteResult> {
+        let mut params = serde_json::Map::new();
+        params.insert("filepath".to_string(), Value::String(filepath.to_string()));
+        params.insert("replay".to_string(), serde_json::to_value(manifest)?);
+
+        if let Some(p) = payload {
+            params.insert("payload".to_string(), serde_json::to_value(p)?);
+        }
+
+        let (request_id, receiver) = self.start_request("execute", Value::Object(params))?;
+        let mut handle = ExecuteHandle {
+            request: RequestHandle {
+                client: self.clone(),
+                method: "execute",
+                request_id,
+                receiver: Some(receiver),
+                timeout: self.timeout,
+                started: Instant::now(),
+                limits: None,
+                worker: None,
+                scratch_dir: None,
+                retain_scratch_on_failure: false,
+                queue_events: Vec::new(),
+                partials: Vec::new(),
+                warnings: Vec::new(),
+                on_event: None,
+                effects_sender: None,
+                state_watchers: Vec::new(),
+                cached_result: None,
+                detached: false,
+            },
+            exports_schema: None,
+        };
+        handle.result()
+    }
+
+    /// Preview how each import in a module resolves (resolver used,
+    /// target path/URL/version, cache state) without executing it, so
+    /// deploy tooling can pre-warm and verify imports ahead of traffic.
+    pub fn resolve_imports(&self, filepath: &str) -> Result<ImportResolutionReport> {
+        let (mut result, _) =
+            self.request("imports:resolve", json!({ "filepath": filepath }), None)?;
+
+        if let Value::Object(map) = &mut result {
+            map.remove("id");
+        }
+
+        let parsed =
+            deserialize_with_path::<ImportResolutionReport>(result.clone()).map_err(|error| {
+                Error::ResultParse(format!(
+                    "{error} (result: {})",
+                    frame_preview(&result.to_string())
+                ))
+            })?;
+        Ok(parsed)
+    }
+
+    /// Send a raw live-protocol frame and stream every reply for it —
+    /// events first, then the final result — as untyped JSON. An escape
+    /// hatch for protocol methods this SDK has not wrapped yet; the
+    /// channel closes after the result arrives or the transport shuts
+    /// down.
+    pub fn send_raw(&self, method: &str, params: Value) -> Result<Receiver<Value>> {
+        let (request_id, transport_receiver) = self.start_request(method, params)?;
+        let (sender, receiver) = mpsc::channel();
+
+        let client = self.clone();
+        let method = method.to_string();
+        thread::spawn(move || {
+            loop {
+                match transport_receiver.recv() {
+                    Ok(TransportMessage::Event(event)) => {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(TransportMessage::Result(result)) => {
+                        client.finish_trace(request_id, true, &result.to_string());
+                        let _ = sender.send(result);
+                        break;
+                    }
+                    Ok(TransportMessage::Closed(message)) => {
+                        client.finish_trace(request_id, false, &message);
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+            client.release_request_slot(&method);
+            client.remove_pending_request(request_id, None);
+            // Raw requests never reach record_latency, so settle the
+            // in-flight gauge here instead.
+            #[cfg(feature = "prometheus")]
+            client.prom.in_flight.dec();
+        });
+
+        Ok(receiver)
+    }
+
+    /// Point this client at an already-running `mlld live --stdio`
+    /// process — one started by a supervisor, say — via its stdio
+    /// handles or socket, instead of spawning and owning a child. The
+    /// usual startup negotiation (framing, compression, hello,
+    /// readiness probe) runs against the attached server; the server
+    /// process itself is never killed by the SDK. Replaces any
+    /// transport this client previously held.
+    pub fn attach(&self, transport: ExistingTransport) -> Result<()> {
+        let live = LiveTransport::adopt(self, transport.reader, transport.writer, None)?;
+        let mut guard = self
+            .transport
+            .lock()
+            .map_err(|_| Error::Transport("transport lock poisoned".to_string()))?;
+        *guard = Some(live);
+        Ok(())
+    }
+
+    /// Protocol version and supported methods the live server reported
+    /// in the startup handshake, spawning the transport first if
+    /// needed. `None` when the server predates the hello method.
+    pub fn server_info(&self) -> Result<Option<ServerInfo>> {
+        let mut guard = self
+            .transport
+            .lock()
+            .map_err(|_| Error::Transport("transport lock poisoned".to_string()))?;
+        let transport = self.ensure_transport_locked(&mut guard)?;
+        Ok(transport.server_info.clone())
+    }
+
+    /// Subscribe to every event frame arriving on live transports this
+    /// client spawns, as untyped JSON and regardless of which request
+    /// the event belongs to. Complements [`Client::send_raw`] for
+    /// server-initiated notifications the SDK has no typed surface for.
+    /// Dropping the receiver unsubscribes.
+    pub fn subscribe_raw(&self) -> Receiver<Value> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut subscribers) = self.raw_subscribers.lock() {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+    ) -> Result<(Value, Vec<StateWrite>)> {
+        self.request_on(method, params, timeout, None)
+    }
+
+    fn request_on(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+    ) -> Result<(Value, Vec<StateWrite>)> {
+        if self.retry.is_some() && is_idempotent_method(method) {
+            return self.retry_transient(|| {
+                self.request_attempt_on(method, params.clone(), timeout, worker)
+            });
+        }
+        self.request_attempt_on(method, params, timeout, worker)
+    }
+
+    fn request_attempt_on(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+    ) -> Result<(Value, Vec<StateWrite>)> {
+        let started = Instant::now();
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "mlld_request",
+            method,
+            request_id = tracing::field::Empty,
+            outcome = tracing::field::Empty
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let (request_id, receiver) =
+            self.start_request_on(method, params, worker, Priority::Normal)?;
+        #[cfg(feature = "tracing")]
+        span.record("request_id", request_id);
+        let outcome = self.await_request(
+            request_id,
+            receiver,
+            timeout,
+            worker,
+            RequestObservers {
+                queue_events: &mut Vec::new(),
+                warnings: &mut Vec::new(),
+                partials: &mut Vec::new(),
+                observer: None,
+                effects: None,
+                state_watchers: &[],
+            },
+        );
+        self.record_latency(method, started.elapsed(), outcome.is_ok());
+        #[cfg(feature = "tracing")]
+        span.record("outcome", if outcome.is_ok() { "ok" } else { "error" });
+        match &outcome {
+            Ok((result, _)) => self.finish_trace(request_id, true, &result.to_string()),
+            Err(error) => self.finish_trace(request_id, false, &error.to_string()),
+        }
+        self.release_request_slot(method);
+        outcome
+    }
+
+    fn start_request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<(u64, Receiver<TransportMessage>)> {
+        self.start_request_on(method, params, None, Priority::Normal)
+    }
+
+    fn start_request_on(
+        &self,
+        method: &str,
+        params: Value,
+        worker: Option<usize>,
+        priority: Priority,
+    ) -> Result<(u64, Receiver<TransportMessage>)> {
+        if self.closing.load(Ordering::SeqCst) {
+            return Err(Error::Transport("client is closing".to_string()));
+        }
+        self.check_rate_limit(method)?;
+        self.acquire_request_slot(method, priority)?;
+        match self.send_request_on(method, params, worker) {
+            Ok(started) => Ok(started),
+            Err(error) => {
+                self.release_request_slot(method);
+                Err(error)
+            }
+        }
+    }
+
+    fn send_request_on(
+        &self,
+        method: &str,
+        params: Value,
+        worker: Option<usize>,
+    ) -> Result<(u64, Receiver<TransportMessage>)> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut last) = self.last_activity.lock() {
+            *last = Instant::now();
+        }
+        if self.trace_limit > 0 {
+            if let Ok(mut pending) = self.trace_pending.lock() {
+                pending.insert(
+                    request_id,
+                    PendingTrace {
+                        method: method.to_string(),
+                        params: frame_preview(&params.to_string()),
+                        started: Instant::now(),
+                        started_at: SystemTime::now(),
+                    },
+                );
+            }
+        }
+        let request = json!({
+            "method": method,
+            "id": request_id,
+            "params": params
+        });
+
+        let receiver = match worker {
+            None => {
+                let mut guard = self
+                    .transport
+                    .lock()
+                    .map_err(|_| Error::Transport("transport lock poisoned".to_string()))?;
+
+                let transport = self.ensure_transport_locked(&mut guard)?;
+                if !transport.supports(method) {
+                    return Err(transport.unsupported_method(method));
+                }
+                let receiver = transport.register_request(request_id);
+                transport.send_json(&request)?;
+                receiver
+            }
+            Some(index) => {
+                let mut guard = self
+                    .workers
+                    .lock()
+                    .map_err(|_| Error::Transport("worker transport lock poisoned".to_string()))?;
+
+                let needs_spawn = match guard.get_mut(&index) {
+                    Some(transport) => !transport.is_running()?,
+                    None => true,
+                };
+                if needs_spawn {
+                    let transport = LiveTransport::spawn(&self.profile_client(index)?)?;
+                    guard.insert(index, transport);
+                }
+
+                let transport = guard
+                    .get_mut(&index)
+                    .expect("worker transport just ensured");
+                if !transport.supports(method) {
+                    return Err(transport.unsupported_method(method));
+                }
+                let receiver = transport.register_request(request_id);
+                transport.send_json(&request)?;
+                receiver
+            }
+        };
+
+        #[cfg(feature = "prometheus")]
+        self.prom.in_flight.inc();
+
+        Ok((request_id, receiver))
+    }
+
+    /// First worker profile serving every requested label, or `None` for
+    /// an unlabeled request.
+    fn worker_for_labels(&self, labels: &[String]) -> Result<Option<usize>> {
+        if labels.is_empty() {
+            return Ok(None);
+        }
+
+        let matched = self
+            .worker_profiles
+            .iter()
+            .position(|profile| labels.iter().all(|label| profile.labels.contains(label)));
+
+        match matched {
+            Some(index) => Ok(Some(index)),
+            None => Err(Error::Transport(format!(
+                "no worker profile matches labels: {}",
+                labels.join(", ")
+            ))),
+        }
+    }
+
+    /// Reject a request below [`Priority::High`] when the live child's
+    /// resident set exceeds the configured limit.
+    fn check_memory_pressure(&self, priority: Priority) -> Result<()> {
+        let Some(limit_bytes) = self.max_rss_bytes else {
+            return Ok(());
+        };
+        if priority >= Priority::High {
+            return Ok(());
+        }
+
+        let pid = self
+            .transport
+            .lock()
+            .ok()
+            .and_then(|guard| guard.as_ref().and_then(LiveTransport::pid));
+        let Some(rss_bytes) = pid.and_then(process_rss_bytes) else {
+            return Ok(());
+        };
+
+        if rss_bytes > limit_bytes {
+            return Err(Error::Overloaded {
+                rss_bytes,
+                limit_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Client configuration for spawning a worker's child process, with
+    /// the profile's overrides applied.
+    fn profile_client(&self, index: usize) -> Result<Client> {
+        let profile = self
+            .worker_profiles
+            .get(index)
+            .ok_or_else(|| Error::Transport(format!("worker profile {index} does not exist")))?;
+
+        let mut derived = self.clone();
+        derived.warm_standby = false;
+        if let Some(command) = &profile.command {
+            derived.command = command.clone();
+        }
+        if let Some(args) = &profile.command_args {
+            derived.command_args = args.clone();
+        }
+        if let Some(dir) = &profile.working_dir {
+            derived.working_dir = Some(dir.clone());
+        }
+        Ok(derived)
+    }
+
+    /// A configuration clone with fresh transport slots, so each pool
+    /// worker drives its own child process instead of sharing this
+    /// client's.
+    pub(crate) fn detached(&self) -> Client {
+        let mut derived = self.clone();
+        derived.transport = Arc::new(Mutex::new(None));
+        derived.standby = Arc::new(Mutex::new(None));
+        derived.workers = Arc::new(Mutex::new(HashMap::new()));
+        derived.breaker = Arc::default();
+        derived
+    }
+
+    /// Spawn the live transport now instead of on the first request, so
+    /// pool workers can be warmed ahead of traffic.
+    pub(crate) fn ensure_live(&self) -> Result<()> {
+        let mut guard = self
+            .transport
+            .lock()
+            .map_err(|_| Error::Transport("transport lock poisoned".to_string()))?;
+        self.ensure_transport_locked(&mut guard)?;
+        Ok(())
+    }
+
+    /// Whether the live child process is currently running; `false`
+    /// before the first spawn or after the child exits.
+    pub(crate) fn transport_running(&self) -> bool {
+        self.transport
+            .lock()
+            .ok()
+            .and_then(|mut guard| {
+                guard
+                    .as_mut()
+                    .map(|transport| transport.is_running().unwrap_or(false))
+            })
+            .unwrap_or(false)
+    }
+
+    fn await_request(
+        &self,
+        request_id: u64,
+        receiver: Receiver<TransportMessage>,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+        observers: RequestObservers<'_>,
+    ) -> Result<(Value, Vec<StateWrite>)> {
+        let RequestObservers {
+            queue_events,
+            warnings,
+            partials,
+            observer,
+            effects,
+            state_watchers,
+        } = observers;
+        let start = Instant::now();
+        let mut state_write_events = Vec::new();
+
+        loop {
+            let message = if let Some(limit) = timeout {
+                let elapsed = start.elapsed();
+                if elapsed >= limit {
+                    self.cancel_request_with_reason(
+                        request_id,
+                        worker,
+                        Some(&CancelReason::TimeoutEscalation),
+                    );
+                    self.remove_pending_request(request_id, worker);
+                    return Err(Error::Timeout(Box::new(TimeoutInfo {
+                        phase: TimeoutPhase::Execution,
+                        limit,
+                        state_writes: state_write_events,
+                        partials: partials.clone(),
+                    })));
+                }
+
+                match receiver.recv_timeout(limit - elapsed) {
+                    Ok(message) => message,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.cancel_request_with_reason(
+                            request_id,
+                            worker,
+                            Some(&CancelReason::TimeoutEscalation),
+                        );
+                        self.remove_pending_request(request_id, worker);
+                        return Err(Error::Timeout(Box::new(TimeoutInfo {
+                            phase: TimeoutPhase::Execution,
+                            limit,
+                            state_writes: state_write_events,
+                            partials: partials.clone(),
+                        })));
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        self.invalidate_worker(worker);
+                        return Err(Error::Transport("live transport disconnected".to_string()));
+                    }
+                }
+            } else {
+                match receiver.recv() {
+                    Ok(message) => message,
+                    Err(_) => {
+                        self.invalidate_worker(worker);
+                        return Err(Error::Transport("live transport disconnected".to_string()));
+                    }
+                }
+            };
+
+            match message {
+                TransportMessage::Event(event) => {
+                    if let Some(write) = parse_state_write_event(&event) {
+                        for (pattern, callback) in state_watchers {
+                            if state_path_matches(pattern, &write.path) {
+                                callback(&write);
+                            }
+                        }
+                        state_write_events.push(write);
+                    }
+                    if let Some(queue_event) = parse_queue_event(&event) {
+                        queue_events.push(queue_event);
+                    }
+                    if let Some(warning) = parse_warning_event(&event) {
+                        warnings.push(warning);
+                    }
+                    if let Some(partial) = parse_partial_result_event(&event) {
+                        partials.push(partial);
+                    }
+                    if let Some(sender) = effects {
+                        if let Some(effect) = parse_effect_event(&event) {
+                            let _ = sender.send(effect);
+                        }
+                    }
+                    if let Some(observer) = observer {
+                        observer(&LiveEvent::from_payload(event));
+                    }
+                }
+                TransportMessage::Result(result) => {
+                    if let Some(error_payload) = result.get("error") {
+                        return Err(error_from_payload(error_payload));
+                    }
+                    return Ok((result, state_write_events));
+                }
+                TransportMessage::Closed(message) => {
+                    self.invalidate_worker(worker);
+                    return Err(closed_error(message));
+                }
+            }
+        }
+    }
+
+    /// Snapshot per-method latency statistics gathered by this client.
+    pub fn stats(&self) -> ClientStats {
+        let methods = self
+            .latency
+            .lock()
+            .map(|windows| {
+                let mut methods = windows
+                    .iter()
+                    .map(|(method, window)| window.summarize(method))
+                    .collect::<Vec<_>>();
+                methods.sort_by(|a, b| a.method.cmp(&b.method));
+                methods
+            })
+            .unwrap_or_default();
+
+        ClientStats { methods }
+    }
+
+    /// Aggregate snapshot of client health: request counters, queue
+    /// depth, transport respawns, orphan frames, and the per-method
+    /// latency percentiles from [`Client::stats`]. `in_flight` and
+    /// `queued` count evaluation requests holding or waiting for a
+    /// concurrency slot. With the `prometheus` feature the same
+    /// numbers are also exported through the prometheus registry.
+    pub fn metrics(&self) -> ClientMetrics {
+        let stats = self.stats();
+        let (requests, failures) = stats
+            .methods
+            .iter()
+            .fold((0, 0), |(requests, failures), method| {
+                (requests + method.count, failures + method.errors)
+            });
+        let (in_flight, queued) = self.request_gate.depth();
+
+        ClientMetrics {
+            requests,
+            failures,
+            in_flight,
+            queued,
+            transport_spawns: self.transport_spawns.load(Ordering::Relaxed),
+            orphan_frames: self.orphan_frame_count(),
+            methods: stats.methods,
+        }
+    }
+
+    /// Registry holding this client's Prometheus collectors.
+    #[cfg(feature = "prometheus")]
+    pub fn prometheus_registry(&self) -> &prometheus::Registry {
+        &self.prom.registry
+    }
+
+    /// The last requests this client sent, oldest first. Empty unless
+    /// tracing is enabled with [`Client::with_trace_limit`].
+    pub fn recent_requests(&self) -> Vec<RequestTrace> {
+        self.traces
+            .lock()
+            .map(|traces| traces.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Total frames observed that referenced an unknown request id.
+    pub fn orphan_frame_count(&self) -> u64 {
+        self.orphan_frames.load(Ordering::Relaxed)
+    }
+
+    /// Take an in-flight slot when concurrency limiting applies to
+    /// `method`, waiting in the queue when the client is at its limit.
+    /// Queued high-priority work is admitted before normal work, and
+    /// background work goes last.
+    fn acquire_request_slot(&self, method: &str, priority: Priority) -> Result<()> {
+        let Some(limit) = self.max_concurrent_requests else {
+            return Ok(());
+        };
+        if !counts_against_concurrency(method) {
+            return Ok(());
+        }
+        self.request_gate
+            .acquire(limit, priority, self.queue_timeout)
+    }
+
+    /// Take a rate-limiter token when a limit is configured and
+    /// `method` starts an evaluation.
+    /// Fail fast while the circuit breaker's cool-down window is
+    /// running.
+    fn check_circuit(&self) -> Result<()> {
+        if self.circuit_breaker.is_none() {
+            return Ok(());
+        }
+        if let Ok(state) = self.breaker.lock() {
+            if let Some(open_until) = state.open_until {
+                let now = Instant::now();
+                if now < open_until {
+                    return Err(Error::CircuitOpen {
+                        retry_after: open_until - now,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn record_spawn_failure(&self) {
+        let Some(breaker) = self.circuit_breaker else {
+            return;
+        };
+        if let Ok(mut state) = self.breaker.lock() {
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= breaker.failure_threshold.max(1) {
+                state.open_until = Some(Instant::now() + breaker.cooldown);
+            }
+        }
+    }
+
+    fn record_spawn_success(&self) {
+        if self.circuit_breaker.is_none() {
+            return;
+        }
+        if let Ok(mut state) = self.breaker.lock() {
+            state.consecutive_failures = 0;
+            state.open_until = None;
+        }
+    }
+
+    /// Run `attempt` under the configured retry policy, dropping the
+    /// dead transport and backing off between tries when the live
+    /// transport disconnects mid-request. Only used for requests that
+    /// are safe to replay.
+    fn retry_transient<T>(&self, attempt: impl Fn() -> Result<T>) -> Result<T> {
+        let Some(policy) = self.retry else {
+            return attempt();
+        };
+
+        let mut tries = 0u32;
+        loop {
+            tries += 1;
+            match attempt() {
+                Err(error)
+                    if tries < policy.max_attempts.max(1) && is_transport_disconnect(&error) =>
+                {
+                    if let Ok(mut guard) = self.transport.lock() {
+                        *guard = None;
+                    }
+                    thread::sleep(policy.backoff(tries));
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    fn check_rate_limit(&self, method: &str) -> Result<()> {
+        let Some(limit) = &self.rate_limit else {
+            return Ok(());
+        };
+        if !counts_against_concurrency(method) {
+            return Ok(());
+        }
+        self.rate_bucket.take(limit)
+    }
+
+    /// Return an in-flight slot once a request settles.
+    fn release_request_slot(&self, method: &str) {
+        if self.max_concurrent_requests.is_some() && counts_against_concurrency(method) {
+            self.request_gate.release();
+        }
+    }
+
+    /// Bundle this client's orphan-frame policy for the reader thread.
+    fn orphan_observer(&self) -> OrphanObserver {
+        OrphanObserver {
+            log: self.log_orphan_frames,
+            counter: Arc::clone(&self.orphan_frames),
+            sink: self.orphan_sink.clone(),
+        }
+    }
+
+    /// Move a pending trace into the ring buffer now that its request
+    /// has settled.
+    fn finish_trace(&self, request_id: u64, success: bool, outcome: &str) {
+        if self.trace_limit == 0 {
+            return;
+        }
+        let Some(pending) = self
+            .trace_pending
+            .lock()
+            .ok()
+            .and_then(|mut map| map.remove(&request_id))
+        else {
+            return;
+        };
+
+        if let Ok(mut traces) = self.traces.lock() {
+            traces.push_back(RequestTrace {
+                method: pending.method,
+                params: pending.params,
+                started_at: pending.started_at,
+                duration: pending.started.elapsed(),
+                success,
+                outcome: frame_preview(outcome),
+            });
+            while traces.len() > self.trace_limit {
+                traces.pop_front();
+            }
+        }
+    }
+
+    fn record_latency(&self, method: &str, elapsed: Duration, ok: bool) {
+        if let Ok(mut windows) = self.latency.lock() {
+            windows
+                .entry(method.to_string())
+                .or_default()
+                .record(elapsed, ok);
+        }
+
+        #[cfg(feature = "prometheus")]
+        self.prom.observe_request(method, elapsed.as_secs_f64(), ok);
+    }
+
+    fn cancel_request(&self, request_id: u64, worker: Option<usize>) {
+        self.cancel_request_with_reason(request_id, worker, None);
+    }
+
+    /// Whether the reader still tracks `request_id` on the given
+    /// transport, i.e. no result frame has settled it yet.
+    fn request_is_pending(&self, request_id: u64, worker: Option<usize>) -> bool {
+        let contains = |transport: &LiveTransport| {
+            transport
+                .pending
+                .lock()
+                .map(|pending| pending.contains_key(&request_id))
+                .unwrap_or(false)
+        };
+
+        match worker {
+            None => self
+                .transport
+                .lock()
+                .ok()
+                .and_then(|guard| guard.as_ref().map(contains))
+                .unwrap_or(false),
+            Some(index) => self
+                .workers
+                .lock()
+                .ok()
+                .and_then(|guard| guard.get(&index).map(contains))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Kill the child behind the given transport and clear its slot;
+    /// the next request spawns a fresh one.
+    fn kill_transport(&self, worker: Option<usize>) {
+        match worker {
+            None => {
+                if let Ok(mut guard) = self.transport.lock() {
+                    *guard = None;
+                }
+            }
+            Some(index) => {
+                if let Ok(mut guard) = self.workers.lock() {
+                    guard.remove(&index);
+                }
+            }
+        }
+    }
+
+    fn cancel_request_with_reason(
+        &self,
+        request_id: u64,
+        worker: Option<usize>,
+        reason: Option<&CancelReason>,
+    ) {
+        let mut params = serde_json::Map::new();
+        if let Some(reason) = reason {
+            params.insert("reason".to_string(), json!(reason.as_str()));
+        }
+        let payload = json!({
+            "method": "cancel",
+            "id": request_id,
+            "params": Value::Object(params)
+        });
+
+        match worker {
+            None => {
+                if let Ok(mut guard) = self.transport.lock() {
+                    if let Some(transport) = guard.as_mut() {
+                        let _ = transport.send_json(&payload);
+                    }
+                }
+            }
+            Some(index) => {
+                if let Ok(mut guard) = self.workers.lock() {
+                    if let Some(transport) = guard.get_mut(&index) {
+                        let _ = transport.send_json(&payload);
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_state_request(
+        &self,
+        request_id: u64,
+        update: StateUpdate<'_>,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+    ) -> Result<()> {
+        if update.path.trim().is_empty() {
+            return Err(Error::Transport(
+                "state update path is required".to_string(),
+            ));
+        }
+
+        let labels = update.labels.and_then(|entries| {
+            let normalized = normalize_labels(entries);
+            if normalized.is_empty() {
+                None
+            } else {
+                Some(normalized)
+            }
+        });
+
+        let max_wait = timeout.unwrap_or(Duration::from_secs(2));
+        let result = self
+            .retry_backoff
+            .retry(max_wait, is_request_not_found, || {
+                let mut params = serde_json::Map::new();
+                params.insert("requestId".to_string(), json!(request_id));
+                params.insert("path".to_string(), json!(update.path));
+                params.insert("value".to_string(), update.value.clone());
+                if update.op != StateOp::Set {
+                    params.insert("op".to_string(), json!(update.op.as_str()));
+                }
+                if let Some(labels) = &labels {
+                    params.insert("labels".to_string(), json!(labels));
+                }
+                self.request_on("state:update", Value::Object(params), timeout, worker)
+            });
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(missing_request_error(error, request_id)),
+        }
+    }
+
+    fn update_states_request(
+        &self,
+        request_id: u64,
+        updates: Vec<(String, Value, StateOp)>,
+        atomic: bool,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+    ) -> Result<()> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        if updates.iter().any(|(path, _, _)| path.trim().is_empty()) {
+            return Err(Error::Transport(
+                "state update path is required".to_string(),
+            ));
+        }
+
+        let batch = state_update_entries(&updates);
+
+        let max_wait = timeout.unwrap_or(Duration::from_secs(2));
+        let result = self
+            .retry_backoff
+            .retry(max_wait, is_request_not_found, || {
+                let mut params = serde_json::Map::new();
+                params.insert("requestId".to_string(), json!(request_id));
+                params.insert("updates".to_string(), json!(batch));
+                if atomic {
+                    params.insert("atomic".to_string(), json!(true));
+                }
+                self.request_on("state:update", Value::Object(params), timeout, worker)
+            });
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(missing_request_error(error, request_id)),
+        }
+    }
+
+    /// Drain `records` into payload:chunk frames for an in-flight
+    /// request, then mark the stream complete with payload:end.
+    fn stream_payload_records<I>(
+        &self,
+        request_id: u64,
+        worker: Option<usize>,
+        records: I,
+    ) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let mut batch: Vec<Value> = Vec::with_capacity(PAYLOAD_STREAM_BATCH);
+        for record in records {
+            batch.push(serde_json::to_value(record)?);
+            if batch.len() >= PAYLOAD_STREAM_BATCH {
+                let records =
+                    std::mem::replace(&mut batch, Vec::with_capacity(PAYLOAD_STREAM_BATCH));
+                self.notify_request(
+                    "payload:chunk",
+                    request_id,
+                    json!({ "records": records }),
+                    worker,
+                )?;
+            }
+        }
+        if !batch.is_empty() {
+            self.notify_request(
+                "payload:chunk",
+                request_id,
+                json!({ "records": batch }),
+                worker,
+            )?;
+        }
+        self.notify_request("payload:end", request_id, json!({}), worker)
+    }
+
+    /// Send a fire-and-forget frame referencing an in-flight request,
+    /// without registering for a reply.
+    fn notify_request(
+        &self,
+        method: &str,
+        request_id: u64,
+        params: Value,
+        worker: Option<usize>,
+    ) -> Result<()> {
+        let payload = json!({
+            "method": method,
+            "id": request_id,
+            "params": params
+        });
+
+        match worker {
+            None => {
+                let mut guard = self
+                    .transport
+                    .lock()
+                    .map_err(|_| Error::Transport("transport lock poisoned".to_string()))?;
+                let transport = guard
+                    .as_mut()
+                    .ok_or_else(|| Error::Transport("live transport is not running".to_string()))?;
+                transport.send_json(&payload)
+            }
+            Some(index) => {
+                let mut guard = self
+                    .workers
+                    .lock()
+                    .map_err(|_| Error::Transport("worker transport lock poisoned".to_string()))?;
+                let transport = guard.get_mut(&index).ok_or_else(|| {
+                    Error::Transport("worker transport is not running".to_string())
+                })?;
+                transport.send_json(&payload)
+            }
+        }
+    }
+
+    fn read_state_request(
+        &self,
+        request_id: u64,
+        path: &str,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+    ) -> Result<Value> {
+        if path.trim().is_empty() {
+            return Err(Error::Transport("state read path is required".to_string()));
+        }
+
+        let max_wait = timeout.unwrap_or(Duration::from_secs(2));
+        let result = self
+            .retry_backoff
+            .retry(max_wait, is_request_not_found, || {
+                let mut params = serde_json::Map::new();
+                params.insert("requestId".to_string(), json!(request_id));
+                params.insert("path".to_string(), json!(path));
+                self.request_on("state:get", Value::Object(params), timeout, worker)
+            });
+
+        match result {
+            Ok((result, _)) => Ok(result.get("value").cloned().unwrap_or(Value::Null)),
+            Err(error) => Err(missing_request_error(error, request_id)),
+        }
+    }
+
+    fn loop_interval_request(
+        &self,
+        request_id: u64,
+        interval: Duration,
+        timeout: Option<Duration>,
+        worker: Option<usize>,
+    ) -> Result<()> {
+        let max_wait = timeout.unwrap_or(Duration::from_secs(2));
+        let result = self
+            .retry_backoff
+            .retry(max_wait, is_request_not_found, || {
+                let mut params = serde_json::Map::new();
+                params.insert("requestId".to_string(), json!(request_id));
+                params.insert("intervalMs".to_string(), json!(interval.as_millis() as u64));
+                self.request_on("loop:interval", Value::Object(params), timeout, worker)
+            });
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(missing_request_error(error, request_id)),
+        }
+    }
+
+    fn remove_pending_request(&self, request_id: u64, worker: Option<usize>) {
+        match worker {
+            None => {
+                if let Ok(mut guard) = self.transport.lock() {
+                    if let Some(transport) = guard.as_mut() {
+                        transport.remove_request(request_id);
+                    }
+                }
+            }
+            Some(index) => {
+                if let Ok(mut guard) = self.workers.lock() {
+                    if let Some(transport) = guard.get_mut(&index) {
+                        transport.remove_request(request_id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take the standby transport when it is still alive.
+    fn take_standby(&self) -> Option<LiveTransport> {
+        let mut guard = self.standby.lock().ok()?;
+        let mut standby = guard.take()?;
+        match standby.is_running() {
+            Ok(true) => Some(standby),
+            _ => None,
+        }
+    }
+
+    /// Provision a fresh standby child without blocking the caller.
+    fn spawn_standby_in_background(&self) {
+        let client = self.clone();
+        thread::spawn(move || {
+            if let Ok(transport) = LiveTransport::spawn(&client) {
+                if let Ok(mut guard) = client.standby.lock() {
+                    if guard.is_none() {
+                        *guard = Some(transport);
+                    }
+                }
+            }
+        });
+    }
+
+    fn invalidate_transport(&self) {
+        if let Ok(mut guard) = self.transport.lock() {
+            *guard = None;
+        }
+    }
+
+    fn invalidate_worker(&self, worker: Option<usize>) {
+        match worker {
+            None => self.invalidate_transport(),
+            Some(index) => {
+                if let Ok(mut guard) = self.workers.lock() {
+                    guard.remove(&index);
+                }
+            }
+        }
+    }
+
+    /// Ping the default transport on the heartbeat schedule. After
+    /// enough consecutive misses the pending map is drained — failing
+    /// every receiver with [`Error::HeartbeatLost`] right away — and
+    /// the transport is dropped so the next request respawns it. The
+    /// thread exits once the transport it watches is gone or replaced.
+    fn spawn_heartbeat(&self, pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>) {
+        let Some(heartbeat) = self.heartbeat else {
+            return;
+        };
+
+        let client = self.clone();
+        thread::spawn(move || {
+            let mut missed = 0u32;
+            loop {
+                thread::sleep(heartbeat.interval);
+
+                {
+                    let Ok(guard) = client.transport.lock() else {
+                        return;
+                    };
+                    let Some(transport) = guard.as_ref() else {
+                        return;
+                    };
+                    if !Arc::ptr_eq(&transport.pending, &pending) {
+                        return;
+                    }
+                }
+
+                let params = Value::Object(serde_json::Map::new());
+                if client
+                    .request("ping", params, Some(heartbeat.timeout))
+                    .is_ok()
+                {
+                    missed = 0;
+                    continue;
+                }
+
+                missed += 1;
+                if missed < heartbeat.missed_threshold.max(1) {
+                    continue;
+                }
+
+                notify_all_pending(&pending, HEARTBEAT_LOST_MESSAGE.to_string());
+                if let Ok(mut guard) = client.transport.lock() {
+                    let ours = guard
+                        .as_ref()
+                        .is_some_and(|transport| Arc::ptr_eq(&transport.pending, &pending));
+                    if ours {
+                        *guard = None;
+                    }
+                }
+                return;
+            }
+        });
+    }
+
+    /// Watch the default transport and drop it — killing the child —
+    /// once it has gone [`Client::idle_timeout`] without requests. The
+    /// freshly spawned transport is identified by its pending map, so
+    /// the reaper exits as soon as the transport is replaced or was
+    /// not the default one to begin with. Respawn happens transparently
+    /// on the next request.
+    fn spawn_idle_reaper(&self, pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>) {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return;
+        };
+
+        let slot = Arc::clone(&self.transport);
+        let last_activity = Arc::clone(&self.last_activity);
+        let poll = (idle_timeout / 4).clamp(Duration::from_millis(50), Duration::from_secs(1));
+
+        thread::spawn(move || loop {
+            thread::sleep(poll);
+
+            let Ok(mut guard) = slot.lock() else {
+                return;
+            };
+            let Some(transport) = guard.as_ref() else {
+                return;
+            };
+            if !Arc::ptr_eq(&transport.pending, &pending) {
+                return;
+            }
+
+            let busy = pending.lock().map(|map| !map.is_empty()).unwrap_or(true);
+            if busy {
+                if let Ok(mut last) = last_activity.lock() {
+                    *last = Instant::now();
+                }
+                continue;
+            }
+
+            let idle_for = last_activity
+                .lock()
+                .map(|last| last.elapsed())
+                .unwrap_or(Duration::ZERO);
+            if idle_for >= idle_timeout {
+                *guard = None;
+                return;
+            }
+        });
+    }
+
+    fn ensure_transport_locked<'a>(
+        &'a self,
+        slot: &'a mut Option<LiveTransport>,
+    ) -> Result<&'a mut LiveTransport> {
+        let needs_restart = match slot.as_mut() {
+            Some(transport) => !transport.is_running()?,
+            None => true,
+        };
+
+        if needs_restart {
+            self.check_circuit()?;
+            let promoted = if self.warm_standby {
+                self.take_standby()
+            } else {
+                None
+            };
+
+            *slot = Some(match promoted {
+                Some(standby) => standby,
+                None => match LiveTransport::spawn(self) {
+                    Ok(transport) => transport,
+                    Err(error) => {
+                        self.record_spawn_failure();
+                        return Err(error);
+                    }
+                },
+            });
+            self.record_spawn_success();
+
+            if self.transport_spawns.fetch_add(1, Ordering::Relaxed) > 0 {
+                #[cfg(feature = "prometheus")]
+                self.prom.transport_restarts.inc();
+            }
+
+            if self.warm_standby {
+                self.spawn_standby_in_background();
+            }
+
+            if let Some(transport) = slot.as_ref() {
+                self.spawn_idle_reaper(Arc::clone(&transport.pending));
+                self.spawn_heartbeat(Arc::clone(&transport.pending));
+            }
+        }
+
+        slot.as_mut()
+            .ok_or_else(|| Error::Transport("failed to initialize transport".to_string()))
+    }
+}
+
+/// Default cap on captured stderr retained for error reporting.
+#[cfg(feature = "client")]
+const DEFAULT_STDERR_BUFFER_LIMIT: usize = 64 * 1024;
+
+/// Default cap on a single stdout protocol frame.
+#[cfg(feature = "client")]
+const DEFAULT_MAX_FRAME_BYTES: usize = 64 * 1024 * 1024;
+
+/// Records per payload:chunk frame when streaming a payload.
+#[cfg(feature = "client")]
+const PAYLOAD_STREAM_BATCH: usize = 256;
+
+/// Whether `method` starts a new evaluation and therefore takes an
+/// in-flight slot. Auxiliary control traffic issued while a request
+/// is running (state access, loop pacing, snapshots) bypasses the
+/// gate so a full queue cannot deadlock the request it belongs to.
+#[cfg(feature = "client")]
+fn counts_against_concurrency(method: &str) -> bool {
+    matches!(method, "process" | "execute" | "resume")
+}
+
+/// Counting gate bounding concurrent evaluation requests. Waiters
+/// queue in priority lanes: when a slot frees, high-priority waiters
+/// are admitted before normal ones and background work goes last.
+#[cfg(feature = "client")]
+#[derive(Clone, Default)]
+struct ConcurrencyGate {
+    inner: Arc<(Mutex<GateState>, Condvar)>,
+}
+
+#[cfg(feature = "client")]
+#[derive(Default)]
+struct GateState {
+    held: usize,
+    /// Waiters per lane, indexed by `Priority as usize`.
+    waiting: [usize; 3],
+}
+
+#[cfg(feature = "client")]
+impl GateState {
+    /// Whether a waiter at `priority` may take a slot now: one must be
+    /// free and no higher-priority lane may have waiters.
+    fn admissible(&self, limit: usize, priority: Priority) -> bool {
+        self.held < limit.max(1)
+            && self.waiting[priority as usize + 1..]
+                .iter()
+                .all(|&count| count == 0)
+    }
+}
+
+#[cfg(feature = "client")]
+impl ConcurrencyGate {
+    /// Take a slot, waiting up to `queue_timeout` for one to free.
+    fn acquire(
+        &self,
+        limit: usize,
+        priority: Priority,
+        queue_timeout: Option<Duration>,
+    ) -> Result<()> {
+        let (state, freed) = &*self.inner;
+        let deadline = queue_timeout.map(|timeout| Instant::now() + timeout);
+        let mut guard = state
+            .lock()
+            .map_err(|_| Error::Transport("request queue lock poisoned".to_string()))?;
+
+        if guard.admissible(limit, priority) {
+            guard.held += 1;
+            return Ok(());
+        }
+
+        guard.waiting[priority as usize] += 1;
+        loop {
+            guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        guard.waiting[priority as usize] -= 1;
+                        return Err(Error::Timeout(Box::new(TimeoutInfo::queued(
+                            queue_timeout.expect("deadline implies queue timeout"),
+                        ))));
+                    }
+                    freed
+                        .wait_timeout(guard, deadline - now)
+                        .map_err(|_| Error::Transport("request queue lock poisoned".to_string()))?
+                        .0
+                }
+                None => freed
+                    .wait(guard)
+                    .map_err(|_| Error::Transport("request queue lock poisoned".to_string()))?,
+            };
+
+            if guard.admissible(limit, priority) {
+                guard.waiting[priority as usize] -= 1;
+                guard.held += 1;
+                return Ok(());
+            }
+        }
+    }
+
+    /// Return a slot and wake every waiter so the highest-priority
+    /// lane claims it.
+    fn release(&self) {
+        let (state, freed) = &*self.inner;
+        if let Ok(mut guard) = state.lock() {
+            guard.held = guard.held.saturating_sub(1);
+            freed.notify_all();
+        }
+    }
+
+    /// Slots held and callers waiting, for metrics snapshots.
+    fn depth(&self) -> (u64, u64) {
+        let (state, _) = &*self.inner;
+        state
+            .lock()
+            .map(|guard| {
+                (
+                    guard.held as u64,
+                    guard.waiting.iter().sum::<usize>() as u64,
+                )
+            })
+            .unwrap_or((0, 0))
+    }
+}
+
+/// Token-bucket rate limit on evaluation request starts.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    /// Sustained request starts allowed per second.
+    pub requests_per_second: f64,
+
+    /// Requests that may start back-to-back before the sustained rate
+    /// applies.
+    pub burst: u32,
+
+    /// How long a call may block waiting for a token before failing
+    /// with [`Error::RateLimited`]; `None` fails immediately when the
+    /// bucket is empty.
+    pub max_wait: Option<Duration>,
+}
+
+/// Backoff schedule for retrying replay-safe requests after a
+/// transport disconnect; see [`Client::with_retry`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total tries per request, counting the first.
+    pub max_attempts: u32,
+
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+
+    /// Factor applied to the delay after each failed attempt.
+    pub backoff_multiplier: f64,
+
+    /// Ceiling on the delay between attempts.
+    pub max_backoff: Duration,
+}
+
+#[cfg(feature = "client")]
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl RetryPolicy {
+    /// Delay before the retry following failed attempt `attempt`
+    /// (1-based).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_multiplier
+            .max(1.0)
+            .powi(attempt.saturating_sub(1) as i32);
+        let delay = Duration::from_secs_f64(self.initial_backoff.as_secs_f64() * factor);
+        delay.min(self.max_backoff)
+    }
+}
+
+/// Whether a request method can be replayed without repeating side
+/// effects; process retries are opted into per call instead via
+/// `ProcessOptions::pure`.
+#[cfg(feature = "client")]
+fn is_idempotent_method(method: &str) -> bool {
+    matches!(method, "analyze" | "ping")
+}
+
+#[cfg(feature = "client")]
+fn is_transport_disconnect(error: &Error) -> bool {
+    matches!(error, Error::Transport(message) if message == "live transport disconnected")
+}
+
+/// Thresholds for fast-failing while the child keeps crashing on
+/// startup; see [`Client::with_circuit_breaker`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreaker {
+    /// Consecutive spawn failures that open the circuit.
+    pub failure_threshold: u32,
+
+    /// How long spawns fast-fail with [`Error::CircuitOpen`] once the
+    /// circuit opens.
+    pub cooldown: Duration,
+}
+
+#[cfg(feature = "client")]
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Keepalive schedule for the live transport; see
+/// [`Client::with_heartbeat`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat {
+    /// Time between keepalive pings.
+    pub interval: Duration,
+
+    /// Wait per ping before it counts as missed.
+    pub timeout: Duration,
+
+    /// Consecutive missed pings that declare the transport dead.
+    pub missed_threshold: u32,
+}
+
+#[cfg(feature = "client")]
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(2),
+            missed_threshold: 3,
+        }
+    }
+}
+
+/// Mutable side of the circuit breaker. A success closes the circuit;
+/// once open, a single failure after the cool-down re-opens it.
+#[cfg(feature = "client")]
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Token bucket backing [`RateLimit`]; refilled lazily on each take.
+#[cfg(feature = "client")]
+#[derive(Clone, Default)]
+struct TokenBucket {
+    state: Arc<Mutex<Option<BucketState>>>,
+}
+
+#[cfg(feature = "client")]
+struct BucketState {
+    tokens: f64,
+    refilled: Instant,
+}
+
+#[cfg(feature = "client")]
+impl TokenBucket {
+    /// Take a token, blocking up to `limit.max_wait` for a refill.
+    fn take(&self, limit: &RateLimit) -> Result<()> {
+        let deadline = limit.max_wait.map(|wait| Instant::now() + wait);
+
+        loop {
+            let retry_after = {
+                let mut guard = self
+                    .state
+                    .lock()
+                    .map_err(|_| Error::Transport("rate limiter lock poisoned".to_string()))?;
+                let now = Instant::now();
+                let capacity = f64::from(limit.burst).max(1.0);
+                let state = guard.get_or_insert_with(|| BucketState {
+                    tokens: capacity,
+                    refilled: now,
+                });
+
+                let rate = limit.requests_per_second.max(f64::MIN_POSITIVE);
+                let refill = now.duration_since(state.refilled).as_secs_f64() * rate;
+                state.tokens = (state.tokens + refill).min(capacity);
+                state.refilled = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return Ok(());
+                }
+                Duration::from_secs_f64((1.0 - state.tokens) / rate)
+            };
+
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now + retry_after > deadline {
+                        return Err(Error::RateLimited { retry_after });
+                    }
+                    thread::sleep(retry_after);
+                }
+                None => return Err(Error::RateLimited { retry_after }),
+            }
+        }
+    }
+}
+
+/// Bounded line buffer that keeps the most recent stderr output.
+#[derive(Debug)]
+#[cfg(feature = "client")]
+struct StderrRing {
+    lines: VecDeque<String>,
+    bytes: usize,
+    limit: usize,
+}
+
+#[cfg(feature = "client")]
+impl StderrRing {
+    fn new(limit: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            bytes: 0,
+            limit,
+        }
+    }
+
+    fn push_line(&mut self, line: String) {
+        self.bytes += line.len();
+        self.lines.push_back(line);
+
+        while self.bytes > self.limit && self.lines.len() > 1 {
+            if let Some(evicted) = self.lines.pop_front() {
+                self.bytes -= evicted.len();
+            }
+        }
+    }
+
+    fn contents(&self) -> String {
+        self.lines.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Number of recent samples retained per method for percentile estimates.
+#[cfg(feature = "client")]
+const LATENCY_WINDOW_SIZE: usize = 512;
+
+/// Sliding window of recent request durations for one protocol method.
+#[derive(Debug, Default)]
+#[cfg(feature = "client")]
+struct LatencyWindow {
+    samples: Vec<Duration>,
+    next: usize,
+    count: u64,
+    errors: u64,
+}
+
+#[cfg(feature = "client")]
+impl LatencyWindow {
+    fn record(&mut self, elapsed: Duration, ok: bool) {
+        self.count += 1;
+        if !ok {
+            self.errors += 1;
+        }
+
+        if self.samples.len() < LATENCY_WINDOW_SIZE {
+            self.samples.push(elapsed);
+        } else {
+            self.samples[self.next] = elapsed;
+            self.next = (self.next + 1) % LATENCY_WINDOW_SIZE;
+        }
+    }
+
+    fn summarize(&self, method: &str) -> MethodStats {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+
+        MethodStats {
+            method: method.to_string(),
+            count: self.count,
+            errors: self.errors,
+            p50: percentile(&sorted, 50),
+            p95: percentile(&sorted, 95),
+            p99: percentile(&sorted, 99),
+        }
+    }
+}
+
+/// Nearest-rank percentile over an ascending sample slice.
+#[cfg(feature = "client")]
+fn percentile(sorted: &[Duration], pct: u32) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (pct as usize * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Latency statistics for one protocol method.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct MethodStats {
+    pub method: String,
+
+    /// Total requests recorded, including those outside the sample window.
+    pub count: u64,
+
+    /// Requests that completed with an error.
+    pub errors: u64,
+
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+/// Snapshot of client-side latency statistics, sorted by method name.
+#[derive(Debug, Clone, Default)]
+#[cfg(feature = "client")]
+pub struct ClientStats {
+    pub methods: Vec<MethodStats>,
+}
+
+/// Aggregate client health counters; see [`Client::metrics`].
+#[derive(Debug, Clone, Default)]
+#[cfg(feature = "client")]
+pub struct ClientMetrics {
+    /// Requests that have completed, across all methods.
+    pub requests: u64,
+
+    /// Completed requests that ended in an error.
+    pub failures: u64,
+
+    /// Evaluation requests currently holding a concurrency slot.
+    pub in_flight: u64,
+
+    /// Evaluation requests waiting in the admission queue.
+    pub queued: u64,
+
+    /// Times a live transport was spawned, including respawns.
+    pub transport_spawns: u64,
+
+    /// Frames that referenced a request id this client no longer
+    /// tracks.
+    pub orphan_frames: u64,
+
+    /// Per-method latency percentiles, sorted by method name.
+    pub methods: Vec<MethodStats>,
+}
+
+/// Per-request observation sinks threaded into the await loop.
+#[cfg(feature = "client")]
+struct RequestObservers<'a> {
+    queue_events: &'a mut Vec<QueueEvent>,
+    warnings: &'a mut Vec<Warning>,
+    partials: &'a mut Vec<Value>,
+    observer: Option<&'a EventCallback>,
+    effects: Option<&'a Sender<Effect>>,
+    state_watchers: &'a [StateWatcher],
+}
+
+#[derive(Debug)]
+#[cfg(feature = "client")]
+enum TransportMessage {
+    Event(Value),
+    Result(Value),
+    Closed(String),
+}
+
+/// What the live server reported about itself in the startup
+/// handshake; see [`Client::server_info`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    /// Live protocol version the server speaks.
+    #[serde(default)]
+    pub protocol_version: Option<String>,
+
+    /// Version of the mlld CLI backing the server.
+    #[serde(default)]
+    pub cli_version: Option<String>,
+
+    /// Protocol methods the server supports; empty when the server does
+    /// not advertise them, in which case every method is attempted.
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+/// One settled request recorded by the client-side tracing ring
+/// buffer; see [`Client::with_trace_limit`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct RequestTrace {
+    /// Protocol method, e.g. `process` or `state:update`.
+    pub method: String,
+
+    /// Request params as JSON, truncated past the frame preview limit.
+    pub params: String,
+
+    /// When the request was sent.
+    pub started_at: SystemTime,
+
+    /// Wall-clock time until the request settled.
+    pub duration: Duration,
+
+    /// Whether the request produced a result rather than an error.
+    pub success: bool,
+
+    /// Result JSON or error message, truncated past the frame preview
+    /// limit.
+    pub outcome: String,
+}
+
+/// In-flight half of a [`RequestTrace`], keyed by request id until the
+/// request settles.
+#[cfg(feature = "client")]
+#[derive(Debug)]
+struct PendingTrace {
+    method: String,
+    params: String,
+    started: Instant,
+    started_at: SystemTime,
+}
+
+/// Polling schedule with exponential growth and jitter, used wherever
+/// the SDK waits for something that becomes ready shortly after it is
+/// requested — most notably state operations against a request that is
+/// still starting. Configure per client with [`Client::with_retry_backoff`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay before the first retry.
+    pub initial_interval: Duration,
+
+    /// Growth factor applied to the delay after each attempt; values
+    /// below 1.0 are treated as 1.0.
+    pub multiplier: f64,
+
+    /// Upper bound on the delay between attempts.
+    pub max_interval: Duration,
+
+    /// Random jitter as a fraction of each delay, spreading out retries
+    /// when many clients poll the same server. 0.0 disables it.
+    pub jitter: f64,
+}
+
+#[cfg(feature = "client")]
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(25),
+            multiplier: 2.0,
+            max_interval: Duration::from_millis(250),
+            jitter: 0.1,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl Backoff {
+    /// Retry `operation` until it succeeds, fails with an error that
+    /// `retryable` rejects, or `max_wait` elapses. The last error is
+    /// returned when the deadline passes.
+    pub fn retry<T>(
+        &self,
+        max_wait: Duration,
+        mut retryable: impl FnMut(&Error) -> bool,
+        mut operation: impl FnMut() -> Result<T>,
+    ) -> Result<T> {
+        let deadline = Instant::now() + max_wait;
+        let mut intervals = self.intervals();
+
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(error) if retryable(&error) && Instant::now() < deadline => {
+                    thread::sleep(intervals.next().unwrap_or(self.max_interval));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Endless sequence of sleep intervals following this schedule, for
+    /// polling loops that are not driven by a retryable error.
+    pub fn intervals(&self) -> BackoffIntervals {
+        BackoffIntervals {
+            backoff: self.clone(),
+            next_interval: self.initial_interval.min(self.max_interval),
+            jitter_state: jitter_seed(),
+        }
+    }
+
+    fn jittered(&self, interval: Duration, state: &mut u64) -> Duration {
+        if self.jitter <= 0.0 {
+            return interval;
+        }
+
+        // xorshift64*; quality is irrelevant here, spread is the point.
+        let mut x = *state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *state = x;
+
+        let unit = (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64;
+        let spread = self.jitter.min(1.0) * (2.0 * unit - 1.0);
+        interval.mul_f64((1.0 + spread).max(0.0))
+    }
+}
+
+/// Iterator over the sleep intervals of a [`Backoff`] schedule; never
+/// ends, so cap iterations with a deadline.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct BackoffIntervals {
+    backoff: Backoff,
+    next_interval: Duration,
+    jitter_state: u64,
+}
+
+#[cfg(feature = "client")]
+impl Iterator for BackoffIntervals {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let interval = self.next_interval;
+        let grown = interval.mul_f64(self.backoff.multiplier.max(1.0));
+        self.next_interval = grown.min(self.backoff.max_interval);
+        Some(self.backoff.jittered(interval, &mut self.jitter_state))
+    }
+}
+
+#[cfg(feature = "client")]
+fn jitter_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos() as u64)
+        .unwrap_or(0);
+    nanos | 1
+}
+
+/// Wire framing for live protocol frames; see [`TransportOptions`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// One JSON envelope per newline-terminated line — the protocol
+    /// default, readable in logs and understood by every CLI.
+    #[default]
+    NewlineJson,
+
+    /// Each JSON envelope preceded by its byte length as a big-endian
+    /// u32. Immune to embedded newlines and cheaper to scan for large
+    /// payloads. Negotiated at startup; the transport stays on newline
+    /// framing when the server rejects or ignores the offer.
+    LengthPrefixedJson,
+}
+
+/// Payload compression for live protocol frames; see
+/// [`TransportOptions`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Frames travel as plain JSON.
+    #[default]
+    None,
+
+    /// Frames are gzip-compressed on the wire. Requires the
+    /// `compression` feature and length-prefixed framing, since gzip
+    /// output cannot be newline-delimited. Negotiated at startup; the
+    /// transport stays uncompressed when the server declines.
+    Gzip,
+}
+
+/// Low-level wire settings for the live transport, for
+/// [`Client::with_transport_options`].
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Default)]
+pub struct TransportOptions {
+    /// Wire framing to negotiate with the server.
+    pub framing: Framing,
+
+    /// Frame compression to negotiate with the server; multi-megabyte
+    /// scripts and document payloads dominate transport latency
+    /// otherwise.
+    pub compression: Compression,
+}
+
+/// How to reach a remote live server, for [`Client::with_remote`].
+#[derive(Debug, Clone, Default)]
+pub struct RemoteOptions {
+    /// Endpoint as `host:port`.
+    pub addr: String,
+
+    /// Bearer token presented to the endpoint in an auth handshake
+    /// before any requests; `None` skips authentication.
+    pub token: Option<String>,
+
+    /// Wrap the connection in TLS. Requires the `rustls` feature;
+    /// certificate validation uses the platform's web PKI roots.
+    pub tls: bool,
+
+    /// Server name for TLS certificate validation; defaults to the
+    /// host part of `addr`.
+    pub tls_server_name: Option<String>,
+}
+
+/// Reader and writer halves of an already-running live server, for
+/// [`Client::attach`] — the server's stdio handles when a supervisor
+/// spawned it, or a connected socket.
+#[cfg(feature = "client")]
+pub struct ExistingTransport {
+    reader: Box<dyn std::io::Read + Send>,
+    writer: Box<dyn Write + Send>,
+}
+
+#[cfg(feature = "client")]
+impl ExistingTransport {
+    /// Wrap a stream the server sends frames on and one it reads frames
+    /// from.
+    pub fn new(
+        reader: impl std::io::Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Self {
+        Self {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Wrap a connected TCP socket to a live server.
+    pub fn from_tcp(stream: std::net::TcpStream) -> std::io::Result<Self> {
+        let reader = stream.try_clone()?;
+        Ok(Self {
+            reader: Box::new(reader),
+            writer: Box::new(stream),
+        })
+    }
+}
+
+/// Fault injection knobs for the live transport, enabled with
+/// [`Client::with_chaos`]. Probabilities are in `[0, 1]` and are rolled
+/// independently per frame or request by a seeded deterministic
+/// generator, so failing runs replay exactly. For tests only.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct ChaosOptions {
+    /// Probability that an incoming frame is silently dropped.
+    pub drop_frame: f64,
+
+    /// Probability that an incoming frame is mangled into a malformed
+    /// envelope before parsing.
+    pub corrupt_frame: f64,
+
+    /// Probability, rolled per outgoing frame, that the child process
+    /// is killed mid-request.
+    pub crash: f64,
+
+    /// Fixed delay added before every outgoing frame.
+    pub send_delay: Option<Duration>,
+
+    /// Seed for the deterministic generator.
+    pub seed: u64,
+}
+
+#[cfg(feature = "client")]
+impl Default for ChaosOptions {
+    fn default() -> Self {
+        Self {
+            drop_frame: 0.0,
+            corrupt_frame: 0.0,
+            crash: 0.0,
+            send_delay: None,
+            seed: 0x6d6c_6c64,
+        }
+    }
+}
+
+/// Seeded xorshift generator driving chaos probabilities; shared
+/// between the send path and the stdout reader thread.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+struct ChaosRng {
+    opts: ChaosOptions,
+    state: Arc<Mutex<u64>>,
+}
+
+#[cfg(feature = "client")]
+impl ChaosRng {
+    fn new(opts: ChaosOptions) -> Self {
+        let state = Arc::new(Mutex::new(opts.seed | 1));
+        Self { opts, state }
+    }
+
+    fn should(&self, probability: f64) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+        if probability >= 1.0 {
+            return true;
+        }
+        self.roll() < probability
+    }
+
+    /// Uniform draw in `[0, 1)` from xorshift64*.
+    fn roll(&self) -> f64 {
+        let Ok(mut state) = self.state.lock() else {
+            return 1.0;
+        };
+        let mut x = *state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *state = x;
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(feature = "client")]
+struct LiveTransport {
+    child: Option<Child>,
+    writer: Box<dyn Write + Send>,
+    pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
+    stderr_buffer: Arc<Mutex<StderrRing>>,
+    stdout_thread: Option<thread::JoinHandle<()>>,
+    stderr_thread: Option<thread::JoinHandle<()>>,
+    chaos: Option<ChaosRng>,
+    framing: Framing,
+    compression: Compression,
+    server_info: Option<ServerInfo>,
+}
+
+/// Reserved request id for the hello handshake that collects
+/// [`ServerInfo`].
+#[cfg(feature = "client")]
+const HELLO_REQUEST_ID: u64 = u64::MAX - 4;
+
+/// Reserved request id for the framing negotiation handshake, known to
+/// both the sender and the reader thread so the reader can switch
+/// decoding as soon as the server accepts the offer.
+#[cfg(feature = "client")]
+const FRAMING_REQUEST_ID: u64 = u64::MAX - 2;
+
+/// Reserved request id for the compression negotiation handshake.
+#[cfg(feature = "client")]
+const COMPRESSION_REQUEST_ID: u64 = u64::MAX - 3;
+
+#[cfg(feature = "client")]
+impl LiveTransport {
+    fn spawn(client: &Client) -> Result<Self> {
+        if let Some(remote) = &client.remote {
+            return Self::connect(client, remote);
+        }
+        let mut args = client.command_args.to_vec();
+        args.push("live".to_string());
+        args.push("--stdio".to_string());
+        let args_for_error = args.clone();
+
+        let mut cmd = Command::new(&client.command);
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(match client.stderr_mode {
+                StderrMode::Discard => Stdio::null(),
+                _ => Stdio::piped(),
+            });
+
+        if let Some(dir) = &client.working_dir {
+            cmd.current_dir(dir);
+        }
+
+        // Run the child in its own process group so kills can sweep
+        // grandchildren (shell commands, node workers) along with it.
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|error| spawn_error(&client.command, &args_for_error, error))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::Transport("live transport stdin is unavailable".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Transport("live transport stdout is unavailable".to_string()))?;
+
+        let pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let stderr_buffer = Arc::new(Mutex::new(StderrRing::new(client.stderr_buffer_limit)));
+
+        let stderr_thread = child.stderr.take().map(|stderr| {
+            start_stderr_thread(
+                stderr,
+                Arc::clone(&stderr_buffer),
+                client.stderr_mode.clone(),
+            )
+        });
+        let chaos = client.chaos.clone().map(ChaosRng::new);
+        let stdout_thread = Some(start_stdout_thread(
+            stdout,
+            ReaderContext {
+                pending: Arc::clone(&pending),
+                stderr_buffer: Arc::clone(&stderr_buffer),
+                max_frame_bytes: client.max_frame_bytes,
+                chaos: chaos.clone(),
+                framing_upgrade: framing_upgrade(&client.transport_options),
+                compression_upgrade: compression_upgrade(&client.transport_options),
+                raw_subscribers: Arc::clone(&client.raw_subscribers),
+                orphans: client.orphan_observer(),
+            },
+        ));
+
+        let mut transport = Self {
+            child: Some(child),
+            writer: Box::new(stdin),
+            pending,
+            stderr_buffer,
+            stdout_thread,
+            stderr_thread,
+            chaos,
+            framing: Framing::NewlineJson,
+            compression: Compression::None,
+            server_info: None,
+        };
+        transport.negotiate_framing(
+            client.transport_options.framing,
+            client.startup_probe_timeout,
+        )?;
+        transport.negotiate_compression(&client.transport_options, client.startup_probe_timeout)?;
+        transport.hello(client.startup_probe_timeout);
+        transport.probe_ready(client.startup_probe_timeout)?;
+        Ok(transport)
+    }
+
+    /// Connect to a remote live server over TCP instead of spawning a
+    /// child, optionally wrapping the stream in TLS and authenticating
+    /// with a bearer token before the startup probe.
+    fn connect(client: &Client, remote: &RemoteOptions) -> Result<Self> {
+        let (reader, writer) = open_remote_stream(remote)?;
+        Self::adopt(client, reader, writer, remote.token.as_deref())
+    }
+
+    /// Drive an externally provided stream pair as the live transport:
+    /// start the reader thread, run the startup negotiations, and probe
+    /// readiness. There is no child to own, so the server process
+    /// outlives this transport.
+    fn adopt(
+        client: &Client,
+        reader: Box<dyn std::io::Read + Send>,
+        writer: Box<dyn Write + Send>,
+        token: Option<&str>,
+    ) -> Result<Self> {
+        let pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let stderr_buffer = Arc::new(Mutex::new(StderrRing::new(client.stderr_buffer_limit)));
+
+        let chaos = client.chaos.clone().map(ChaosRng::new);
+        let stdout_thread = Some(start_stdout_thread(
+            reader,
+            ReaderContext {
+                pending: Arc::clone(&pending),
+                stderr_buffer: Arc::clone(&stderr_buffer),
+                max_frame_bytes: client.max_frame_bytes,
+                chaos: chaos.clone(),
+                framing_upgrade: framing_upgrade(&client.transport_options),
+                compression_upgrade: compression_upgrade(&client.transport_options),
+                raw_subscribers: Arc::clone(&client.raw_subscribers),
+                orphans: client.orphan_observer(),
+            },
+        ));
+
+        let mut transport = Self {
+            child: None,
+            writer,
+            pending,
+            stderr_buffer,
+            stdout_thread,
+            stderr_thread: None,
+            chaos,
+            framing: Framing::NewlineJson,
+            compression: Compression::None,
+            server_info: None,
+        };
+        if let Some(token) = token {
+            transport.authenticate(token, client.startup_probe_timeout)?;
+        }
+        transport.negotiate_framing(
+            client.transport_options.framing,
+            client.startup_probe_timeout,
+        )?;
+        transport.negotiate_compression(&client.transport_options, client.startup_probe_timeout)?;
+        transport.hello(client.startup_probe_timeout);
+        transport.probe_ready(client.startup_probe_timeout)?;
+        Ok(transport)
+    }
+
+    /// Present a bearer token to the remote endpoint and wait for it to
+    /// be accepted.
+    fn authenticate(&mut self, token: &str, timeout: Duration) -> Result<()> {
+        /// Reserved request id for the auth handshake, alongside the
+        /// probe id; caller requests start from 1.
+        const AUTH_REQUEST_ID: u64 = u64::MAX - 1;
+
+        let receiver = self.register_request(AUTH_REQUEST_ID);
+        self.send_json(&json!({
+            "method": "auth",
+            "id": AUTH_REQUEST_ID,
+            "params": { "token": token }
+        }))?;
+
+        let outcome = receiver.recv_timeout(timeout);
+        self.remove_request(AUTH_REQUEST_ID);
+        match outcome {
+            Ok(TransportMessage::Result(result)) => {
+                if let Some(error_payload) = result.get("error") {
+                    return Err(Error::Transport(format!(
+                        "remote authentication failed: {}",
+                        error_payload
+                            .get("message")
+                            .and_then(Value::as_str)
+                            .unwrap_or("token rejected")
+                    )));
+                }
+                Ok(())
+            }
+            Ok(TransportMessage::Closed(message)) => Err(Error::Transport(format!(
+                "remote closed during authentication: {message}"
+            ))),
+            Ok(TransportMessage::Event(_)) => Ok(()),
+            Err(_) => Err(Error::Transport(
+                "remote did not answer the auth handshake".to_string(),
+            )),
+        }
+    }
+
+    /// Offer the server an upgraded wire framing. The reader thread
+    /// switches decoding when it sees the server accept; the send side
+    /// switches here. A rejected or unanswered offer leaves both sides
+    /// on newline framing.
+    fn negotiate_framing(&mut self, target: Framing, timeout: Duration) -> Result<()> {
+        if target == Framing::NewlineJson {
+            return Ok(());
+        }
+
+        let receiver = self.register_request(FRAMING_REQUEST_ID);
+        let sent = self.send_json(&json!({
+            "method": "transport:framing",
+            "id": FRAMING_REQUEST_ID,
+            "params": { "framing": "length-prefixed-json" }
+        }));
+        if sent.is_err() {
+            self.remove_request(FRAMING_REQUEST_ID);
+            return sent;
+        }
+
+        let outcome = receiver.recv_timeout(timeout);
+        self.remove_request(FRAMING_REQUEST_ID);
+        if let Ok(TransportMessage::Result(result)) = outcome {
+            if result.get("error").is_none() {
+                self.framing = target;
+            }
+        }
+        Ok(())
+    }
+
+    /// Offer the server gzip frame compression. Misconfiguration —
+    /// compression without length-prefixed framing, or without the
+    /// `compression` feature — is an error; a server that declined the
+    /// framing upgrade just leaves the transport uncompressed.
+    fn negotiate_compression(
+        &mut self,
+        options: &TransportOptions,
+        timeout: Duration,
+    ) -> Result<()> {
+        if options.compression == Compression::None {
+            return Ok(());
+        }
+        if options.framing != Framing::LengthPrefixedJson {
+            return Err(Error::Transport(
+                "payload compression requires length-prefixed framing".to_string(),
+            ));
+        }
+
+        #[cfg(not(feature = "compression"))]
+        {
+            let _ = timeout;
+            Err(Error::Transport(
+                "gzip payload compression requires the compression feature".to_string(),
+            ))
+        }
+
+        #[cfg(feature = "compression")]
+        {
+            if self.framing != Framing::LengthPrefixedJson {
+                return Ok(());
+            }
+
+            let receiver = self.register_request(COMPRESSION_REQUEST_ID);
+            let sent = self.send_json(&json!({
+                "method": "transport:compression",
+                "id": COMPRESSION_REQUEST_ID,
+                "params": { "compression": "gzip" }
+            }));
+            if sent.is_err() {
+                self.remove_request(COMPRESSION_REQUEST_ID);
+                return sent;
+            }
+
+            let outcome = receiver.recv_timeout(timeout);
+            self.remove_request(COMPRESSION_REQUEST_ID);
+            if let Ok(TransportMessage::Result(result)) = outcome {
+                if result.get("error").is_none() {
+                    self.compression = options.compression;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Introduce ourselves and collect the server's protocol version
+    /// and supported methods. Servers that predate the hello method
+    /// simply do not answer; every method is then attempted as before.
+    fn hello(&mut self, timeout: Duration) {
+        let receiver = self.register_request(HELLO_REQUEST_ID);
+        let sent = self.send_json(&json!({
+            "method": "hello",
+            "id": HELLO_REQUEST_ID,
+            "params": {
+                "sdk": "rust",
+                "sdkVersion": env!("CARGO_PKG_VERSION")
+            }
+        }));
+        if sent.is_err() {
+            self.remove_request(HELLO_REQUEST_ID);
+            return;
+        }
+
+        let outcome = receiver.recv_timeout(timeout);
+        self.remove_request(HELLO_REQUEST_ID);
+        if let Ok(TransportMessage::Result(result)) = outcome {
+            if result.get("error").is_none() {
+                self.server_info = serde_json::from_value(result).ok();
+            }
+        }
+    }
+
+    /// Whether the server advertises support for `method`. Servers that
+    /// sent no method list are given the benefit of the doubt.
+    fn supports(&self, method: &str) -> bool {
+        match &self.server_info {
+            Some(info) if !info.methods.is_empty() => {
+                info.methods.iter().any(|supported| supported == method)
+            }
+            _ => true,
+        }
+    }
+
+    /// Typed error for a method the server does not support.
+    fn unsupported_method(&self, method: &str) -> Error {
+        Error::UnsupportedMethod {
+            method: method.to_string(),
+            cli_version: self
+                .server_info
+                .as_ref()
+                .and_then(|info| info.cli_version.clone()),
+        }
+    }
+
+    /// Probe the freshly spawned server with a protocol ping, bounded by
+    /// `timeout`. Early child exit is a startup failure carrying captured
+    /// stderr; a server that simply does not answer the ping is let
+    /// through, since older CLIs predate the ping method.
+    fn probe_ready(&mut self, timeout: Duration) -> Result<()> {
+        /// Reserved request id for startup probes; never allocated to
+        /// caller requests, which start from 1.
+        const PROBE_REQUEST_ID: u64 = u64::MAX;
+
+        let receiver = self.register_request(PROBE_REQUEST_ID);
+        self.send_json(&json!({
+            "method": "ping",
+            "id": PROBE_REQUEST_ID
+        }))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = self
+                .child
+                .as_mut()
+                .map(Child::try_wait)
+                .transpose()?
+                .flatten()
+            {
+                let stderr = self
+                    .stderr_buffer
+                    .lock()
+                    .map(|buffer| buffer.contents())
+                    .unwrap_or_default();
+                self.remove_request(PROBE_REQUEST_ID);
+                return Err(Error::StartupFailed {
+                    stderr: if stderr.trim().is_empty() {
+                        format!("child exited during startup with status {status}")
+                    } else {
+                        stderr.trim().to_string()
+                    },
+                });
+            }
+
+            match receiver.recv_timeout(Duration::from_millis(50)) {
+                Ok(TransportMessage::Closed(message)) => {
+                    self.remove_request(PROBE_REQUEST_ID);
+                    return Err(Error::StartupFailed { stderr: message });
+                }
+                Ok(_) => {
+                    self.remove_request(PROBE_REQUEST_ID);
+                    return Ok(());
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if Instant::now() >= deadline {
+                        self.remove_request(PROBE_REQUEST_ID);
+                        return Ok(());
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.remove_request(PROBE_REQUEST_ID);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.child.as_ref().map(Child::id)
+    }
+
+    fn register_request(&mut self, request_id: u64) -> Receiver<TransportMessage> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(request_id, sender);
+        }
+        receiver
+    }
+
+    fn remove_request(&mut self, request_id: u64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&request_id);
+        }
+    }
+
+    fn send_json(&mut self, payload: &Value) -> Result<()> {
+        if let Some(chaos) = &self.chaos {
+            if let Some(delay) = chaos.opts.send_delay {
+                thread::sleep(delay);
+            }
+            if chaos.should(chaos.opts.crash) {
+                if let Some(child) = &mut self.child {
+                    let _ = child.kill();
+                }
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            let method = payload
+                .get("method")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let id = payload.get("id").and_then(Value::as_u64);
+            tracing::debug!(target: "mlld::wire", method, id, "frame sent");
+        }
+
+        let line = serde_json::to_string(payload)?;
+        match self.framing {
+            Framing::NewlineJson => {
+                self.writer.write_all(line.as_bytes())?;
+                self.writer.write_all(b"\n")?;
+            }
+            Framing::LengthPrefixedJson => {
+                let bytes = match self.compression {
+                    Compression::None => line.into_bytes(),
+                    #[cfg(feature = "compression")]
+                    Compression::Gzip => gzip_compress(line.as_bytes())?,
+                    #[cfg(not(feature = "compression"))]
+                    Compression::Gzip => line.into_bytes(),
+                };
+                let length = u32::try_from(bytes.len()).map_err(|_| {
+                    Error::Transport("frame exceeds the 4 GiB framing limit".to_string())
+                })?;
+                self.writer.write_all(&length.to_be_bytes())?;
+                self.writer.write_all(&bytes)?;
+            }
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn is_running(&mut self) -> Result<bool> {
+        match &mut self.child {
+            Some(child) => Ok(child.try_wait()?.is_none()),
+            None => Ok(true),
+        }
+    }
+}
+
+/// Kill `child` and everything it spawned. `Child::kill` only reaps
+/// the direct child; grandchildren spawned by node (shell commands,
+/// python) would linger as zombies. The child runs in its own process
+/// group on unix, so the whole group is signalled; on Windows
+/// `taskkill /T` sweeps the tree.
+#[cfg(feature = "client")]
+fn kill_process_tree(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let group = -(child.id() as i32);
+        unsafe {
+            libc::kill(group, libc::SIGKILL);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(feature = "client")]
+impl Drop for LiveTransport {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+        if let Some(child) = &mut self.child {
+            kill_process_tree(child);
+        }
+
+        if let Some(thread_handle) = self.stdout_thread.take() {
+            let _ = thread_handle.join();
+        }
+        if let Some(thread_handle) = self.stderr_thread.take() {
+            let _ = thread_handle.join();
+        }
+    }
+}
+
+/// Everything the stdout reader thread needs beyond the stream itself.
+#[cfg(feature = "client")]
+struct ReaderContext {
+    pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
+    stderr_buffer: Arc<Mutex<StderrRing>>,
+    max_frame_bytes: usize,
+    chaos: Option<ChaosRng>,
+    framing_upgrade: Option<Framing>,
+    compression_upgrade: Option<Compression>,
+    raw_subscribers: Arc<Mutex<Vec<Sender<Value>>>>,
+    orphans: OrphanObserver,
+}
+
+#[cfg(feature = "client")]
+fn start_stdout_thread<R: std::io::Read + Send + 'static>(
+    stdout: R,
+    context: ReaderContext,
+) -> thread::JoinHandle<()> {
+    let ReaderContext {
+        pending,
+        stderr_buffer,
+        max_frame_bytes,
+        chaos,
+        framing_upgrade,
+        compression_upgrade,
+        raw_subscribers,
+        orphans,
+    } = context;
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+        let mut framing = Framing::NewlineJson;
+        let mut compression = Compression::None;
+
+        loop {
+            let frame = match framing {
+                Framing::NewlineJson => read_frame_line(&mut reader, max_frame_bytes),
+                Framing::LengthPrefixedJson => {
+                    read_length_prefixed_frame(&mut reader, max_frame_bytes, compression)
+                }
+            };
+            let line = match frame {
+                Ok(Some(FrameLine::Line(line))) => line,
+                Ok(Some(FrameLine::Oversized(bytes))) => {
+                    notify_all_pending(
+                        &pending,
+                        format!(
+                            "live frame exceeded {max_frame_bytes} byte limit ({bytes} bytes read); frame dropped"
+                        ),
+                    );
+                    continue;
+                }
+                Ok(None) => break,
+                Err(error) => {
+                    notify_all_pending(&pending, format!("live transport read error: {error}"));
+                    return;
+                }
+            };
+
+            let line = match &chaos {
+                Some(chaos) if chaos.should(chaos.opts.drop_frame) => continue,
+                Some(chaos) if chaos.should(chaos.opts.corrupt_frame) => {
+                    format!("{{\"corrupted\":{line}")
+                }
+                _ => line,
+            };
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            match parse_envelope(trimmed) {
+                Ok(Envelope::Event(event)) => {
+                    #[cfg(feature = "tracing")]
+                    {
+                        let id = event.get("id").and_then(Value::as_u64);
+                        let event_type = event
+                            .get("type")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default();
+                        tracing::debug!(target: "mlld::wire", id, event_type, "event received");
+                    }
+                    forward_raw_event(&raw_subscribers, &event);
+                    dispatch_event(&pending, &orphans, event);
+                }
+                Ok(Envelope::Result(result)) => {
+                    if result.get("error").is_none() {
+                        let id = result.get("id").and_then(value_to_request_id);
+                        if let (Some(upgrade), Some(FRAMING_REQUEST_ID)) = (framing_upgrade, id) {
+                            framing = upgrade;
+                        }
+                        if let (Some(upgrade), Some(COMPRESSION_REQUEST_ID)) =
+                            (compression_upgrade, id)
+                        {
+                            compression = upgrade;
+                        }
+                    }
+                    #[cfg(feature = "tracing")]
+                    {
+                        let id = result.get("id").and_then(Value::as_u64);
+                        tracing::debug!(target: "mlld::wire", id, "result received");
+                    }
+                    dispatch_result(&pending, &orphans, result);
+                }
+                Err(error) => {
+                    notify_all_pending(&pending, error);
+                    continue;
+                }
+            }
+        }
+
+        let message = {
+            if let Ok(stderr) = stderr_buffer.lock() {
+                let contents = stderr.contents();
+                let trimmed = contents.trim();
+                if trimmed.is_empty() {
+                    "live transport closed".to_string()
+                } else {
+                    trimmed.to_string()
+                }
+            } else {
+                "live transport closed".to_string()
+            }
+        };
+
+        notify_all_pending(&pending, message);
+    })
+}
+
+/// Read one length-prefixed frame: a big-endian u32 byte count
+/// followed by that many bytes of JSON. EOF at a frame boundary closes
+/// the stream cleanly.
+#[cfg(feature = "client")]
+fn read_length_prefixed_frame<R: BufRead>(
+    reader: &mut R,
+    max_bytes: usize,
+    compression: Compression,
+) -> std::io::Result<Option<FrameLine>> {
+    let mut header = [0u8; 4];
+    let mut filled = 0usize;
+    while filled < header.len() {
+        let read = reader.read(&mut header[filled..])?;
+        if read == 0 {
+            return Ok(None);
+        }
+        filled += read;
+    }
+
+    let length = u32::from_be_bytes(header) as usize;
+    if length > max_bytes {
+        let mut remaining = length;
+        let mut scratch = [0u8; 8192];
+        while remaining > 0 {
+            let chunk = remaining.min(scratch.len());
+            reader.read_exact(&mut scratch[..chunk])?;
+            remaining -= chunk;
+        }
+        return Ok(Some(FrameLine::Oversized(length)));
+    }
+
+    let mut buffer = vec![0u8; length];
+    reader.read_exact(&mut buffer)?;
+    let buffer = match compression {
+        Compression::None => buffer,
+        #[cfg(feature = "compression")]
+        Compression::Gzip => gzip_decompress(&buffer)?,
+        #[cfg(not(feature = "compression"))]
+        Compression::Gzip => buffer,
+    };
+    Ok(Some(FrameLine::Line(
+        String::from_utf8_lossy(&buffer).into_owned(),
+    )))
+}
+
+/// The framing the reader thread should switch to once the server
+/// accepts the negotiation offer; `None` when no offer will be made.
+#[cfg(feature = "client")]
+fn framing_upgrade(options: &TransportOptions) -> Option<Framing> {
+    match options.framing {
+        Framing::NewlineJson => None,
+        other => Some(other),
+    }
+}
+
+/// The compression the reader thread should switch to once the server
+/// accepts the negotiation offer; `None` when no offer will be made.
+#[cfg(feature = "client")]
+fn compression_upgrade(options: &TransportOptions) -> Option<Compression> {
+    match options.compression {
+        Compression::None => None,
+        other => Some(other),
+    }
+}
+
+/// Fan an event frame out to raw subscribers, dropping any whose
+/// receiver has gone away.
+#[cfg(feature = "client")]
+fn forward_raw_event(subscribers: &Arc<Mutex<Vec<Sender<Value>>>>, event: &Value) {
+    if let Ok(mut subscribers) = subscribers.lock() {
+        subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(feature = "compression")]
+fn gzip_compress(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+#[cfg(feature = "compression")]
+fn gzip_decompress(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+    decoder.write_all(bytes)?;
+    decoder.finish()
+}
+
+/// A validated protocol envelope from the live stdout stream.
+#[derive(Debug)]
+#[cfg(feature = "client")]
+enum Envelope {
+    Event(Value),
+    Result(Value),
+}
+
+/// Longest prefix of a raw frame echoed back in validation errors.
+const FRAME_ERROR_PREVIEW_BYTES: usize = 256;
+
+fn frame_preview(raw: &str) -> String {
+    if raw.len() <= FRAME_ERROR_PREVIEW_BYTES {
+        return raw.to_string();
+    }
+
+    let mut end = FRAME_ERROR_PREVIEW_BYTES;
+    while !raw.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}… ({} bytes total)", &raw[..end], raw.len())
+}
+
+/// Validate a raw frame against the envelope schema: a JSON object carrying
+/// exactly one of `result` or `event`, whose payload has a numeric or
+/// numeric-string `id`.
+#[cfg(feature = "client")]
+fn parse_envelope(raw: &str) -> std::result::Result<Envelope, String> {
+    let envelope = serde_json::from_str::<Value>(raw).map_err(|error| {
+        format!(
+            "invalid live frame: {error} (frame: {})",
+            frame_preview(raw)
+        )
+    })?;
+
+    let Value::Object(map) = &envelope else {
+        return Err(format!(
+            "invalid live frame: expected a JSON object envelope (frame: {})",
+            frame_preview(raw)
+        ));
+    };
+
+    let (payload, field) = match (map.get("result"), map.get("event")) {
+        (Some(_), Some(_)) => {
+            return Err(format!(
+                "invalid live frame: envelope carries both result and event (frame: {})",
+                frame_preview(raw)
+            ));
+        }
+        (None, None) => {
+            return Err(format!(
+                "invalid live frame: envelope carries neither result nor event (frame: {})",
+                frame_preview(raw)
+            ));
+        }
+        (Some(result), None) => (result, "result"),
+        (None, Some(event)) => (event, "event"),
+    };
+
+    match payload.get("id") {
+        Some(id) if value_to_request_id(id).is_some() => {}
+        Some(id) => {
+            return Err(format!(
+                "invalid live frame: {field}.id is not a request id (got {id}) (frame: {})",
+                frame_preview(raw)
+            ));
+        }
+        None => {
+            return Err(format!(
+                "invalid live frame: {field}.id is missing (frame: {})",
+                frame_preview(raw)
+            ));
+        }
+    }
+
+    if field == "result" {
+        Ok(Envelope::Result(payload.clone()))
+    } else {
+        Ok(Envelope::Event(payload.clone()))
+    }
+}
+
+/// One decoded line from the live stdout stream.
+#[cfg(feature = "client")]
+enum FrameLine {
+    Line(String),
+    /// A frame exceeded the configured limit; carries total bytes discarded.
+    Oversized(usize),
+}
+
+/// Read one newline-delimited frame, discarding frames over `max_bytes`.
+/// Returns `None` at end of stream.
+#[cfg(feature = "client")]
+fn read_frame_line<R: BufRead>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> std::io::Result<Option<FrameLine>> {
+    let mut buffer = Vec::new();
+    let mut oversized = false;
+    let mut discarded = 0usize;
+
+    loop {
+        let (consumed, complete) = {
+            let available = reader.fill_buf()?;
+
+            if available.is_empty() {
+                if oversized {
+                    return Ok(Some(FrameLine::Oversized(discarded)));
+                }
+                if buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Ok(Some(FrameLine::Line(
+                    String::from_utf8_lossy(&buffer).into_owned(),
+                )));
+            }
+
+            match available.iter().position(|&byte| byte == b'\n') {
+                Some(newline) => {
+                    if oversized {
+                        discarded += newline;
+                    } else {
+                        buffer.extend_from_slice(&available[..newline]);
+                    }
+                    (newline + 1, true)
+                }
+                None => {
+                    if oversized {
+                        discarded += available.len();
+                    } else {
+                        buffer.extend_from_slice(available);
+                    }
+                    (available.len(), false)
+                }
+            }
+        };
+
+        reader.consume(consumed);
+
+        if !oversized && buffer.len() > max_bytes {
+            oversized = true;
+            discarded = buffer.len();
+            buffer.clear();
+        }
+
+        if complete {
+            if oversized {
+                return Ok(Some(FrameLine::Oversized(discarded)));
+            }
+            return Ok(Some(FrameLine::Line(
+                String::from_utf8_lossy(&buffer).into_owned(),
+            )));
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+fn start_stderr_thread(
+    stderr: ChildStderr,
+    stderr_buffer: Arc<Mutex<StderrRing>>,
+    mode: StderrMode,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let reader = BufReader::new(stderr);
+
+        for line in reader.lines().map_while(std::result::Result::ok) {
+            match &mode {
+                StderrMode::Capture => {
+                    if let Ok(mut buffer) = stderr_buffer.lock() {
+                        buffer.push_line(line.clone());
+                    }
+                }
+                StderrMode::Forward => eprintln!("{line}"),
+                StderrMode::Callback(callback) => callback(&line),
+                StderrMode::Structured(callback) => callback(&LogLine::parse(&line)),
+                StderrMode::Discard => {}
+            }
+        }
+    })
+}
+
+/// A frame referencing a request id this client no longer tracks: a
+/// late result arriving after its request timed out, or events — state
+/// writes especially — arriving just after the request settled.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct OrphanFrame {
+    /// Request id the frame referenced.
+    pub request_id: u64,
+
+    /// Which envelope shape the frame arrived as.
+    pub kind: OrphanKind,
+
+    /// The full frame as received.
+    pub payload: Value,
+}
+
+/// Which envelope shape an orphan frame arrived as.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanKind {
+    Event,
+    Result,
+}
+
+#[cfg(feature = "client")]
+impl OrphanKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Event => "event",
+            Self::Result => "result",
+        }
+    }
+}
+
+/// Callback invoked for every orphan frame; see
+/// [`Client::with_orphan_sink`].
+#[cfg(feature = "client")]
+pub type OrphanCallback = dyn Fn(&OrphanFrame) + Send + Sync;
+
+/// The orphan-frame policy as seen by the reader thread: count always,
+/// log and forward when configured.
+#[cfg(feature = "client")]
+#[derive(Clone, Default)]
+struct OrphanObserver {
+    log: bool,
+    counter: Arc<AtomicU64>,
+    sink: Option<Arc<OrphanCallback>>,
+}
+
+#[cfg(feature = "client")]
+impl OrphanObserver {
+    fn record(&self, kind: OrphanKind, request_id: u64, payload: &Value) {
+        if is_reserved_request_id(request_id) {
+            return;
+        }
+        self.counter.fetch_add(1, Ordering::Relaxed);
+        if self.log {
+            eprintln!(
+                "mlld: dropped orphan {} frame for request {request_id}",
+                kind.label()
+            );
+        }
+        if let Some(sink) = &self.sink {
+            sink(&OrphanFrame {
+                request_id,
+                kind,
+                payload: payload.clone(),
+            });
+        }
+    }
+}
+
+/// Reserved ids at the top of the range carry startup handshakes; late
+/// replies to them are expected after a handshake times out and are
+/// not interesting as orphans.
+#[cfg(feature = "client")]
+fn is_reserved_request_id(request_id: u64) -> bool {
+    request_id >= HELLO_REQUEST_ID
+}
+
+#[cfg(feature = "client")]
+fn dispatch_event(
+    pending: &Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
+    orphans: &OrphanObserver,
+    event: Value,
+) {
+    let request_id = event.get("id").and_then(value_to_request_id);
+
+    let Some(request_id) = request_id else {
+        return;
+    };
+
+    let sender = pending
+        .lock()
+        .ok()
+        .and_then(|map| map.get(&request_id).cloned());
+
+    if let Some(sender) = sender {
+        let _ = sender.send(TransportMessage::Event(event));
+    } else {
+        orphans.record(OrphanKind::Event, request_id, &event);
+    }
+}
+
+#[cfg(feature = "client")]
+fn dispatch_result(
+    pending: &Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
+    orphans: &OrphanObserver,
+    result: Value,
+) {
+    let request_id = result.get("id").and_then(value_to_request_id);
+
+    let Some(request_id) = request_id else {
+        return;
+    };
+
+    // Checkpoints of long runs arrive as results marked partial; the
+    // request stays pending and the checkpoint travels down the event
+    // stream instead of settling the request.
+    if result.get("partial").and_then(Value::as_bool) == Some(true) {
+        let sender = pending
+            .lock()
+            .ok()
+            .and_then(|map| map.get(&request_id).cloned());
+        if let Some(sender) = sender {
+            let event = json!({
+                "type": "result:partial",
+                "id": request_id,
+                "result": result
+            });
+            let _ = sender.send(TransportMessage::Event(event));
+        } else {
+            orphans.record(OrphanKind::Result, request_id, &result);
+        }
+        return;
+    }
+
+    let sender = pending
+        .lock()
+        .ok()
+        .and_then(|mut map| map.remove(&request_id));
+
+    if let Some(sender) = sender {
+        let _ = sender.send(TransportMessage::Result(result));
+    } else {
+        orphans.record(OrphanKind::Result, request_id, &result);
+    }
+}
+
+/// Closed-message marker the heartbeat monitor drains pending
+/// requests with, so receivers surface [`Error::HeartbeatLost`]
+/// instead of a generic transport error.
+#[cfg(feature = "client")]
+const HEARTBEAT_LOST_MESSAGE: &str = "live transport missed heartbeats";
+
+/// Map a reader-side close message to the error receivers see.
+#[cfg(feature = "client")]
+fn closed_error(message: String) -> Error {
+    if message == HEARTBEAT_LOST_MESSAGE {
+        return Error::HeartbeatLost;
+    }
+    Error::Transport(message)
+}
+
+#[cfg(feature = "client")]
+fn notify_all_pending(
+    pending: &Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
+    message: String,
+) {
+    let senders = pending
+        .lock()
+        .map(|mut map| map.drain().map(|(_, sender)| sender).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    for sender in senders {
+        let _ = sender.send(TransportMessage::Closed(message.clone()));
+    }
+}
+
+#[cfg(feature = "client")]
+fn value_to_request_id(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64().or_else(|| {
+            number
+                .as_i64()
+                .and_then(|n| if n >= 0 { Some(n as u64) } else { None })
+        }),
+        Value::String(text) => text.parse::<u64>().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "client")]
+fn normalize_labels<I, S>(labels: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut seen = std::collections::HashSet::new();
+    let mut normalized = Vec::new();
+
+    for label in labels {
+        let trimmed = label.into().trim().to_string();
+        if trimmed.is_empty() || !seen.insert(trimmed.clone()) {
+            continue;
+        }
+        normalized.push(trimmed);
+    }
+
+    normalized
+}
+
+#[cfg(feature = "client")]
+fn normalize_label_map(
+    input: Option<HashMap<String, Vec<String>>>,
+) -> Option<HashMap<String, Vec<String>>> {
+    let mut normalized = HashMap::new();
+
+    for (field, labels) in input.unwrap_or_default() {
+        let deduped = normalize_labels(labels);
+        if !deduped.is_empty() {
+            normalized.insert(field, deduped);
+        }
+    }
+
+    if normalized.is_empty() {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+/// The result-affecting request options folded into a cache key, shared
+/// by the process and execute paths. Maps are rebuilt as BTreeMaps so
+/// insertion order cannot change the key.
+#[cfg(feature = "client")]
+#[derive(Serialize)]
+struct CacheKeyInputs<'a> {
+    state: Option<&'a Value>,
+    pins: std::collections::BTreeMap<&'a String, &'a String>,
+    mode: Option<&'a String>,
+    dynamic_modules: Option<std::collections::BTreeMap<&'a String, &'a Value>>,
+    dynamic_module_source: Option<&'a String>,
+    import_policy: Option<&'a ImportPolicy>,
+    timezone: Option<&'a String>,
+    locale: Option<&'a String>,
+    now: Option<&'a String>,
+    clock_offset_ms: Option<i64>,
+    extra_flags: &'a [String],
+}
+
+/// Cache key for a process request: script source plus every input
+/// that can change the result.
+#[cfg(feature = "client")]
+fn process_cache_key(script: &str, opts: &ProcessOptions) -> String {
+    result_cache_key(
+        "process",
+        script,
+        opts.payload.as_ref(),
+        &CacheKeyInputs {
+            state: opts.state.as_ref(),
+            pins: opts.pins.iter().collect(),
+            mode: opts.mode.as_ref(),
+            dynamic_modules: opts
+                .dynamic_modules
+                .as_ref()
+                .map(|map| map.iter().collect()),
+            dynamic_module_source: opts.dynamic_module_source.as_ref(),
+            import_policy: opts.import_policy.as_ref(),
+            timezone: opts.timezone.as_ref(),
+            locale: opts.locale.as_ref(),
+            now: opts.now.as_ref(),
+            clock_offset_ms: opts.clock_offset_ms,
+            extra_flags: &opts.extra_flags,
+        },
+    )
+}
+
+/// Cache key for an execute request.
+#[cfg(feature = "client")]
+fn execute_cache_key(filepath: &str, payload: Option<&Value>, opts: &ExecuteOptions) -> String {
+    result_cache_key(
+        "execute",
+        filepath,
+        payload,
+        &CacheKeyInputs {
+            state: opts.state.as_ref(),
+            pins: opts.pins.iter().collect(),
+            mode: opts.mode.as_ref(),
+            dynamic_modules: opts
+                .dynamic_modules
+                .as_ref()
+                .map(|map| map.iter().collect()),
+            dynamic_module_source: opts.dynamic_module_source.as_ref(),
+            import_policy: opts.import_policy.as_ref(),
+            timezone: opts.timezone.as_ref(),
+            locale: opts.locale.as_ref(),
+            now: opts.now.as_ref(),
+            clock_offset_ms: opts.clock_offset_ms,
+            extra_flags: &opts.extra_flags,
+        },
+    )
+}
+
+#[cfg(feature = "client")]
+fn result_cache_key(
+    method: &str,
+    target: &str,
+    payload: Option<&Value>,
+    inputs: &CacheKeyInputs<'_>,
+) -> String {
+    let descriptor = json!({
+        "method": method,
+        "target": target,
+        "payload": payload,
+        "options": inputs,
+    });
+
+    format!("{:016x}", fnv1a_hash(descriptor.to_string().as_bytes()))
+}
+
+/// FNV-1a over the key descriptor: deterministic across runs, unlike
+/// the std hasher, so persistent cache stores stay valid.
+#[cfg(feature = "client")]
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Deserialize a JSON value, reporting the exact JSON path on failure
+/// (e.g. `exports.items[3].name: missing field`).
+fn deserialize_with_path<T: serde::de::DeserializeOwned>(
+    value: Value,
+) -> std::result::Result<T, String> {
+    serde_path_to_error::deserialize(value).map_err(|error| {
+        let path = error.path().to_string();
+        if path == "." {
+            error.inner().to_string()
+        } else {
+            format!("at {path}: {}", error.inner())
+        }
+    })
+}
+
+/// Resident set size of a process in bytes, when the platform exposes
+/// it (`/proc/<pid>/status` on Linux).
+#[cfg(feature = "client")]
+#[cfg(target_os = "linux")]
+fn process_rss_bytes(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    parse_vm_rss_bytes(&status)
+}
+
+#[cfg(feature = "client")]
+#[cfg(not(target_os = "linux"))]
+fn process_rss_bytes(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Parse the `VmRSS:` line of a `/proc/<pid>/status` file into bytes.
+#[cfg(feature = "client")]
+fn parse_vm_rss_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|field| field.parse().ok())?;
+    Some(kilobytes * 1024)
+}
+
+/// Open reader and writer halves of a connection to a remote live
+/// server, wrapping them in TLS when asked.
+#[cfg(feature = "client")]
+fn open_remote_stream(
+    remote: &RemoteOptions,
+) -> Result<(Box<dyn std::io::Read + Send>, Box<dyn Write + Send>)> {
+    let tcp = std::net::TcpStream::connect(&remote.addr)
+        .map_err(|error| Error::Transport(format!("connect to {} failed: {error}", remote.addr)))?;
+
+    if !remote.tls {
+        let reader = tcp.try_clone()?;
+        return Ok((Box::new(reader), Box::new(tcp)));
+    }
+
+    #[cfg(feature = "rustls")]
+    {
+        let host = remote.tls_server_name.clone().unwrap_or_else(|| {
+            remote
+                .addr
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        let server_name = rustls::pki_types::ServerName::try_from(host.clone())
+            .map_err(|_| Error::Transport(format!("invalid TLS server name: {host}")))?;
+
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let conn = rustls::ClientConnection::new(Arc::new(config), server_name)
+            .map_err(|error| Error::Transport(format!("TLS setup failed: {error}")))?;
+
+        let shared = Arc::new(Mutex::new(conn));
+        let reader = TlsHalf {
+            conn: Arc::clone(&shared),
+            tcp: tcp.try_clone()?,
+        };
+        let writer = TlsHalf { conn: shared, tcp };
+        #[allow(clippy::needless_return)]
+        return Ok((Box::new(reader), Box::new(writer)));
+    }
+
+    #[cfg(not(feature = "rustls"))]
+    Err(Error::Transport(
+        "TLS for remote transports requires the rustls feature".to_string(),
+    ))
+}
+
+/// One direction of a shared TLS session. The connection state is
+/// behind a mutex; the reader blocks on a lock-free `peek` of its own
+/// socket clone before touching the session, so writes are not starved
+/// by the blocking read.
+#[cfg(feature = "rustls")]
+struct TlsHalf {
+    conn: Arc<Mutex<rustls::ClientConnection>>,
+    tcp: std::net::TcpStream,
+}
+
+#[cfg(feature = "rustls")]
+impl std::io::Read for TlsHalf {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            {
+                let mut conn = self
+                    .conn
+                    .lock()
+                    .map_err(|_| std::io::Error::other("TLS session lock poisoned"))?;
+                match conn.reader().read(buf) {
+                    Ok(read) => return Ok(read),
+                    Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {}
+                    Err(error) => return Err(error),
+                }
+            }
+
+            // Wait for ciphertext without holding the session lock.
+            let mut probe = [0u8; 1];
+            if self.tcp.peek(&mut probe)? == 0 {
+                return Ok(0);
+            }
+
+            let mut conn = self
+                .conn
+                .lock()
+                .map_err(|_| std::io::Error::other("TLS session lock poisoned"))?;
+            if conn.read_tls(&mut self.tcp)? == 0 {
+                return Ok(0);
+            }
+            conn.process_new_packets().map_err(std::io::Error::other)?;
+            while conn.wants_write() {
+                conn.write_tls(&mut self.tcp)?;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl Write for TlsHalf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| std::io::Error::other("TLS session lock poisoned"))?;
+        let written = conn.writer().write(buf)?;
+        while conn.wants_write() {
+            conn.write_tls(&mut self.tcp)?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| std::io::Error::other("TLS session lock poisoned"))?;
+        while conn.wants_write() {
+            conn.write_tls(&mut self.tcp)?;
+        }
+        self.tcp.flush()
+    }
+}
+
+/// Whether an error means the server has not registered the request
+/// yet, which resolves itself once startup completes.
+#[cfg(feature = "client")]
+fn is_request_not_found(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Mlld {
+            code: Some(ErrorCode::RequestNotFound),
+            ..
+        }
+    )
+}
+
+/// Rewrite an exhausted REQUEST_NOT_FOUND retry into a message naming
+/// the request id; other errors pass through unchanged.
+#[cfg(feature = "client")]
+fn missing_request_error(error: Error, request_id: u64) -> Error {
+    match error {
+        Error::Mlld {
+            code: Some(ErrorCode::RequestNotFound),
+            ..
+        } => Error::Mlld {
+            message: format!("No active request for id {request_id}"),
+            code: Some(ErrorCode::RequestNotFound),
+            diagnostic: None,
+        },
+        other => other,
+    }
+}
+
+/// Build a rich spawn failure error with the attempted command line,
+/// PATH, and a hint when the binary looks like an npm shim that cannot
+/// find node.
+#[cfg(feature = "client")]
+fn spawn_error(command: &str, args: &[String], source: std::io::Error) -> Error {
+    let command_line = std::iter::once(command.to_string())
+        .chain(args.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let path_env = std::env::var("PATH").unwrap_or_else(|_| "<unset>".to_string());
+
+    let hint = if source.kind() == std::io::ErrorKind::NotFound {
+        if command.contains('/') || command.contains('\\') {
+            "; hint: the configured command path does not exist".to_string()
+        } else {
+            format!(
+                "; hint: `{command}` was not found on PATH — if it is an npm-installed shim, \
+                 ensure node is installed, or point the client at the CLI directly with \
+                 with_command(\"node\") and with_command_args([\"path/to/cli.cjs\"])"
+            )
+        }
+    } else {
+        String::new()
+    };
+
+    Error::SpawnFailed {
+        command_line,
+        path_env,
+        hint,
+        source,
+    }
+}
+
+#[cfg(feature = "client")]
+fn read_to_string_lossy<R: std::io::Read>(source: Option<R>) -> String {
+    let Some(mut source) = source else {
+        return String::new();
+    };
+    let mut bytes = Vec::new();
+    let _ = source.read_to_end(&mut bytes);
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Build live protocol params for a process request from its options.
+#[cfg(feature = "client")]
+fn build_process_params(
+    script: &str,
+    opts: ProcessOptions,
+) -> Result<serde_json::Map<String, Value>> {
+    let mut params = serde_json::Map::new();
+    params.insert("script".to_string(), Value::String(script.to_string()));
+
+    if let Some(file_path) = opts.file_path {
+        params.insert("filePath".to_string(), Value::String(file_path));
+    }
+    if let Some(payload) = opts.payload {
+        params.insert("payload".to_string(), payload);
+    }
+    if let Some(session_id) = opts.session_id {
+        params.insert("sessionId".to_string(), Value::String(session_id));
+    }
+    if let Some(correlation_id) = opts.correlation_id {
+        params.insert("correlationId".to_string(), Value::String(correlation_id));
+    }
+    if !opts.tags.is_empty() {
+        params.insert("tags".to_string(), serde_json::to_value(&opts.tags)?);
+    }
+    if let Some(payload_labels) = normalize_label_map(opts.payload_labels) {
+        params.insert(
+            "payloadLabels".to_string(),
+            serde_json::to_value(payload_labels)?,
+        );
+    }
+    if let Some(state) = opts.state {
+        params.insert("state".to_string(), state);
+    }
+    if let Some(dynamic_modules) = opts.dynamic_modules {
+        params.insert(
+            "dynamicModules".to_string(),
+            serde_json::to_value(dynamic_modules)?,
+        );
+    }
+    if let Some(source) = opts.dynamic_module_source {
+        params.insert("dynamicModuleSource".to_string(), Value::String(source));
+    }
+    if let Some(mode) = opts.mode {
+        params.insert("mode".to_string(), Value::String(mode));
+    }
+    if let Some(allow_absolute_paths) = opts.allow_absolute_paths {
+        params.insert(
+            "allowAbsolutePaths".to_string(),
+            Value::Bool(allow_absolute_paths),
+        );
+    }
+    if let Some(import_policy) = opts.import_policy {
+        params.insert(
+            "importPolicy".to_string(),
+            serde_json::to_value(import_policy)?,
+        );
+    }
+    if let Some(timezone) = opts.timezone {
+        params.insert("timezone".to_string(), Value::String(timezone));
+    }
+    if let Some(locale) = opts.locale {
+        params.insert("locale".to_string(), Value::String(locale));
+    }
+    if let Some(now) = opts.now {
+        params.insert("now".to_string(), Value::String(now));
+    }
+    if let Some(offset) = opts.clock_offset_ms {
+        params.insert("clockOffsetMs".to_string(), json!(offset));
+    }
+
+    if !opts.pins.is_empty() {
+        params.insert("pins".to_string(), serde_json::to_value(&opts.pins)?);
+    }
+    if let Some(readonly_filesystem) = opts.readonly_filesystem {
+        params.insert(
+            "readonlyFilesystem".to_string(),
+            Value::Bool(readonly_filesystem),
+        );
+    }
+    if let Some(capture_file_writes) = opts.capture_file_writes {
+        params.insert(
+            "captureFileWrites".to_string(),
+            Value::Bool(capture_file_writes),
+        );
+    }
+    if !opts.extra_flags.is_empty() {
+        params.insert("extraFlags".to_string(), json!(opts.extra_flags));
+    }
+
+    Ok(params)
+}
+
+/// Build live protocol params for an execute request from its options.
+#[cfg(feature = "client")]
+fn build_execute_params<P: Serialize>(
+    filepath: &str,
+    payload: Option<P>,
+    opts: ExecuteOptions,
+) -> Result<serde_json::Map<String, Value>> {
+    let mut params = serde_json::Map::new();
+    params.insert("filepath".to_string(), Value::String(filepath.to_string()));
+
+    if let Some(p) = payload {
+        params.insert("payload".to_string(), serde_json::to_value(p)?);
+    }
+    if let Some(session_id) = opts.session_id {
+        params.insert("sessionId".to_string(), Value::String(session_id));
+    }
+    if let Some(correlation_id) = opts.correlation_id {
+        params.insert("correlationId".to_string(), Value::String(correlation_id));
+    }
+    if !opts.tags.is_empty() {
+        params.insert("tags".to_string(), serde_json::to_value(&opts.tags)?);
+    }
+    if let Some(payload_labels) = normalize_label_map(opts.payload_labels) {
+        params.insert(
+            "payloadLabels".to_string(),
+            serde_json::to_value(payload_labels)?,
+        );
+    }
+    if let Some(state) = opts.state {
+        params.insert("state".to_string(), state);
+    }
+    if let Some(dynamic_modules) = opts.dynamic_modules {
+        params.insert(
+            "dynamicModules".to_string(),
+            serde_json::to_value(dynamic_modules)?,
+        );
+    }
+    if let Some(source) = opts.dynamic_module_source {
+        params.insert("dynamicModuleSource".to_string(), Value::String(source));
+    }
+    if let Some(allow_absolute_paths) = opts.allow_absolute_paths {
+        params.insert(
+            "allowAbsolutePaths".to_string(),
+            Value::Bool(allow_absolute_paths),
+        );
+    }
+    if let Some(mode) = opts.mode {
+        params.insert("mode".to_string(), Value::String(mode));
+    }
+    if let Some(import_policy) = opts.import_policy {
+        params.insert(
+            "importPolicy".to_string(),
+            serde_json::to_value(import_policy)?,
+        );
+    }
+    if let Some(timezone) = opts.timezone {
+        params.insert("timezone".to_string(), Value::String(timezone));
+    }
+    if let Some(locale) = opts.locale {
+        params.insert("locale".to_string(), Value::String(locale));
+    }
+    if let Some(now) = opts.now {
+        params.insert("now".to_string(), Value::String(now));
+    }
+    if let Some(offset) = opts.clock_offset_ms {
+        params.insert("clockOffsetMs".to_string(), json!(offset));
+    }
+
+    if !opts.pins.is_empty() {
+        params.insert("pins".to_string(), serde_json::to_value(&opts.pins)?);
+    }
+    if let Some(readonly_filesystem) = opts.readonly_filesystem {
+        params.insert(
+            "readonlyFilesystem".to_string(),
+            Value::Bool(readonly_filesystem),
+        );
+    }
+    if let Some(capture_file_writes) = opts.capture_file_writes {
+        params.insert(
+            "captureFileWrites".to_string(),
+            Value::Bool(capture_file_writes),
+        );
+    }
+    if !opts.extra_flags.is_empty() {
+        params.insert("extraFlags".to_string(), json!(opts.extra_flags));
+    }
+
+    Ok(params)
+}
+
+/// Parse a raw execute result payload into an [`ExecuteResult`], merging
+/// state writes observed as events and enforcing any exports schema.
+#[cfg(feature = "client")]
+fn parse_execute_result(
+    mut result: Value,
+    state_write_events: Vec<StateWrite>,
+    parsing: ResultParsing,
+    exports_schema: Option<&Value>,
+) -> Result<ExecuteResult> {
+    if let Value::Object(map) = &mut result {
+        map.remove("id");
+    }
+
+    let mut execute_result = match deserialize_with_path::<ExecuteResult>(result.clone()) {
+        Ok(parsed) => parsed,
+        Err(error) => match parsing {
+            ResultParsing::Strict => {
+                return Err(Error::ResultParse(format!(
+                    "{error} (result: {})",
+                    frame_preview(&result.to_string())
+                )));
+            }
+            ResultParsing::Lenient => ExecuteResult {
+                output: result.to_string(),
+                ..Default::default()
+            },
+        },
+    };
+
+    execute_result.state_writes =
+        merge_state_writes(execute_result.state_writes, state_write_events);
+
+    if let Some(exports_schema) = exports_schema {
+        let violations = schema::validate(exports_schema, &execute_result.exports);
+        if !violations.is_empty() {
+            return Err(Error::SchemaViolation(violations));
+        }
+    }
+
+    Ok(execute_result)
+}
+
+/// Parse a raw analyze result payload into an [`AnalyzeResult`].
+#[cfg(feature = "client")]
+fn parse_analyze_result(mut result: Value) -> Result<AnalyzeResult> {
+    if let Value::Object(map) = &mut result {
+        map.remove("id");
+    }
+
+    deserialize_with_path::<AnalyzeResult>(result.clone()).map_err(|error| {
+        Error::ResultParse(format!(
+            "{error} (result: {})",
+            frame_preview(&result.to_string())
+        ))
+    })
+}
+
+#[cfg(feature = "client")]
+fn error_from_payload(payload: &Value) -> Error {
+    let message = payload
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or("mlld request failed")
+        .to_string();
+
+    let code = payload.get("code").and_then(Value::as_str);
+
+    if code == Some("CANCELLED") {
+        return Error::Cancelled {
+            reason: payload
+                .get("reason")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+        };
+    }
+
+    Error::Mlld {
+        message,
+        code: code.map(ErrorCode::from_wire),
+        diagnostic: MlldDiagnostic::from_payload(payload).map(Box::new),
+    }
+}
+
+/// Which wait a timed-out request was in when its deadline expired.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    /// Still waiting in the SDK's admission queue for an in-flight
+    /// slot; the server never saw the request. Bounded by
+    /// `with_queue_timeout`.
+    Queue,
+
+    /// Executing on the server. Bounded by the request timeout.
+    #[default]
+    Execution,
+}
+
+impl std::fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Queue => "queue",
+            Self::Execution => "execution",
+        })
+    }
+}
+
+/// What a timed-out request accomplished before its deadline; carried
+/// by [`Error::Timeout`] so callers can log partial progress instead
+/// of losing it, and which phase — queue wait or execution — the
+/// deadline expired in.
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutInfo {
+    /// The wait that expired.
+    pub phase: TimeoutPhase,
+
+    /// The timeout that elapsed.
+    pub limit: Duration,
+
+    /// State writes observed before the deadline.
+    pub state_writes: Vec<StateWrite>,
+
+    /// Partial result checkpoints observed before the deadline.
+    pub partials: Vec<Value>,
+}
+
+#[cfg(feature = "client")]
+impl TimeoutInfo {
+    /// An execution timeout with no observed progress.
+    fn bare(limit: Duration) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// A timeout that expired while waiting in the admission queue.
+    fn queued(limit: Duration) -> Self {
+        Self {
+            phase: TimeoutPhase::Queue,
+            limit,
+            ..Self::default()
+        }
+    }
+}
+
+/// Structured diagnostic attached to [`Error::Mlld`], mirroring what
+/// the CLI renders: location, source excerpt, offending directive,
+/// and hint. Fields the server did not report are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MlldDiagnostic {
+    /// Path of the file the error points into.
+    pub file: Option<String>,
+
+    /// 1-based line of the error location.
+    pub line: Option<u64>,
+
+    /// 1-based column of the error location.
+    pub column: Option<u64>,
+
+    /// Source excerpt around the error location.
+    pub source: Option<String>,
+
+    /// Directive being evaluated when the error occurred.
+    pub directive: Option<String>,
+
+    /// Suggested fix, as the CLI prints it.
+    pub hint: Option<String>,
+}
+
+/// Typed error code attached to [`Error::Mlld`], so callers can match
+/// on codes without maintaining their own string tables. Codes this
+/// SDK version does not know arrive as [`ErrorCode::Other`] verbatim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The server has no active request with the given id.
+    RequestNotFound,
+
+    /// The script failed to parse.
+    ParseError,
+
+    /// The script failed during evaluation.
+    ScriptError,
+
+    /// A run/sh command inside the script failed.
+    CommandExecution,
+
+    /// An import could not be resolved.
+    ImportResolution,
+
+    /// A referenced file is missing.
+    FileNotFound,
+
+    /// A guard blocked execution.
+    GuardDenied,
+
+    /// The server-side execution timed out.
+    Timeout,
+
+    /// The execution was aborted.
+    Aborted,
+
+    /// Any other interpreter failure.
+    RuntimeError,
+
+    /// A code this SDK version has no variant for, verbatim.
+    Other(String),
+}
+
+impl ErrorCode {
+    /// The wire string for this code.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::RequestNotFound => "REQUEST_NOT_FOUND",
+            Self::ParseError => "PARSE_ERROR",
+            Self::ScriptError => "SCRIPT_ERROR",
+            Self::CommandExecution => "COMMAND_EXECUTION",
+            Self::ImportResolution => "IMPORT_RESOLUTION",
+            Self::FileNotFound => "FILE_NOT_FOUND",
+            Self::GuardDenied => "GUARD_DENIED",
+            Self::Timeout => "TIMEOUT",
+            Self::Aborted => "ABORTED",
+            Self::RuntimeError => "RUNTIME_ERROR",
+            Self::Other(code) => code,
+        }
+    }
+
+    #[cfg(feature = "client")]
+    fn from_wire(code: &str) -> Self {
+        match code {
+            "REQUEST_NOT_FOUND" => Self::RequestNotFound,
+            "PARSE_ERROR" => Self::ParseError,
+            "SCRIPT_ERROR" => Self::ScriptError,
+            "COMMAND_EXECUTION" => Self::CommandExecution,
+            "IMPORT_RESOLUTION" => Self::ImportResolution,
+            "FILE_NOT_FOUND" => Self::FileNotFound,
+            "GUARD_DENIED" => Self::GuardDenied,
+            "TIMEOUT" => Self::Timeout,
+            "ABORTED" => Self::Aborted,
+            "RUNTIME_ERROR" => Self::RuntimeError,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl MlldDiagnostic {
+    /// Parse diagnostic fields from an error payload. Fields may sit
+    /// at the top level or under `details`; returns `None` when the
+    /// payload carries none of them.
+    fn from_payload(payload: &Value) -> Option<Self> {
+        let field = |names: &[&str]| {
+            for scope in [Some(payload), payload.get("details")]
+                .into_iter()
+                .flatten()
+            {
+                for name in names {
+                    if let Some(value) = scope.get(name) {
+                        if !value.is_null() {
+                            return Some(value.clone());
+                        }
+                    }
+                }
+            }
+            None
+        };
+        let text =
+            |names: &[&str]| field(names).and_then(|value| value.as_str().map(ToString::to_string));
+        let number = |names: &[&str]| field(names).and_then(|value| value.as_u64());
+
+        let diagnostic = Self {
+            file: text(&["filePath", "file"]),
+            line: number(&["line"]),
+            column: number(&["column"]),
+            source: text(&["source", "excerpt"]),
+            directive: text(&["directive"]),
+            hint: text(&["hint", "suggestion"]),
+        };
+
+        if diagnostic == Self::default() {
+            return None;
+        }
+        Some(diagnostic)
+    }
+}
+
+/// Where a request is in the server's admission queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg(feature = "client")]
+pub enum QueuePhase {
+    /// Admission was deferred because the server hit its concurrency limit.
+    Queued,
+    /// The request left the queue and is about to be scheduled.
+    Dequeued,
+    /// The server began executing the request.
+    Started,
+}
+
+/// A queue lifecycle event reported by the server, so hosts can account
+/// queue wait separately from execution time.
+#[derive(Debug, Clone, Copy)]
+#[cfg(feature = "client")]
+pub struct QueueEvent {
+    pub phase: QueuePhase,
+
+    /// Queue depth at the time of the event, when reported.
+    pub depth: Option<u64>,
+
+    /// When this client received the event.
+    pub received: Instant,
+}
+
+#[cfg(feature = "client")]
+fn parse_queue_event(event: &Value) -> Option<QueueEvent> {
+    let phase = match event.get("type").and_then(Value::as_str)? {
+        "queue:queued" => QueuePhase::Queued,
+        "queue:dequeued" => QueuePhase::Dequeued,
+        "queue:started" => QueuePhase::Started,
+        _ => return None,
+    };
+
+    Some(QueueEvent {
+        phase,
+        depth: event.get("depth").and_then(Value::as_u64),
+        received: Instant::now(),
+    })
+}
+
+/// Parse a warning event into a [`Warning`]; `None` for other events.
+#[cfg(feature = "client")]
+fn parse_warning_event(event: &Value) -> Option<Warning> {
+    if event.get("type").and_then(Value::as_str) != Some("warning") {
+        return None;
+    }
+
+    Some(Warning {
+        message: event
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        code: event
+            .get("code")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+        path: event
+            .get("path")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+    })
+}
+
+/// Extract the payload of a partial-result checkpoint event
+/// synthesized by the dispatcher for results marked `partial`.
+#[cfg(feature = "client")]
+fn parse_partial_result_event(event: &Value) -> Option<Value> {
+    if event.get("type").and_then(Value::as_str) != Some("result:partial") {
+        return None;
+    }
+    event.get("result").cloned()
+}
+
+#[cfg(feature = "client")]
+fn parse_effect_event(event: &Value) -> Option<Effect> {
+    if event.get("type").and_then(Value::as_str) != Some("effect") {
+        return None;
+    }
+    Some(Effect::from_value(event.get("effect")?.clone()))
+}
+
+#[cfg(feature = "client")]
+fn parse_output_chunk_event(event: &Value) -> Option<OutputChunk> {
+    if event.get("type").and_then(Value::as_str) != Some("effect") {
+        return None;
+    }
+
+    let effect = event.get("effect")?;
+    let effect_type = effect.get("type").and_then(Value::as_str)?;
+    if effect_type != "show" && effect_type != "doc" {
+        return None;
+    }
+
+    Some(OutputChunk {
+        effect_type: effect_type.to_string(),
+        content: effect.get("content").and_then(Value::as_str)?.to_string(),
+    })
+}
+
+/// Whether a dot-separated state path matches a watch pattern, where
+/// `*` matches exactly one segment.
+#[cfg(feature = "client")]
+fn state_path_matches(pattern: &str, path: &str) -> bool {
+    let mut segments = path.split('.');
+    for wanted in pattern.split('.') {
+        match segments.next() {
+            Some(segment) if wanted == "*" || wanted == segment => {}
+            _ => return false,
+        }
+    }
+    segments.next().is_none()
+}
+
+#[cfg(feature = "client")]
+fn parse_state_write_event(event: &Value) -> Option<StateWrite> {
+    if event.get("type").and_then(Value::as_str) != Some("state:write") {
+        return None;
+    }
+
+    let write = event.get("write")?;
+    let path = write.get("path")?.as_str()?.to_string();
+
+    Some(StateWrite {
+        path,
+        value: write.get("value").cloned().unwrap_or(Value::Null),
+        timestamp: write
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+    })
+}
+
+/// Append event-observed warnings the final result did not already
+/// carry, keyed by code and message.
+#[cfg(feature = "client")]
+fn merge_warnings(primary: &mut Vec<Warning>, observed: &[Warning]) {
+    for warning in observed {
+        let seen = primary
+            .iter()
+            .any(|existing| existing.code == warning.code && existing.message == warning.message);
+        if !seen {
+            primary.push(warning.clone());
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+fn merge_state_writes(primary: Vec<StateWrite>, secondary: Vec<StateWrite>) -> Vec<StateWrite> {
+    if secondary.is_empty() {
+        return primary;
+    }
+    if primary.is_empty() {
+        return secondary;
+    }
+
+    let mut merged = Vec::with_capacity(primary.len() + secondary.len());
+    let mut seen = std::collections::HashSet::new();
+
+    for state_write in primary.into_iter().chain(secondary) {
+        let key = state_write_key(&state_write);
+        if seen.insert(key) {
+            merged.push(state_write);
+        }
+    }
+
+    merged
+}
+
+#[cfg(feature = "client")]
+fn state_write_key(state_write: &StateWrite) -> String {
+    format!(
+        "{}|{}",
+        state_write.path,
+        serde_json::to_string(&state_write.value).unwrap_or_else(|_| "null".to_string())
+    )
+}
+
+/// Output of an mlld CLI subcommand run via [`Client::cli`].
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct CliOutput {
+    /// Process exit code, when the process exited normally.
+    pub status: Option<i32>,
+
+    /// Whether the process exited successfully.
+    pub success: bool,
+
+    pub stdout: String,
+    pub stderr: String,
+
+    /// Parsed stdout, when the subcommand produced JSON.
+    pub json: Option<Value>,
+}
+
+/// Why a request was cancelled. Forwarded to the interpreter with the
+/// cancel request and echoed in [`Error::Cancelled`] and audit events,
+/// so post-mortems can tell user aborts from drains from escalations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg(feature = "client")]
+pub enum CancelReason {
+    /// A user or operator asked for the work to stop.
+    UserAbort,
+
+    /// The host is draining for a deploy or shutdown.
+    DeployDrain,
+
+    /// A timeout elapsed and the client escalated to cancellation.
+    TimeoutEscalation,
+
+    /// Any other reason, in free text.
+    Other(String),
+}
+
+#[cfg(feature = "client")]
+impl CancelReason {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::UserAbort => "user-abort",
+            Self::DeployDrain => "deploy-drain",
+            Self::TimeoutEscalation => "timeout-escalation",
+            Self::Other(reason) => reason,
+        }
+    }
+}
+
+/// How a state:update applies its value, so nested objects can be
+/// patched or pruned without re-sending (and clobbering) the whole
+/// subtree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg(feature = "client")]
+pub enum StateOp {
+    /// Replace the value at the path. The wire default.
+    #[default]
+    Set,
+
+    /// Merge the value into the object at the path, RFC 7396 style:
+    /// absent keys are kept, null values delete keys.
+    MergePatch,
+
+    /// Remove the path entirely. The value is ignored.
+    Delete,
+
+    /// Push the value onto the array at the path.
+    Append,
+}
+
+#[cfg(feature = "client")]
+impl StateOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Set => "set",
+            Self::MergePatch => "merge-patch",
+            Self::Delete => "delete",
+            Self::Append => "append",
+        }
+    }
+}
+
+/// One state:update write as handed to the transport layer, bundling
+/// the per-write knobs the handle wrappers expose.
+#[cfg(feature = "client")]
+struct StateUpdate<'a> {
+    path: &'a str,
+    value: Value,
+    op: StateOp,
+    labels: Option<Vec<String>>,
+}
+
+/// Build the wire entries for a batched state:update. The `op` field
+/// is omitted for plain sets, which older servers treat as the only
+/// operation.
+#[cfg(feature = "client")]
+fn state_update_entries(updates: &[(String, Value, StateOp)]) -> Vec<Value> {
+    updates
+        .iter()
+        .map(|(path, value, op)| {
+            if *op == StateOp::Set {
+                json!({ "path": path, "value": value })
+            } else {
+                json!({ "path": path, "value": value, "op": op.as_str() })
+            }
+        })
+        .collect()
+}
+
+/// Buffered state writes for one in-flight request, applied together.
+///
+/// Nothing reaches the server until [`commit`](Self::commit), which
+/// sends every buffered write in a single atomic state:update — the
+/// server applies all of them or none. [`rollback`](Self::rollback)
+/// (or dropping the transaction) discards the buffer without sending
+/// anything.
+#[cfg(feature = "client")]
+#[must_use = "buffered state writes are discarded unless commit() is called"]
+pub struct StateTransaction<'a> {
+    request: &'a RequestHandle,
+    updates: Vec<(String, Value, StateOp)>,
+}
+
+#[cfg(feature = "client")]
+impl StateTransaction<'_> {
+    /// Buffer a plain write of `value` at `path`.
+    pub fn set<V: Serialize>(&mut self, path: &str, value: V) -> Result<()> {
+        self.push(path, serde_json::to_value(value)?, StateOp::Set)
+    }
+
+    /// Buffer an RFC 7396 merge of `value` into the object at `path`.
+    pub fn merge_patch<V: Serialize>(&mut self, path: &str, value: V) -> Result<()> {
+        self.push(path, serde_json::to_value(value)?, StateOp::MergePatch)
+    }
+
+    /// Buffer removal of `path`.
+    pub fn delete(&mut self, path: &str) -> Result<()> {
+        self.push(path, Value::Null, StateOp::Delete)
+    }
+
+    /// Buffer a push of `value` onto the array at `path`.
+    pub fn append<V: Serialize>(&mut self, path: &str, value: V) -> Result<()> {
+        self.push(path, serde_json::to_value(value)?, StateOp::Append)
+    }
+
+    fn push(&mut self, path: &str, value: Value, op: StateOp) -> Result<()> {
+        if path.trim().is_empty() {
+            return Err(Error::Transport(
+                "state update path is required".to_string(),
+            ));
+        }
+        self.updates.push((path.to_string(), value, op));
+        Ok(())
+    }
+
+    /// Send every buffered write in one atomic state:update. An empty
+    /// transaction commits without a round trip.
+    pub fn commit(self) -> Result<()> {
+        self.request.client.update_states_request(
+            self.request.request_id,
+            self.updates,
+            true,
+            self.request.timeout,
+            self.request.worker,
+        )
+    }
+
+    /// Discard the buffered writes without contacting the server.
+    pub fn rollback(self) {
+        drop(self);
+    }
+}
+
+/// Scheduling priority for a request, used when shedding load under
+/// memory pressure and when ordering the concurrency queue: queued
+/// high-priority work is released first, `Low` is background work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg(feature = "client")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Spawn configuration for a labeled pool worker. Unset fields inherit
+/// the client's settings.
+#[derive(Debug, Clone, Default)]
+#[cfg(feature = "client")]
+pub struct WorkerProfile {
+    /// Labels this worker serves (e.g. `gpu`, `large-memory`).
+    pub labels: Vec<String>,
+
+    /// Override the CLI command for this worker.
+    pub command: Option<String>,
+
+    /// Override the command args used before live transport args.
+    pub command_args: Option<Vec<String>>,
+
+    /// Override the working directory for this worker.
+    pub working_dir: Option<String>,
+}
+
+/// How result envelopes that fail to deserialize into their typed shape
+/// are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg(feature = "client")]
+pub enum ResultParsing {
+    /// Surface the deserialization error, naming the mismatching fields.
+    #[default]
+    Strict,
+
+    /// Fall back to returning the raw JSON as `output`.
+    Lenient,
+}
+
+/// How the live transport handles child process stderr.
+#[derive(Clone, Default)]
+#[cfg(feature = "client")]
+pub enum StderrMode {
+    /// Buffer stderr and use it to enrich transport-close errors.
+    #[default]
+    Capture,
+
+    /// Pass child stderr lines through to the parent process stderr live.
+    Forward,
+
+    /// Drop child stderr entirely.
+    Discard,
+
+    /// Invoke a callback for each stderr line as it arrives.
+    Callback(Arc<dyn Fn(&str) + Send + Sync>),
+
+    /// Parse each stderr line into a [`LogLine`] and invoke a callback;
+    /// see [`Client::with_stderr_handler`].
+    Structured(Arc<LogCallback>),
+}
+
+/// Callback invoked for every parsed child log line; see
+/// [`Client::with_stderr_handler`].
+#[cfg(feature = "client")]
+pub type LogCallback = dyn Fn(&LogLine) + Send + Sync;
+
+#[cfg(feature = "client")]
+impl std::fmt::Debug for StderrMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Capture => f.write_str("Capture"),
+            Self::Forward => f.write_str("Forward"),
+            Self::Discard => f.write_str("Discard"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+            Self::Structured(_) => f.write_str("Structured(..)"),
+        }
+    }
+}
+
+/// Severity of a child log line.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+#[cfg(feature = "client")]
+impl LogLevel {
+    fn from_label(label: &str) -> Self {
+        match label.to_ascii_lowercase().as_str() {
+            "debug" | "trace" => Self::Debug,
+            "warn" | "warning" => Self::Warn,
+            "error" | "fatal" => Self::Error,
+            _ => Self::Info,
+        }
+    }
+}
+
+/// A parsed line of the child's stderr; see
+/// [`Client::with_stderr_handler`]. JSON log lines contribute their
+/// `level`, `message`, and `requestId` fields; plain text lines are
+/// parsed for a leading `[level]` or `level:` prefix. Anything else
+/// arrives as an info-level line verbatim.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// Parsed severity; [`LogLevel::Info`] when the line carries none.
+    pub level: LogLevel,
+
+    /// The message with any level prefix stripped.
+    pub message: String,
+
+    /// Request id, when the line names one.
+    pub request_id: Option<u64>,
+
+    /// The unparsed line as the child wrote it.
+    pub raw: String,
+}
+
+#[cfg(feature = "client")]
+impl LogLine {
+    fn parse(raw: &str) -> Self {
+        if let Ok(value) = serde_json::from_str::<Value>(raw) {
+            if value.is_object() {
+                let level = value
+                    .get("level")
+                    .and_then(Value::as_str)
+                    .map(LogLevel::from_label)
+                    .unwrap_or_default();
+                let message = value
+                    .get("message")
+                    .or_else(|| value.get("msg"))
+                    .and_then(Value::as_str)
+                    .unwrap_or(raw)
+                    .to_string();
+                let request_id = value
+                    .get("requestId")
+                    .or_else(|| value.get("id"))
+                    .and_then(value_to_request_id);
+                return Self {
+                    level,
+                    message,
+                    request_id,
+                    raw: raw.to_string(),
+                };
+            }
+        }
+
+        for label in [
+            "debug", "trace", "info", "warn", "warning", "error", "fatal",
+        ] {
+            for prefix in [format!("[{label}] "), format!("{label}: ")] {
+                let matches =
+                    raw.len() >= prefix.len() && raw[..prefix.len()].eq_ignore_ascii_case(&prefix);
+                if matches {
+                    return Self {
+                        level: LogLevel::from_label(label),
+                        message: raw[prefix.len()..].to_string(),
+                        request_id: None,
+                        raw: raw.to_string(),
+                    };
+                }
+            }
+        }
+
+        Self {
+            level: LogLevel::Info,
+            message: raw.to_string(),
+            request_id: None,
+            raw: raw.to_string(),
+        }
+    }
+}
+
+/// Options for process().
+#[derive(Debug, Default, Clone)]
+#[cfg(feature = "client")]
+pub struct ProcessOptions {
+    /// Provides context for relative imports.
+    pub file_path: Option<String>,
+
+    /// Data injected as @payload.
+    pub payload: Option<Value>,
+
+    /// Per-field security labels for @payload object fields.
+    pub payload_labels: Option<HashMap<String, Vec<String>>>,
+
+    /// Data injected as @state.
+    pub state: Option<Value>,
+
+    /// Additional modules to inject.
+    pub dynamic_modules: Option<HashMap<String, Value>>,
+
+    /// Source label added to dynamic modules.
+    pub dynamic_module_source: Option<String>,
+
+    /// Parsing mode (strict|markdown).
+    pub mode: Option<String>,
+
+    /// Allow absolute path access.
+    pub allow_absolute_paths: Option<bool>,
+
+    /// Restrict which import sources this request may use.
+    pub import_policy: Option<ImportPolicy>,
+
+    /// Caps on result size; exceeding them fails the request.
+    pub limits: Option<ResultLimits>,
+
+    /// IANA timezone applied to `@now` and date builtins (e.g.
+    /// `Europe/Berlin`); defaults to the child process environment.
+    pub timezone: Option<String>,
+
+    /// BCP 47 locale applied to date/number formatting (e.g. `de-DE`).
+    pub locale: Option<String>,
+
+    /// Fix the interpreter clock at this ISO-8601 instant so timestamp
+    /// output is deterministic (e.g. for snapshot tests).
+    pub now: Option<String>,
+
+    /// Offset the interpreter clock by this many milliseconds instead of
+    /// fixing it entirely.
+    pub clock_offset_ms: Option<i64>,
+
+    /// Scheduling priority; requests below [`Priority::High`] are shed
+    /// under memory pressure. See [`Client::with_max_rss_bytes`].
+    pub priority: Priority,
+
+    /// Route this request to a worker whose profile carries all of these
+    /// labels. Client-side routing; see [`Client::with_worker_profile`].
+    pub worker_labels: Vec<String>,
+
+    /// Sticky session this run belongs to, so server-side state and
+    /// warmed module caches persist across calls; set by [`Session`].
+    pub session_id: Option<String>,
+
+    /// Caller-supplied correlation id, propagated to the live server
+    /// and echoed in its events and logs, so node-side logs can be
+    /// joined with host traces.
+    pub correlation_id: Option<String>,
+
+    /// Free-form tags propagated to the server with the request.
+    pub tags: HashMap<String, String>,
+
+    /// Registry module versions pinned for this request, overriding the
+    /// lockfile. Keys are module names (`@author/module`), values exact
+    /// versions.
+    pub pins: HashMap<String, String>,
+
+    /// Treat the filesystem as read-only for this request: file outputs
+    /// and mutations fail instead of writing to disk. Enforced by the
+    /// interpreter; intended for preview and analysis servers.
+    pub readonly_filesystem: Option<bool>,
+
+    /// Intercept file outputs and return them as [`FileArtifact`]s on the
+    /// result instead of writing them, so hosts can show what would have
+    /// been written. Enforced by the interpreter.
+    pub capture_file_writes: Option<bool>,
+
+    /// Raw CLI flags forwarded with the request, for capabilities not yet
+    /// modeled by typed options.
+    pub extra_flags: Vec<String>,
+
+    /// Provision a per-request scratch directory for intermediate
+    /// files, exposed to the script as `@ctx.scratch` and removed when
+    /// the request completes.
+    pub scratch: bool,
+
+    /// Keep the scratch directory when the request fails, so its
+    /// contents can be inspected. Read it from the handle's
+    /// `scratch_dir()` afterwards.
+    pub retain_scratch_on_failure: bool,
+
+    /// Skip the configured result cache for this request: neither read
+    /// a cached result nor store this run's.
+    pub bypass_cache: bool,
+
+    /// Declare the script free of side effects, making it safe for the
+    /// configured retry policy to replay after a transport disconnect;
+    /// see [`Client::with_retry`].
+    pub pure: bool,
+
+    /// Override the client default timeout.
+    pub timeout: Option<Duration>,
+}
+
+#[cfg(feature = "client")]
+impl ProcessOptions {
+    /// Pin a registry module to an exact version for this request,
+    /// overriding the lockfile.
+    pub fn pin(mut self, module: impl Into<String>, version: impl Into<String>) -> Self {
+        self.pins.insert(module.into(), version.into());
+        self
+    }
+
+    /// Forward raw CLI flags with this request.
+    pub fn extra_flags<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extra_flags = flags.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// Options for [`Client::execute_many`].
+#[derive(Debug, Default, Clone)]
+#[cfg(feature = "client")]
+pub struct ExecuteManyOptions {
+    /// Options applied to every run in the batch.
+    pub execute: ExecuteOptions,
+
+    /// Runs kept in flight at once; `0` or `1` dispatches the batch
+    /// sequentially.
+    pub concurrency: usize,
+}
+
+/// Options for execute().
+#[derive(Debug, Default, Clone)]
+#[cfg(feature = "client")]
+pub struct ExecuteOptions {
+    /// Per-field security labels for @payload object fields.
+    pub payload_labels: Option<HashMap<String, Vec<String>>>,
+
+    /// Data injected as @state.
+    pub state: Option<Value>,
+
+    /// Additional modules to inject.
+    pub dynamic_modules: Option<HashMap<String, Value>>,
+
+    /// Source label added to dynamic modules.
+    pub dynamic_module_source: Option<String>,
+
+    /// Parsing mode (strict|markdown).
+    pub mode: Option<String>,
+
+    /// Allow absolute path access.
+    pub allow_absolute_paths: Option<bool>,
+
+    /// Restrict which import sources this request may use.
+    pub import_policy: Option<ImportPolicy>,
+
+    /// Caps on result size; exceeding them fails the request.
+    pub limits: Option<ResultLimits>,
+
+    /// IANA timezone applied to `@now` and date builtins (e.g.
+    /// `Europe/Berlin`); defaults to the child process environment.
+    pub timezone: Option<String>,
+
+    /// BCP 47 locale applied to date/number formatting (e.g. `de-DE`).
+    pub locale: Option<String>,
+
+    /// Fix the interpreter clock at this ISO-8601 instant so timestamp
+    /// output is deterministic (e.g. for snapshot tests).
+    pub now: Option<String>,
+
+    /// Offset the interpreter clock by this many milliseconds instead of
+    /// fixing it entirely.
+    pub clock_offset_ms: Option<i64>,
+
+    /// Scheduling priority; requests below [`Priority::High`] are shed
+    /// under memory pressure. See [`Client::with_max_rss_bytes`].
+    pub priority: Priority,
+
+    /// Route this request to a worker whose profile carries all of these
+    /// labels. Client-side routing; see [`Client::with_worker_profile`].
+    pub worker_labels: Vec<String>,
+
+    /// Sticky session this run belongs to, so server-side state and
+    /// warmed module caches persist across calls; set by [`Session`].
+    pub session_id: Option<String>,
+
+    /// Caller-supplied correlation id, propagated to the live server
+    /// and echoed in its events and logs, so node-side logs can be
+    /// joined with host traces.
+    pub correlation_id: Option<String>,
+
+    /// Free-form tags propagated to the server with the request.
+    pub tags: HashMap<String, String>,
+
+    /// Registry module versions pinned for this request, overriding the
+    /// lockfile. Keys are module names (`@author/module`), values exact
+    /// versions.
+    pub pins: HashMap<String, String>,
+
+    /// Treat the filesystem as read-only for this request: file outputs
+    /// and mutations fail instead of writing to disk. Enforced by the
+    /// interpreter; intended for preview and analysis servers.
+    pub readonly_filesystem: Option<bool>,
+
+    /// Intercept file outputs and return them as [`FileArtifact`]s on the
+    /// result instead of writing them, so hosts can show what would have
+    /// been written. Enforced by the interpreter.
+    pub capture_file_writes: Option<bool>,
+
+    /// Raw CLI flags forwarded with the request, for capabilities not yet
+    /// modeled by typed options.
+    pub extra_flags: Vec<String>,
+
+    /// Provision a per-request scratch directory for intermediate
+    /// files, exposed to the script as `@ctx.scratch` and removed when
+    /// the request completes.
+    pub scratch: bool,
+
+    /// Keep the scratch directory when the request fails, so its
+    /// contents can be inspected. Read it from the handle's
+    /// `scratch_dir()` afterwards.
+    pub retain_scratch_on_failure: bool,
+
+    /// Skip the configured result cache for this request: neither read
+    /// a cached result nor store this run's.
+    pub bypass_cache: bool,
+
+    /// Override the client default timeout.
+    pub timeout: Option<Duration>,
+
+    /// JSON Schema the module's exports must satisfy after execution.
+    pub exports_schema: Option<Value>,
+}
+
+#[cfg(feature = "client")]
+impl ExecuteOptions {
+    /// Require the module's exports to satisfy `schema` after execution.
+    pub fn exports_schema(mut self, schema: Value) -> Self {
+        self.exports_schema = Some(schema);
+        self
+    }
+
+    /// Pin a registry module to an exact version for this request,
+    /// overriding the lockfile.
+    pub fn pin(mut self, module: impl Into<String>, version: impl Into<String>) -> Self {
+        self.pins.insert(module.into(), version.into());
+        self
+    }
+
+    /// Forward raw CLI flags with this request.
+    pub fn extra_flags<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extra_flags = flags.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// Per-request caps on result size. Each cap is optional; exceeding one
+/// fails the request with [`Error::ResultTooLarge`] naming the cap.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg(feature = "client")]
+pub struct ResultLimits {
+    /// Maximum rendered output size in bytes.
+    pub max_output_bytes: Option<usize>,
+
+    /// Maximum number of effects in the result.
+    pub max_effects: Option<usize>,
+
+    /// Maximum number of state writes in the result.
+    pub max_state_writes: Option<usize>,
+}
+
+#[cfg(feature = "client")]
+impl ResultLimits {
+    fn check(&self, result: &Value, state_writes: &[StateWrite]) -> Result<()> {
+        if let Some(limit) = self.max_output_bytes {
+            let actual = result
+                .get("output")
+                .or_else(|| result.get("value"))
+                .and_then(Value::as_str)
+                .map_or(0, str::len);
+            if actual > limit {
+                return Err(Error::ResultTooLarge {
+                    what: "output bytes",
+                    actual,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(limit) = self.max_effects {
+            let actual = result
+                .get("effects")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            if actual > limit {
+                return Err(Error::ResultTooLarge {
+                    what: "effect count",
+                    actual,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(limit) = self.max_state_writes {
+            let embedded = result
+                .get("stateWrites")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            let actual = embedded.max(state_writes.len());
+            if actual > limit {
+                return Err(Error::ResultTooLarge {
+                    what: "state write count",
+                    actual,
+                    limit,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Restricts which import sources a request may use; enforced by the
+/// interpreter, so untrusted scripts cannot import arbitrary remote code.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg(feature = "client")]
+pub struct ImportPolicy {
+    /// Allow imports from local files.
+    pub allow_local: bool,
+
+    /// Allow URL imports.
+    pub allow_urls: bool,
+
+    /// Allow registry module imports.
+    pub allow_registry: bool,
+
+    /// When registry imports are allowed, restrict them to these
+    /// namespaces (e.g. `@company`); empty means any namespace.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub registry_namespaces: Vec<String>,
+}
+
+#[cfg(feature = "client")]
+impl ImportPolicy {
+    /// Only local file imports; no URLs, no registry modules.
+    pub fn local_only() -> Self {
+        Self {
+            allow_local: true,
+            allow_urls: false,
+            allow_registry: false,
+            registry_namespaces: Vec::new(),
         }
-        receiver
     }
 
-    fn remove_request(&mut self, request_id: u64) {
-        if let Ok(mut pending) = self.pending.lock() {
-            pending.remove(&request_id);
+    /// Local files plus registry modules, but no URL imports.
+    pub fn no_urls() -> Self {
+        Self {
+            allow_local: true,
+            allow_urls: false,
+            allow_registry: true,
+            registry_namespaces: Vec::new(),
         }
     }
 
-    fn send_json(&mut self, payload: &Value) -> Result<()> {
-        let line = serde_json::to_string(payload)?;
-        self.stdin.write_all(line.as_bytes())?;
-        self.stdin.write_all(b"\n")?;
-        self.stdin.flush()?;
-        Ok(())
+    /// Local files plus registry modules from the given namespaces only.
+    pub fn registry_namespaces<I, S>(namespaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allow_local: true,
+            allow_urls: false,
+            allow_registry: true,
+            registry_namespaces: namespaces.into_iter().map(Into::into).collect(),
+        }
     }
+}
 
-    fn is_running(&mut self) -> Result<bool> {
-        Ok(self.child.try_wait()?.is_none())
+/// Pluggable storage backing the opt-in result cache; see
+/// [`Client::with_result_cache`]. Implementations must be safe to call
+/// from multiple threads.
+#[cfg(feature = "client")]
+pub trait ResultCacheStore: Send + Sync {
+    /// Look up a cached result by key; `None` on miss.
+    fn get(&self, key: &str) -> Option<CachedResult>;
+
+    /// Store a result under `key`, replacing any existing entry.
+    fn put(&self, key: &str, entry: CachedResult);
+}
+
+/// A cached execution result and when it was stored, for TTL expiry.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct CachedResult {
+    pub result: ExecuteResult,
+    pub stored_at: SystemTime,
+}
+
+/// In-process [`ResultCacheStore`] backed by a `HashMap`, for hosts
+/// that re-run identical work within one process lifetime.
+#[derive(Default)]
+#[cfg(feature = "client")]
+pub struct MemoryCacheStore {
+    entries: Mutex<HashMap<String, CachedResult>>,
+}
+
+#[cfg(feature = "client")]
+impl MemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl Drop for LiveTransport {
-    fn drop(&mut self) {
-        let _ = self.stdin.flush();
-        let _ = self.child.kill();
-        let _ = self.child.wait();
+#[cfg(feature = "client")]
+impl ResultCacheStore for MemoryCacheStore {
+    fn get(&self, key: &str) -> Option<CachedResult> {
+        self.entries.lock().ok()?.get(key).cloned()
+    }
 
-        if let Some(thread_handle) = self.stdout_thread.take() {
-            let _ = thread_handle.join();
-        }
-        if let Some(thread_handle) = self.stderr_thread.take() {
-            let _ = thread_handle.join();
+    fn put(&self, key: &str, entry: CachedResult) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), entry);
         }
     }
 }
 
-fn start_stdout_thread(
-    stdout: ChildStdout,
-    pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
-    stderr_buffer: Arc<Mutex<String>>,
-) -> thread::JoinHandle<()> {
-    thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-
-        for line in reader.lines() {
-            match line {
-                Ok(line) => {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
-                        continue;
-                    }
+/// Structured output from process_full(). Script runs and file runs
+/// share a wire shape, so this is the same type as [`ExecuteResult`].
+pub type ProcessResult = ExecuteResult;
 
-                    let envelope = match serde_json::from_str::<Value>(trimmed) {
-                        Ok(parsed) => parsed,
-                        Err(error) => {
-                            notify_all_pending(&pending, format!("invalid live response: {error}"));
-                            continue;
-                        }
-                    };
+/// Structured output from execute().
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecuteResult {
+    pub output: String,
 
-                    if let Some(event) = envelope.get("event") {
-                        dispatch_event(&pending, event.clone());
-                    }
+    #[serde(default)]
+    pub state_writes: Vec<StateWrite>,
 
-                    if let Some(result) = envelope.get("result") {
-                        dispatch_result(&pending, result.clone());
-                    }
-                }
-                Err(error) => {
-                    notify_all_pending(&pending, format!("live transport read error: {error}"));
-                    return;
-                }
-            }
-        }
+    #[serde(default)]
+    pub exports: Value,
 
-        let message = {
-            if let Ok(stderr) = stderr_buffer.lock() {
-                let trimmed = stderr.trim();
-                if trimmed.is_empty() {
-                    "live transport closed".to_string()
-                } else {
-                    trimmed.to_string()
-                }
-            } else {
-                "live transport closed".to_string()
-            }
-        };
+    /// Ordered effect log for the run: doc chunks, show output, stderr,
+    /// and file writes in the order the interpreter emitted them.
+    /// [`render`] rebuilds the final document from this log.
+    #[serde(default)]
+    pub effects: Vec<Effect>,
 
-        notify_all_pending(&pending, message);
-    })
-}
+    #[serde(default)]
+    pub denials: Vec<GuardDenial>,
 
-fn start_stderr_thread(
-    stderr: ChildStderr,
-    stderr_buffer: Arc<Mutex<String>>,
-) -> thread::JoinHandle<()> {
-    thread::spawn(move || {
-        let reader = BufReader::new(stderr);
+    /// Non-fatal warnings raised during the run, merged from the final
+    /// result and warning events.
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
 
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                if let Ok(mut buffer) = stderr_buffer.lock() {
-                    if !buffer.is_empty() {
-                        buffer.push('\n');
-                    }
-                    buffer.push_str(&line);
-                }
-            }
-        }
-    })
+    pub metrics: Option<Metrics>,
+
+    /// File outputs intercepted by `capture_file_writes` instead of being
+    /// written to disk.
+    #[serde(default)]
+    pub artifacts: Vec<FileArtifact>,
+
+    /// Reproducibility record for this run, when the server provides one.
+    pub provenance: Option<Provenance>,
 }
 
-fn dispatch_event(pending: &Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>, event: Value) {
-    let request_id = event.get("id").and_then(value_to_request_id);
+impl ExecuteResult {
+    /// Deserialize the module exports into a caller-provided type,
+    /// avoiding manual `serde_json::Value` plumbing.
+    pub fn exports_as<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        deserialize_with_path::<T>(self.exports.clone()).map_err(|error| {
+            Error::ResultParse(format!(
+                "{error} (exports: {})",
+                frame_preview(&self.exports.to_string())
+            ))
+        })
+    }
+}
 
-    let Some(request_id) = request_id else {
-        return;
-    };
+/// A file output captured virtually instead of being written to disk.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileArtifact {
+    /// Path the script targeted, relative to the working directory.
+    pub path: String,
 
-    let sender = pending
-        .lock()
-        .ok()
-        .and_then(|map| map.get(&request_id).cloned());
+    /// Content that would have been written.
+    pub content: String,
+}
 
-    if let Some(sender) = sender {
-        let _ = sender.send(TransportMessage::Event(event));
-    }
+/// Content hash identifying a script in the server's store, returned
+/// by [`Client::store_script`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ScriptHash(pub String);
+
+/// Result of a [`Client::ping`] health check.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct Ping {
+    /// Round-trip time of the protocol ping.
+    pub latency: Duration,
+
+    /// Whether the live child process is still running after the ping.
+    pub process_alive: bool,
 }
 
-fn dispatch_result(pending: &Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>, result: Value) {
-    let request_id = result.get("id").and_then(value_to_request_id);
+/// Settings for the background health monitor; see
+/// [`Client::start_health_monitor`].
+#[derive(Clone)]
+#[cfg(feature = "client")]
+pub struct HealthMonitorOptions {
+    /// Time between pings.
+    pub interval: Duration,
 
-    let Some(request_id) = request_id else {
-        return;
-    };
+    /// Ping wait after which the child counts as hung.
+    pub ping_timeout: Duration,
 
-    let sender = pending
-        .lock()
-        .ok()
-        .and_then(|mut map| map.remove(&request_id));
+    /// Invoked after each kill-and-respawn incident.
+    pub on_respawn: Option<Arc<HealthCallback>>,
+}
 
-    if let Some(sender) = sender {
-        let _ = sender.send(TransportMessage::Result(result));
+#[cfg(feature = "client")]
+impl Default for HealthMonitorOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            ping_timeout: Duration::from_secs(2),
+            on_respawn: None,
+        }
     }
 }
 
-fn notify_all_pending(
-    pending: &Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>>,
-    message: String,
-) {
-    let senders = pending
-        .lock()
-        .map(|mut map| map.drain().map(|(_, sender)| sender).collect::<Vec<_>>())
-        .unwrap_or_default();
+/// Callback invoked when the health monitor restarts the child; see
+/// [`Client::start_health_monitor`].
+#[cfg(feature = "client")]
+pub type HealthCallback = dyn Fn(&HealthEvent) + Send + Sync;
 
-    for sender in senders {
-        let _ = sender.send(TransportMessage::Closed(message.clone()));
-    }
+/// What the health monitor found wrong with the child.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg(feature = "client")]
+pub enum HealthIssue {
+    /// The process is alive but did not answer the ping in time.
+    Hung,
+
+    /// The process exited.
+    Exited,
 }
 
-fn value_to_request_id(value: &Value) -> Option<u64> {
-    match value {
-        Value::Number(number) => number.as_u64().or_else(|| {
-            number
-                .as_i64()
-                .and_then(|n| if n >= 0 { Some(n as u64) } else { None })
-        }),
-        Value::String(text) => text.parse::<u64>().ok(),
-        _ => None,
-    }
+/// Report of one health monitor incident.
+#[derive(Debug, Clone)]
+#[cfg(feature = "client")]
+pub struct HealthEvent {
+    /// What was wrong with the child.
+    pub issue: HealthIssue,
+
+    /// Whether the respawn brought a healthy child back.
+    pub respawned: bool,
 }
 
-fn normalize_labels<I, S>(labels: I) -> Vec<String>
-where
-    I: IntoIterator<Item = S>,
-    S: Into<String>,
-{
-    let mut seen = std::collections::HashSet::new();
-    let mut normalized = Vec::new();
+/// Handle to a running health monitor thread; the monitor stops when
+/// this is dropped.
+#[cfg(feature = "client")]
+pub struct HealthMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
 
-    for label in labels {
-        let trimmed = label.into().trim().to_string();
-        if trimmed.is_empty() || !seen.insert(trimmed.clone()) {
-            continue;
+#[cfg(feature = "client")]
+impl HealthMonitor {
+    /// Stop the monitor and wait for its thread to exit.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread_handle) = self.thread.take() {
+            let _ = thread_handle.join();
         }
-        normalized.push(trimmed);
     }
+}
 
-    normalized
+#[cfg(feature = "client")]
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
 }
 
-fn normalize_label_map(
-    input: Option<HashMap<String, Vec<String>>>,
-) -> Option<HashMap<String, Vec<String>>> {
-    let mut normalized = HashMap::new();
+/// A script prepared on the live server: the source lives in the
+/// content-addressed store and the parsed AST is cached keyed by its
+/// hash, so repeated runs skip both resending and re-parsing. Created
+/// by [`Client::prepare`].
+#[cfg(feature = "client")]
+pub struct PreparedScript {
+    client: Client,
+    hash: ScriptHash,
+}
 
-    for (field, labels) in input.unwrap_or_default() {
-        let deduped = normalize_labels(labels);
-        if !deduped.is_empty() {
-            normalized.insert(field, deduped);
-        }
+#[cfg(feature = "client")]
+impl PreparedScript {
+    /// Content hash identifying the prepared script on the server.
+    pub fn hash(&self) -> &ScriptHash {
+        &self.hash
     }
 
-    if normalized.is_empty() {
-        None
-    } else {
-        Some(normalized)
+    /// Run the prepared script with `payload` and return the output.
+    pub fn run<P: Serialize>(&self, payload: Option<P>) -> Result<String> {
+        let mut handle = self.run_async(payload, None)?;
+        handle.result()
     }
-}
 
-fn error_from_payload(payload: &Value) -> Error {
-    let message = payload
-        .get("message")
-        .and_then(Value::as_str)
-        .unwrap_or("mlld request failed")
-        .to_string();
+    /// Run the prepared script and return the full result.
+    pub fn run_full<P: Serialize>(
+        &self,
+        payload: Option<P>,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessResult> {
+        let mut handle = self.run_async(payload, opts)?;
+        handle.result_full()
+    }
 
-    let code = payload
-        .get("code")
-        .and_then(Value::as_str)
-        .map(ToString::to_string);
+    /// Start a run and return the in-flight request handle.
+    pub fn run_async<P: Serialize>(
+        &self,
+        payload: Option<P>,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessHandle> {
+        let mut opts = opts.unwrap_or_default();
+        if let Some(payload) = payload {
+            opts.payload = Some(serde_json::to_value(payload)?);
+        }
+        self.client.process_stored_async(&self.hash, Some(opts))
+    }
+}
 
-    Error::Mlld { message, code }
+/// A sticky session for stateful request sequences: requests carry a
+/// shared server-side session id and are pinned to one worker, so
+/// state and warmed module caches persist across calls. Created by
+/// [`Client::session`].
+#[cfg(feature = "client")]
+pub struct Session {
+    client: Client,
+    id: String,
+    worker_labels: Vec<String>,
 }
 
-fn parse_state_write_event(event: &Value) -> Option<StateWrite> {
-    if event.get("type").and_then(Value::as_str) != Some("state:write") {
-        return None;
+#[cfg(feature = "client")]
+impl Session {
+    /// Server-side session identifier carried by every request.
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
-    let write = event.get("write")?;
-    let path = write.get("path")?.as_str()?.to_string();
+    /// Execute a script string within the session.
+    pub fn process(&self, script: &str, opts: Option<ProcessOptions>) -> Result<String> {
+        self.client.process(script, Some(self.tag_process(opts)))
+    }
 
-    Some(StateWrite {
-        path,
-        value: write.get("value").cloned().unwrap_or(Value::Null),
-        timestamp: write
-            .get("timestamp")
-            .and_then(Value::as_str)
-            .map(ToString::to_string),
-    })
-}
+    /// Execute a script string within the session and return the full
+    /// result.
+    pub fn process_full(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessResult> {
+        self.client
+            .process_full(script, Some(self.tag_process(opts)))
+    }
 
-fn merge_state_writes(primary: Vec<StateWrite>, secondary: Vec<StateWrite>) -> Vec<StateWrite> {
-    if secondary.is_empty() {
-        return primary;
+    /// Start a script execution within the session and return the
+    /// in-flight request handle.
+    pub fn process_async(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessHandle> {
+        self.client
+            .process_async(script, Some(self.tag_process(opts)))
     }
-    if primary.is_empty() {
-        return secondary;
+
+    /// Run an mlld file within the session.
+    pub fn execute<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<ExecuteResult> {
+        self.client
+            .execute(filepath, payload, Some(self.tag_execute(opts)))
+    }
+
+    /// Start an mlld file execution within the session and return the
+    /// in-flight request handle.
+    pub fn execute_async<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<ExecuteHandle> {
+        self.client
+            .execute_async(filepath, payload, Some(self.tag_execute(opts)))
+    }
+
+    /// End the session on the server, releasing its state and caches.
+    pub fn end(self) -> Result<()> {
+        let mut params = serde_json::Map::new();
+        params.insert("sessionId".to_string(), Value::String(self.id.clone()));
+        self.client
+            .request("session:end", Value::Object(params), self.client.timeout)?;
+        Ok(())
     }
 
-    let mut merged = Vec::with_capacity(primary.len() + secondary.len());
-    let mut seen = std::collections::HashSet::new();
-
-    for state_write in primary.into_iter().chain(secondary.into_iter()) {
-        let key = state_write_key(&state_write);
-        if seen.insert(key) {
-            merged.push(state_write);
+    fn tag_process(&self, opts: Option<ProcessOptions>) -> ProcessOptions {
+        let mut opts = opts.unwrap_or_default();
+        opts.session_id = Some(self.id.clone());
+        if opts.worker_labels.is_empty() {
+            opts.worker_labels = self.worker_labels.clone();
         }
+        opts
     }
 
-    merged
+    fn tag_execute(&self, opts: Option<ExecuteOptions>) -> ExecuteOptions {
+        let mut opts = opts.unwrap_or_default();
+        opts.session_id = Some(self.id.clone());
+        if opts.worker_labels.is_empty() {
+            opts.worker_labels = self.worker_labels.clone();
+        }
+        opts
+    }
 }
 
-fn state_write_key(state_write: &StateWrite) -> String {
-    format!(
-        "{}|{}",
-        state_write.path,
-        serde_json::to_string(&state_write.value).unwrap_or_else(|_| "null".to_string())
-    )
+impl std::fmt::Display for ScriptHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
-/// Options for process().
-#[derive(Debug, Default, Clone)]
-pub struct ProcessOptions {
-    /// Provides context for relative imports.
-    pub file_path: Option<String>,
+/// Serializable snapshot of a suspended execution, produced by
+/// `suspend()` and consumed by [`Client::resume`]. The `state` blob is
+/// opaque interpreter state (loop counters, `@state`, environment) and
+/// is only guaranteed to restore on a compatible CLI version.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionSnapshot {
+    /// Opaque interpreter state captured at suspension.
+    pub state: Value,
 
-    /// Data injected as @payload.
-    pub payload: Option<Value>,
+    /// Script or file the execution was running, when known.
+    pub filepath: Option<String>,
 
-    /// Per-field security labels for @payload object fields.
-    pub payload_labels: Option<HashMap<String, Vec<String>>>,
+    /// Version of the mlld CLI that produced the snapshot.
+    pub cli_version: Option<String>,
+}
 
-    /// Data injected as @state.
-    pub state: Option<Value>,
+/// Reproducibility record: exactly what content and versions produced a
+/// result, so stored results can be re-run bit-for-bit later.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Content hash (sha256) of the executed script or file.
+    pub script_hash: String,
 
-    /// Additional modules to inject.
-    pub dynamic_modules: Option<HashMap<String, Value>>,
+    /// Resolved versions of all imported modules, keyed by module name.
+    #[serde(default)]
+    pub modules: HashMap<String, String>,
 
-    /// Source label added to dynamic modules.
-    pub dynamic_module_source: Option<String>,
+    /// Version of the mlld CLI that produced the result.
+    pub cli_version: String,
+}
 
-    /// Parsing mode (strict|markdown).
-    pub mode: Option<String>,
+/// An output effect from execution.
+///
+/// Effects arrive as tagged JSON; unrecognized types are preserved in
+/// [`Effect::Unknown`] so new interpreter effect kinds round-trip
+/// instead of failing deserialization.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Content appended to the rendered document.
+    Doc {
+        content: String,
+        security: Option<Value>,
+    },
 
-    /// Allow absolute path access.
-    pub allow_absolute_paths: Option<bool>,
+    /// Content shown to the user without joining the document.
+    Show {
+        content: String,
+        security: Option<Value>,
+    },
 
-    /// Override the client default timeout.
-    pub timeout: Option<Duration>,
+    /// Content written to the interpreter's stderr channel.
+    Stderr { content: String },
+
+    /// A file written (or captured) by the script.
+    FileWrite {
+        path: String,
+        content: Option<String>,
+        security: Option<Value>,
+    },
+
+    /// An effect type this SDK version does not know, kept as raw JSON.
+    Unknown(Value),
 }
 
-/// Options for execute().
-#[derive(Debug, Default, Clone)]
-pub struct ExecuteOptions {
-    /// Per-field security labels for @payload object fields.
-    pub payload_labels: Option<HashMap<String, Vec<String>>>,
+impl Effect {
+    fn from_value(value: Value) -> Self {
+        let Some(effect_type) = value.get("type").and_then(Value::as_str) else {
+            return Self::Unknown(value);
+        };
 
-    /// Data injected as @state.
-    pub state: Option<Value>,
+        let content = value
+            .get("content")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        let security = value.get("security").cloned();
 
-    /// Additional modules to inject.
-    pub dynamic_modules: Option<HashMap<String, Value>>,
+        match effect_type {
+            "doc" => Self::Doc {
+                content: content.unwrap_or_default(),
+                security,
+            },
+            "show" => Self::Show {
+                content: content.unwrap_or_default(),
+                security,
+            },
+            "stderr" => Self::Stderr {
+                content: content.unwrap_or_default(),
+            },
+            "file" => match value.get("path").and_then(Value::as_str) {
+                Some(path) => Self::FileWrite {
+                    path: path.to_string(),
+                    content,
+                    security,
+                },
+                None => Self::Unknown(value),
+            },
+            _ => Self::Unknown(value),
+        }
+    }
 
-    /// Source label added to dynamic modules.
-    pub dynamic_module_source: Option<String>,
+    fn to_value(&self) -> Value {
+        fn tagged(
+            effect_type: &str,
+            content: Option<&str>,
+            path: Option<&str>,
+            security: Option<&Value>,
+        ) -> Value {
+            let mut map = serde_json::Map::new();
+            map.insert("type".to_string(), json!(effect_type));
+            if let Some(path) = path {
+                map.insert("path".to_string(), json!(path));
+            }
+            if let Some(content) = content {
+                map.insert("content".to_string(), json!(content));
+            }
+            if let Some(security) = security {
+                map.insert("security".to_string(), security.clone());
+            }
+            Value::Object(map)
+        }
 
-    /// Parsing mode (strict|markdown).
-    pub mode: Option<String>,
+        match self {
+            Self::Doc { content, security } => {
+                tagged("doc", Some(content), None, security.as_ref())
+            }
+            Self::Show { content, security } => {
+                tagged("show", Some(content), None, security.as_ref())
+            }
+            Self::Stderr { content } => tagged("stderr", Some(content), None, None),
+            Self::FileWrite {
+                path,
+                content,
+                security,
+            } => tagged("file", content.as_deref(), Some(path), security.as_ref()),
+            Self::Unknown(value) => value.clone(),
+        }
+    }
+}
 
-    /// Allow absolute path access.
-    pub allow_absolute_paths: Option<bool>,
+/// Rebuild the rendered document from an ordered effect log by joining
+/// doc chunks in arrival order — the same composition the CLI performs
+/// when printing a run. Show, stderr, and file effects never join the
+/// document, so hosts that collect effects incrementally can call this
+/// at any point to reproduce the canonical output so far.
+pub fn render(effects: &[Effect]) -> String {
+    let mut document = String::new();
+    for effect in effects {
+        if let Effect::Doc { content, .. } = effect {
+            document.push_str(content);
+        }
+    }
+    document
+}
 
-    /// Override the client default timeout.
-    pub timeout: Option<Duration>,
+impl<'de> Deserialize<'de> for Effect {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        Ok(Self::from_value(Value::deserialize(deserializer)?))
+    }
 }
 
-/// Structured output from execute().
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ExecuteResult {
-    pub output: String,
+impl Serialize for Effect {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
 
-    #[serde(default)]
-    pub state_writes: Vec<StateWrite>,
+/// One script feature a target CLI version does not support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatIssue {
+    /// Feature the script uses.
+    pub feature: String,
 
-    #[serde(default)]
-    pub exports: Value,
+    /// First CLI version that supports it.
+    pub required_version: String,
+}
 
-    #[serde(default)]
-    pub effects: Vec<Effect>,
+/// Outcome of [`Client::check_compat`]: every feature the script uses
+/// that the target CLI version predates.
+#[derive(Debug, Clone)]
+pub struct CompatReport {
+    /// CLI version the script was checked against.
+    pub target_version: String,
 
-    #[serde(default)]
-    pub denials: Vec<GuardDenial>,
+    pub issues: Vec<CompatIssue>,
+}
 
-    pub metrics: Option<Metrics>,
+impl CompatReport {
+    /// Whether the script can run on the target version.
+    pub fn is_compatible(&self) -> bool {
+        self.issues.is_empty()
+    }
 }
 
-/// An output effect from execution.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Effect {
-    #[serde(rename = "type")]
-    pub effect_type: String,
-    pub content: Option<String>,
-    pub security: Option<Value>,
+/// A non-fatal interpreter warning — deprecated syntax, lossy
+/// coercions, retry exhaustion with fallback — surfaced separately
+/// from errors so hosts can log and trend them before they harden into
+/// failures.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub message: String,
+
+    /// Stable warning code, when the interpreter assigns one.
+    #[serde(default)]
+    pub code: Option<String>,
+
+    /// File the warning points at, when known.
+    #[serde(default)]
+    pub path: Option<String>,
 }
 
 /// Structured information about a denied guard/policy decision.
@@ -1086,6 +8037,72 @@ pub struct StateWrite {
     pub timestamp: Option<String>,
 }
 
+impl StateWrite {
+    /// The written value as an integer. Accepts numeric strings, since
+    /// interpolated state values sometimes arrive stringified.
+    pub fn as_i64(&self) -> Option<i64> {
+        match &self.value {
+            Value::Number(number) => number.as_i64(),
+            Value::String(text) => text.parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+
+    /// The written value as a string slice, when it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        self.value.as_str()
+    }
+
+    /// The written value as a boolean. Accepts `"true"`/`"false"`
+    /// strings, since interpolated state values sometimes arrive
+    /// stringified.
+    pub fn as_bool(&self) -> Option<bool> {
+        match &self.value {
+            Value::Bool(flag) => Some(*flag),
+            Value::String(text) => match text.as_str() {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Deserialize the written value into `T`, with the failing field
+    /// path included in the error.
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        deserialize_with_path::<T>(self.value.clone())
+            .map_err(|error| Error::ResultParse(format!("state write at {}: {error}", self.path)))
+    }
+}
+
+/// A map-style view over a run's state writes, keyed by path.
+///
+/// Wraps the [`state_writes`](ExecuteResult::state_writes) slice
+/// without copying it; [`get`](Self::get) returns the last write to a
+/// path, matching what the state tree holds after the run.
+#[derive(Debug, Clone, Copy)]
+pub struct StateWrites<'a> {
+    writes: &'a [StateWrite],
+}
+
+impl<'a> StateWrites<'a> {
+    /// A view over `writes`.
+    pub fn new(writes: &'a [StateWrite]) -> Self {
+        Self { writes }
+    }
+
+    /// The last write to `path`, or `None` when the run never wrote it.
+    pub fn get(&self, path: &str) -> Option<&'a StateWrite> {
+        self.writes.iter().rev().find(|write| write.path == path)
+    }
+
+    /// The last value written to `path`.
+    pub fn value(&self, path: &str) -> Option<&'a Value> {
+        self.get(path).map(|write| &write.value)
+    }
+}
+
 /// Execution statistics.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -1093,6 +8110,15 @@ pub struct Metrics {
     pub total_ms: f64,
     pub parse_ms: f64,
     pub evaluate_ms: f64,
+
+    /// CPU time the request's environment consumed, sampled by the live
+    /// server; absent from older CLIs. Lets multi-tenant hosts attribute
+    /// interpreter cost to specific tenants and scripts.
+    pub cpu_ms: Option<f64>,
+
+    /// Peak resident memory of the request's environment in bytes,
+    /// sampled by the live server; absent from older CLIs.
+    pub peak_rss_bytes: Option<u64>,
 }
 
 /// Static analysis of an mlld module.
@@ -1119,6 +8145,43 @@ pub struct AnalyzeResult {
     pub needs: Option<Needs>,
 }
 
+/// Combined validation and execution output from execute_project().
+#[derive(Debug, Clone)]
+pub struct ProjectRun {
+    /// Analyze output for the entry file and every local import reachable
+    /// from it.
+    pub analyses: Vec<AnalyzeResult>,
+
+    /// Import resolution preview for the entry file, checked against the
+    /// lockfile.
+    pub imports: ImportResolutionReport,
+
+    /// Execution result; `None` when validation failed and the entry was
+    /// not run.
+    pub result: Option<ExecuteResult>,
+}
+
+impl ProjectRun {
+    /// Whether every analyzed file in the project is valid.
+    pub fn is_valid(&self) -> bool {
+        self.analyses.iter().all(|analysis| analysis.valid)
+    }
+
+    /// All analysis errors across the project, paired with the file that
+    /// produced them.
+    pub fn errors(&self) -> Vec<(&str, &AnalysisError)> {
+        self.analyses
+            .iter()
+            .flat_map(|analysis| {
+                analysis
+                    .errors
+                    .iter()
+                    .map(move |error| (analysis.filepath.as_str(), error))
+            })
+            .collect()
+    }
+}
+
 /// A parse or analysis error.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AnalysisError {
@@ -1149,27 +8212,307 @@ pub struct Import {
     pub names: Vec<String>,
 }
 
-/// A guard defined in a module.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Guard {
-    pub name: String,
-    pub timing: String,
-    pub label: Option<String>,
+/// A guard defined in a module.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Guard {
+    pub name: String,
+    pub timing: String,
+    pub label: Option<String>,
+}
+
+/// Resolution preview for every import in a module.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImportResolutionReport {
+    pub filepath: String,
+
+    #[serde(default)]
+    pub imports: Vec<ImportResolution>,
+}
+
+/// How a single import resolves, without executing the module.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImportResolution {
+    /// Import source as written (`@author/module`, path, URL).
+    #[serde(rename = "from")]
+    pub from: String,
+
+    /// Resolver that claimed the import (registry, local, http, ...).
+    pub resolver: String,
+
+    /// Resolved target: local path, URL, or registry coordinate.
+    pub resolved: String,
+
+    /// Registry version the import pins to, when applicable.
+    pub version: Option<String>,
+
+    /// Whether the resolved content is already in the local cache.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Capability requirements for a module.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Needs {
+    #[serde(default)]
+    pub cmd: Vec<String>,
+
+    #[serde(default)]
+    pub node: Vec<String>,
+
+    #[serde(default)]
+    pub py: Vec<String>,
+}
+
+impl Needs {
+    /// Node package requirements with parsed version ranges.
+    pub fn node_packages(&self) -> Vec<PackageNeed> {
+        self.node
+            .iter()
+            .map(|raw| PackageNeed::parse(raw))
+            .collect()
+    }
+
+    /// Python package requirements with parsed version ranges.
+    pub fn py_packages(&self) -> Vec<PackageNeed> {
+        self.py.iter().map(|raw| PackageNeed::parse(raw)).collect()
+    }
+}
+
+/// One package requirement parsed from a needs entry like `sharp@^0.33`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageNeed {
+    /// Package name, including any scope (`@img/sharp`).
+    pub package: String,
+
+    /// Raw version range, when one was given (`^0.33`, `>=1.2.3`).
+    pub range: Option<String>,
+}
+
+impl PackageNeed {
+    /// Parse a `name@range` needs entry; the range is optional. A leading
+    /// `@` belongs to the scope, not the range separator.
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        let split_from = if raw.starts_with('@') { 1 } else { 0 };
+
+        match raw[split_from..].find('@') {
+            Some(at) => Self {
+                package: raw[..split_from + at].to_string(),
+                range: Some(raw[split_from + at + 1..].to_string()),
+            },
+            None => Self {
+                package: raw.to_string(),
+                range: None,
+            },
+        }
+    }
+
+    /// Whether an installed version satisfies this requirement. A missing
+    /// or unparsable range accepts any version.
+    pub fn matches(&self, installed: &str) -> bool {
+        let Some(range) = &self.range else {
+            return true;
+        };
+        let Some(installed) = SemVersion::parse(installed) else {
+            return false;
+        };
+
+        range_matches(range, &installed)
+    }
+}
+
+/// Three-part version used for needs range checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SemVersion {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl SemVersion {
+    fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().trim_start_matches('v');
+        let core = raw.split(['-', '+']).next()?;
+        let mut parts = core.split('.');
+
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        let patch = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+/// First CLI version supporting each directive. Conservative: extended
+/// as releases add syntax, and directives absent from the table are
+/// assumed to be supported everywhere.
+#[cfg(feature = "client")]
+const DIRECTIVE_VERSIONS: &[(&str, &str)] = &[
+    ("when", "1.3.0"),
+    ("for", "1.4.0"),
+    ("export", "1.4.4"),
+    ("guard", "2.0.0"),
+    ("loop", "2.0.3"),
+];
+
+/// Features used by `source` that `target` predates, from the
+/// directive matrix plus structured analyze data.
+#[cfg(feature = "client")]
+fn compat_issues(source: &str, analysis: &AnalyzeResult, target: &SemVersion) -> Vec<CompatIssue> {
+    let mut issues = Vec::new();
+    let mut push = |feature: &str, required: &str| {
+        if version_before(target, required) {
+            issues.push(CompatIssue {
+                feature: feature.to_string(),
+                required_version: required.to_string(),
+            });
+        }
+    };
+
+    for (directive, required) in DIRECTIVE_VERSIONS {
+        if source_uses_directive(source, directive) {
+            push(&format!("{directive} directive"), required);
+        }
+    }
+    if analysis.needs.is_some() {
+        push("needs declarations", "1.4.0");
+    }
+    if !analysis.guards.is_empty() {
+        push("guards", "2.0.0");
+    }
+
+    issues
+}
+
+#[cfg(feature = "client")]
+fn version_before(target: &SemVersion, required: &str) -> bool {
+    match SemVersion::parse(required) {
+        Some(required) => {
+            (target.major, target.minor, target.patch)
+                < (required.major, required.minor, required.patch)
+        }
+        None => false,
+    }
+}
+
+/// Whether any line of `source` starts with the named directive.
+#[cfg(feature = "client")]
+fn source_uses_directive(source: &str, name: &str) -> bool {
+    source.lines().any(|line| {
+        let Some(rest) = line.trim_start().strip_prefix('/') else {
+            return false;
+        };
+        rest.starts_with(name)
+            && rest[name.len()..]
+                .chars()
+                .next()
+                .is_none_or(|next| !next.is_ascii_alphanumeric())
+    })
+}
+
+fn range_matches(range: &str, installed: &SemVersion) -> bool {
+    let range = range.trim();
+
+    if range.is_empty() || range == "*" {
+        return true;
+    }
+
+    if let Some(rest) = range.strip_prefix('^') {
+        let Some(base) = SemVersion::parse(rest) else {
+            return false;
+        };
+        // Caret: compatible with the leftmost non-zero component.
+        let upper = if base.major > 0 {
+            SemVersion {
+                major: base.major + 1,
+                minor: 0,
+                patch: 0,
+            }
+        } else if base.minor > 0 {
+            SemVersion {
+                major: 0,
+                minor: base.minor + 1,
+                patch: 0,
+            }
+        } else {
+            SemVersion {
+                major: 0,
+                minor: 0,
+                patch: base.patch + 1,
+            }
+        };
+        return *installed >= base && *installed < upper;
+    }
+
+    if let Some(rest) = range.strip_prefix('~') {
+        let Some(base) = SemVersion::parse(rest) else {
+            return false;
+        };
+        let upper = SemVersion {
+            major: base.major,
+            minor: base.minor + 1,
+            patch: 0,
+        };
+        return *installed >= base && *installed < upper;
+    }
+
+    for (op, rest) in [
+        (">=", range.strip_prefix(">=")),
+        ("<=", range.strip_prefix("<=")),
+        (">", range.strip_prefix('>')),
+        ("<", range.strip_prefix('<')),
+        ("=", range.strip_prefix('=')),
+    ] {
+        let Some(rest) = rest else { continue };
+        let Some(base) = SemVersion::parse(rest) else {
+            return false;
+        };
+        return match op {
+            ">=" => *installed >= base,
+            "<=" => *installed <= base,
+            ">" => *installed > base,
+            "<" => *installed < base,
+            _ => *installed == base,
+        };
+    }
+
+    SemVersion::parse(range).is_some_and(|base| *installed == base)
+}
+
+/// Rust types usable as mlld payloads, normally implemented with
+/// `#[derive(MlldPayload)]` from the `derive` feature.
+pub trait MlldPayload: Serialize {
+    /// State/update paths for each field, in declaration order.
+    fn field_paths() -> &'static [&'static str];
+
+    /// JSON Schema describing this payload shape.
+    fn schema() -> Value;
+
+    /// Serialize into the JSON value sent as `@payload`.
+    fn payload_value(&self) -> Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
 }
 
-/// Capability requirements for a module.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Needs {
-    #[serde(default)]
-    pub cmd: Vec<String>,
+/// Rust types deserializable from mlld module exports, normally
+/// implemented with `#[derive(MlldExports)]` from the `derive` feature.
+pub trait MlldExports: serde::de::DeserializeOwned {
+    /// Export paths for each field, in declaration order.
+    fn field_paths() -> &'static [&'static str];
 
-    #[serde(default)]
-    pub node: Vec<String>,
+    /// JSON Schema describing the expected exports shape.
+    fn schema() -> Value;
 
-    #[serde(default)]
-    pub py: Vec<String>,
+    /// Deserialize from an exports value.
+    fn from_exports(exports: &Value) -> Result<Self> {
+        Ok(serde_json::from_value(exports.clone())?)
+    }
 }
 
+#[cfg(feature = "client")]
 fn default_client() -> &'static Client {
     static DEFAULT_CLIENT: OnceLock<Client> = OnceLock::new();
     DEFAULT_CLIENT.get_or_init(Client::new)
@@ -1178,11 +8521,13 @@ fn default_client() -> &'static Client {
 // Convenience functions using a default client
 
 /// Execute an mlld script.
+#[cfg(feature = "client")]
 pub fn process(script: &str, opts: Option<ProcessOptions>) -> Result<String> {
     default_client().process(script, opts)
 }
 
 /// Run an mlld file.
+#[cfg(feature = "client")]
 pub fn execute<P: Serialize>(
     filepath: &str,
     payload: Option<P>,
@@ -1192,11 +8537,12 @@ pub fn execute<P: Serialize>(
 }
 
 /// Analyze an mlld module.
+#[cfg(feature = "client")]
 pub fn analyze(filepath: &str) -> Result<AnalyzeResult> {
     default_client().analyze(filepath)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client"))]
 mod tests {
     use super::*;
     use std::fs;
@@ -1224,6 +8570,906 @@ mod tests {
         assert_eq!(client.working_dir, Some("/tmp".to_string()));
     }
 
+    #[test]
+    fn test_read_frame_line_drops_oversized_frames_and_resyncs() {
+        let input = format!("short\n{}\nnext\n", "x".repeat(100));
+        let mut reader = std::io::BufReader::new(input.as_bytes());
+
+        match read_frame_line(&mut reader, 32).expect("read") {
+            Some(FrameLine::Line(line)) => assert_eq!(line, "short"),
+            other => panic!("expected short line, got {:?}", other.is_some()),
+        }
+        match read_frame_line(&mut reader, 32).expect("read") {
+            Some(FrameLine::Oversized(bytes)) => assert_eq!(bytes, 100),
+            other => panic!("expected oversized frame, got {:?}", other.is_some()),
+        }
+        match read_frame_line(&mut reader, 32).expect("read") {
+            Some(FrameLine::Line(line)) => assert_eq!(line, "next"),
+            other => panic!("expected resynced line, got {:?}", other.is_some()),
+        }
+        assert!(read_frame_line(&mut reader, 32).expect("read").is_none());
+    }
+
+    #[test]
+    fn test_read_length_prefixed_frame_decodes_and_skips_oversized() {
+        let mut input: Vec<u8> = Vec::new();
+        let first = "{\"result\":{\"id\":1}}";
+        input.extend_from_slice(&(first.len() as u32).to_be_bytes());
+        input.extend_from_slice(first.as_bytes());
+        let big = "x".repeat(100);
+        input.extend_from_slice(&(big.len() as u32).to_be_bytes());
+        input.extend_from_slice(big.as_bytes());
+        let last = "tail\nwith newline";
+        input.extend_from_slice(&(last.len() as u32).to_be_bytes());
+        input.extend_from_slice(last.as_bytes());
+
+        let mut reader = std::io::BufReader::new(input.as_slice());
+        match read_length_prefixed_frame(&mut reader, 32, Compression::None).expect("read") {
+            Some(FrameLine::Line(line)) => assert_eq!(line, first),
+            other => panic!("expected first frame, got {:?}", other.is_some()),
+        }
+        match read_length_prefixed_frame(&mut reader, 32, Compression::None).expect("read") {
+            Some(FrameLine::Oversized(bytes)) => assert_eq!(bytes, 100),
+            other => panic!("expected oversized frame, got {:?}", other.is_some()),
+        }
+        match read_length_prefixed_frame(&mut reader, 32, Compression::None).expect("read") {
+            Some(FrameLine::Line(line)) => assert_eq!(line, last),
+            other => panic!("expected resynced frame, got {:?}", other.is_some()),
+        }
+        assert!(
+            read_length_prefixed_frame(&mut reader, 32, Compression::None)
+                .expect("read")
+                .is_none()
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_gzip_frames_round_trip() {
+        let frame = "{\"result\":{\"id\":7}}";
+        let compressed = gzip_compress(frame.as_bytes()).expect("compress");
+        let mut input: Vec<u8> = Vec::new();
+        input.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        input.extend_from_slice(&compressed);
+
+        let mut reader = std::io::BufReader::new(input.as_slice());
+        match read_length_prefixed_frame(&mut reader, 1024, Compression::Gzip).expect("read") {
+            Some(FrameLine::Line(line)) => assert_eq!(line, frame),
+            other => panic!("expected decompressed frame, got {:?}", other.is_some()),
+        }
+        assert!(
+            read_length_prefixed_frame(&mut reader, 1024, Compression::Gzip)
+                .expect("read")
+                .is_none()
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_payload_generates_paths_and_schema() {
+        #[derive(Serialize, crate::MlldPayload)]
+        struct Ticket {
+            title: String,
+            priority: i64,
+            assignee: Option<String>,
+        }
+
+        assert_eq!(
+            <Ticket as crate::MlldPayload>::field_paths(),
+            ["title", "priority", "assignee"]
+        );
+
+        let schema = <Ticket as crate::MlldPayload>::schema();
+        assert_eq!(schema["properties"]["title"]["type"], "string");
+        assert_eq!(schema["properties"]["priority"]["type"], "integer");
+        assert_eq!(schema["required"], json!(["title", "priority"]));
+
+        let ticket = Ticket {
+            title: "fix".to_string(),
+            priority: 1,
+            assignee: None,
+        };
+        let value = crate::MlldPayload::payload_value(&ticket).expect("serializes");
+        assert_eq!(value["title"], "fix");
+    }
+
+    #[test]
+    fn test_parse_envelope_validates_schema() {
+        assert!(matches!(
+            parse_envelope(r#"{"result":{"id":1,"output":"hi"}}"#),
+            Ok(Envelope::Result(_))
+        ));
+        assert!(matches!(
+            parse_envelope(r#"{"event":{"id":"2","type":"state:write"}}"#),
+            Ok(Envelope::Event(_))
+        ));
+
+        let both = parse_envelope(r#"{"result":{"id":1},"event":{"id":1}}"#).unwrap_err();
+        assert!(both.contains("both result and event"));
+
+        let neither = parse_envelope(r#"{"hello":true}"#).unwrap_err();
+        assert!(neither.contains("neither result nor event"));
+
+        let bad_id = parse_envelope(r#"{"result":{"id":{"nested":1}}}"#).unwrap_err();
+        assert!(bad_id.contains("result.id"));
+
+        let missing_id = parse_envelope(r#"{"event":{"type":"x"}}"#).unwrap_err();
+        assert!(missing_id.contains("event.id is missing"));
+
+        let long = format!(r#"not json {}"#, "y".repeat(400));
+        let error = parse_envelope(&long).unwrap_err();
+        assert!(error.contains("bytes total"));
+    }
+
+    #[test]
+    fn test_effect_round_trips_typed_and_unknown_variants() {
+        let effects: Vec<Effect> = serde_json::from_value(json!([
+            { "type": "doc", "content": "hello" },
+            { "type": "show", "content": "progress", "security": { "labels": ["public"] } },
+            { "type": "stderr", "content": "warn" },
+            { "type": "file", "path": "out/report.md", "content": "# done" },
+            { "type": "telemetry", "span": "abc" }
+        ]))
+        .unwrap();
+
+        assert!(matches!(&effects[0], Effect::Doc { content, .. } if content == "hello"));
+        assert!(matches!(
+            &effects[1],
+            Effect::Show {
+                security: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(&effects[2], Effect::Stderr { content } if content == "warn"));
+        assert!(matches!(
+            &effects[3],
+            Effect::FileWrite { path, .. } if path == "out/report.md"
+        ));
+        assert!(matches!(&effects[4], Effect::Unknown(_)));
+
+        let round_tripped = serde_json::to_value(&effects).unwrap();
+        assert_eq!(round_tripped[0]["type"], "doc");
+        assert_eq!(round_tripped[3]["path"], "out/report.md");
+        assert_eq!(round_tripped[4]["span"], "abc");
+    }
+
+    #[test]
+    fn test_render_joins_doc_chunks_in_order() {
+        let effects = vec![
+            Effect::Doc {
+                content: "# Title\n\n".to_string(),
+                security: None,
+            },
+            Effect::Show {
+                content: "progress".to_string(),
+                security: None,
+            },
+            Effect::Stderr {
+                content: "warn: slow".to_string(),
+            },
+            Effect::Doc {
+                content: "body".to_string(),
+                security: None,
+            },
+            Effect::FileWrite {
+                path: "out/report.md".to_string(),
+                content: None,
+                security: None,
+            },
+        ];
+
+        assert_eq!(render(&effects), "# Title\n\nbody");
+        assert_eq!(render(&[]), "");
+    }
+
+    #[test]
+    fn test_chaos_rng_is_deterministic_and_respects_bounds() {
+        let opts = ChaosOptions {
+            drop_frame: 0.5,
+            seed: 42,
+            ..ChaosOptions::default()
+        };
+        let first: Vec<bool> = {
+            let rng = ChaosRng::new(opts.clone());
+            (0..32).map(|_| rng.should(0.5)).collect()
+        };
+        let second: Vec<bool> = {
+            let rng = ChaosRng::new(opts.clone());
+            (0..32).map(|_| rng.should(0.5)).collect()
+        };
+        assert_eq!(first, second);
+        assert!(first.iter().any(|hit| *hit));
+        assert!(first.iter().any(|hit| !*hit));
+
+        let rng = ChaosRng::new(opts);
+        assert!(!rng.should(0.0));
+        assert!(rng.should(1.0));
+    }
+
+    #[test]
+    fn test_backoff_intervals_grow_and_cap() {
+        let backoff = Backoff {
+            initial_interval: Duration::from_millis(25),
+            multiplier: 2.0,
+            max_interval: Duration::from_millis(100),
+            jitter: 0.0,
+        };
+
+        let intervals: Vec<Duration> = backoff.intervals().take(5).collect();
+        assert_eq!(
+            intervals,
+            vec![
+                Duration::from_millis(25),
+                Duration::from_millis(50),
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+            ]
+        );
+
+        let jittered = Backoff {
+            jitter: 0.5,
+            ..backoff
+        };
+        for interval in jittered.intervals().take(16) {
+            assert!(interval <= Duration::from_millis(150));
+        }
+    }
+
+    #[test]
+    fn test_compat_issues_flags_features_newer_than_target() {
+        let source = "/loop @agent()\n/var @x = 1\n";
+        let analysis = AnalyzeResult {
+            filepath: "inline.mld".to_string(),
+            valid: true,
+            errors: Vec::new(),
+            executables: Vec::new(),
+            exports: Vec::new(),
+            imports: Vec::new(),
+            guards: Vec::new(),
+            needs: None,
+        };
+
+        let old_cli = SemVersion::parse("2.0.0").unwrap();
+        let issues = compat_issues(source, &analysis, &old_cli);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].feature, "loop directive");
+        assert_eq!(issues[0].required_version, "2.0.3");
+
+        let new_cli = SemVersion::parse("2.0.3").unwrap();
+        assert!(compat_issues(source, &analysis, &new_cli).is_empty());
+        assert!(!source_uses_directive("show \"/loop\"", "when"));
+    }
+
+    #[test]
+    fn test_result_cache_key_ignores_pin_insertion_order() {
+        let first = ProcessOptions::default()
+            .pin("@corp/tools", "1.2.0")
+            .pin("@corp/utils", "2.0.1");
+        let second = ProcessOptions::default()
+            .pin("@corp/utils", "2.0.1")
+            .pin("@corp/tools", "1.2.0");
+
+        assert_eq!(
+            process_cache_key("show \"hi\"", &first),
+            process_cache_key("show \"hi\"", &second)
+        );
+        assert_ne!(
+            process_cache_key("show \"hi\"", &first),
+            process_cache_key("show \"bye\"", &first)
+        );
+
+        let markdown = ProcessOptions {
+            mode: Some("markdown".to_string()),
+            ..ProcessOptions::default()
+        };
+        let with_modules = ProcessOptions {
+            dynamic_modules: Some(HashMap::from([("@config".to_string(), json!({}))])),
+            ..ProcessOptions::default()
+        };
+        let base_key = process_cache_key("show \"hi\"", &ProcessOptions::default());
+        assert_ne!(base_key, process_cache_key("show \"hi\"", &markdown));
+        assert_ne!(base_key, process_cache_key("show \"hi\"", &with_modules));
+    }
+
+    #[test]
+    fn test_exports_as_deserializes_typed_exports() {
+        #[derive(Deserialize)]
+        struct Exports {
+            greeting: String,
+        }
+
+        let result = ExecuteResult {
+            exports: json!({ "greeting": "hello" }),
+            ..Default::default()
+        };
+        let typed: Exports = result.exports_as().unwrap();
+        assert_eq!(typed.greeting, "hello");
+
+        let error = result.exports_as::<Vec<String>>().unwrap_err();
+        assert!(matches!(error, Error::ResultParse(_)));
+    }
+
+    #[test]
+    fn test_error_from_payload_maps_cancellation_reasons() {
+        let cancelled = error_from_payload(&json!({
+            "message": "cancelled",
+            "code": "CANCELLED",
+            "reason": "deploy-drain"
+        }));
+        assert!(matches!(
+            cancelled,
+            Error::Cancelled { reason: Some(reason) } if reason == "deploy-drain"
+        ));
+
+        let plain = error_from_payload(&json!({ "message": "boom", "code": "SCRIPT_ERROR" }));
+        assert!(matches!(plain, Error::Mlld { .. }));
+
+        assert_eq!(
+            CancelReason::TimeoutEscalation.as_str(),
+            "timeout-escalation"
+        );
+        assert_eq!(CancelReason::Other("oops".to_string()).as_str(), "oops");
+    }
+
+    #[test]
+    fn test_parse_vm_rss_bytes_reads_proc_status() {
+        let status = "Name:\tnode\nVmPeak:\t  200000 kB\nVmRSS:\t  102400 kB\n";
+        assert_eq!(parse_vm_rss_bytes(status), Some(102400 * 1024));
+        assert_eq!(parse_vm_rss_bytes("Name:\tnode\n"), None);
+    }
+
+    #[test]
+    fn test_parse_output_chunk_event_accepts_show_and_doc_effects() {
+        let show = parse_output_chunk_event(
+            &json!({ "id": 1, "type": "effect", "effect": { "type": "show", "content": "hi" } }),
+        )
+        .expect("show chunk");
+        assert_eq!(show.effect_type, "show");
+        assert_eq!(show.content, "hi");
+
+        assert!(parse_output_chunk_event(
+            &json!({ "id": 1, "type": "effect", "effect": { "type": "file", "content": "x" } })
+        )
+        .is_none());
+        assert!(parse_output_chunk_event(&json!({ "id": 1, "type": "state:write" })).is_none());
+    }
+
+    #[test]
+    fn test_dispatch_result_keeps_partial_results_pending() {
+        let pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let (sender, receiver) = mpsc::channel();
+        pending.lock().unwrap().insert(7, sender);
+
+        dispatch_result(
+            &pending,
+            &OrphanObserver::default(),
+            json!({ "id": 7, "partial": true, "output": "halfway" }),
+        );
+        match receiver.try_recv() {
+            Ok(TransportMessage::Event(event)) => {
+                let partial = parse_partial_result_event(&event).expect("partial payload");
+                assert_eq!(partial["output"], "halfway");
+            }
+            other => panic!("expected partial event, got {other:?}"),
+        }
+        assert!(pending.lock().unwrap().contains_key(&7));
+
+        dispatch_result(
+            &pending,
+            &OrphanObserver::default(),
+            json!({ "id": 7, "output": "done" }),
+        );
+        match receiver.try_recv() {
+            Ok(TransportMessage::Result(result)) => assert_eq!(result["output"], "done"),
+            other => panic!("expected final result, got {other:?}"),
+        }
+        assert!(!pending.lock().unwrap().contains_key(&7));
+    }
+
+    #[test]
+    fn test_orphan_frames_reach_sink_and_counter() {
+        let pending: Arc<Mutex<HashMap<u64, Sender<TransportMessage>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let seen: Arc<Mutex<Vec<OrphanFrame>>> = Arc::new(Mutex::new(Vec::new()));
+        let counter = Arc::new(AtomicU64::new(0));
+        let sink_seen = Arc::clone(&seen);
+        let orphans = OrphanObserver {
+            log: false,
+            counter: Arc::clone(&counter),
+            sink: Some(Arc::new(move |frame: &OrphanFrame| {
+                sink_seen.lock().unwrap().push(frame.clone());
+            })),
+        };
+
+        dispatch_event(
+            &pending,
+            &orphans,
+            json!({ "id": 42, "type": "state:write", "key": "late" }),
+        );
+        dispatch_result(&pending, &orphans, json!({ "id": 42, "output": "late" }));
+        dispatch_result(
+            &pending,
+            &orphans,
+            json!({ "id": FRAMING_REQUEST_ID, "ok": true }),
+        );
+
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].kind, OrphanKind::Event);
+        assert_eq!(seen[1].kind, OrphanKind::Result);
+        assert_eq!(seen[1].payload["output"], "late");
+    }
+
+    #[test]
+    fn test_concurrency_gate_queues_until_release() {
+        let gate = ConcurrencyGate::default();
+        gate.acquire(1, Priority::Normal, None).expect("first slot");
+
+        match gate.acquire(1, Priority::Normal, Some(Duration::from_millis(10))) {
+            Err(Error::Timeout(_)) => {}
+            other => panic!("expected queue timeout, got {other:?}"),
+        }
+
+        gate.release();
+        gate.acquire(1, Priority::Normal, Some(Duration::from_millis(10)))
+            .expect("slot after release");
+    }
+
+    #[test]
+    fn test_concurrency_gate_admits_high_priority_lane_first() {
+        let gate = ConcurrencyGate::default();
+        gate.inner.0.lock().unwrap().waiting[Priority::High as usize] = 1;
+
+        // A free slot is reserved for the queued high-priority waiter.
+        match gate.acquire(1, Priority::Normal, Some(Duration::from_millis(10))) {
+            Err(Error::Timeout(_)) => {}
+            other => panic!("expected queue timeout, got {other:?}"),
+        }
+
+        // Waiters in the same lane contend for it normally.
+        gate.acquire(1, Priority::High, Some(Duration::from_millis(10)))
+            .expect("high-priority slot");
+    }
+
+    #[test]
+    fn test_state_path_patterns_match_single_segments() {
+        assert!(state_path_matches(
+            "agents.*.status",
+            "agents.builder.status"
+        ));
+        assert!(state_path_matches(
+            "agents.builder.status",
+            "agents.builder.status"
+        ));
+        assert!(!state_path_matches(
+            "agents.*.status",
+            "agents.builder.phase"
+        ));
+        assert!(!state_path_matches("agents.*.status", "agents.a.b.status"));
+        assert!(!state_path_matches("agents.*", "agents"));
+        assert!(state_path_matches("*", "agents"));
+    }
+
+    #[test]
+    fn test_state_transaction_entries_omit_op_for_plain_sets() {
+        let updates = vec![
+            ("config.mode".to_string(), json!("fast"), StateOp::Set),
+            (
+                "config.extras".to_string(),
+                json!({"a": 1}),
+                StateOp::MergePatch,
+            ),
+            ("config.legacy".to_string(), Value::Null, StateOp::Delete),
+        ];
+
+        let entries = state_update_entries(&updates);
+        assert_eq!(
+            entries[0],
+            json!({ "path": "config.mode", "value": "fast" })
+        );
+        assert_eq!(entries[1]["op"], json!("merge-patch"));
+        assert_eq!(entries[2]["op"], json!("delete"));
+    }
+
+    #[test]
+    fn test_state_write_accessors_coerce_stringified_values() {
+        let writes = vec![
+            StateWrite {
+                path: "count".to_string(),
+                value: json!("41"),
+                timestamp: None,
+            },
+            StateWrite {
+                path: "count".to_string(),
+                value: json!(42),
+                timestamp: None,
+            },
+            StateWrite {
+                path: "done".to_string(),
+                value: json!("true"),
+                timestamp: None,
+            },
+        ];
+
+        let view = StateWrites::new(&writes);
+        assert_eq!(view.get("count").and_then(StateWrite::as_i64), Some(42));
+        assert_eq!(view.value("count"), Some(&json!(42)));
+        assert_eq!(view.get("done").and_then(StateWrite::as_bool), Some(true));
+        assert!(view.get("missing").is_none());
+        assert_eq!(writes[0].as_i64(), Some(41));
+        assert_eq!(writes[0].as_str(), Some("41"));
+        assert_eq!(view.get("count").unwrap().deserialize::<u32>().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_state_ops_map_to_kebab_case_wire_strings() {
+        assert_eq!(StateOp::default(), StateOp::Set);
+        assert_eq!(StateOp::Set.as_str(), "set");
+        assert_eq!(StateOp::MergePatch.as_str(), "merge-patch");
+        assert_eq!(StateOp::Delete.as_str(), "delete");
+        assert_eq!(StateOp::Append.as_str(), "append");
+    }
+
+    #[test]
+    fn test_time_virtualization_fields_travel_in_process_params() {
+        let opts = ProcessOptions {
+            timezone: Some("Europe/Berlin".to_string()),
+            locale: Some("de-DE".to_string()),
+            now: Some("2026-01-01T00:00:00Z".to_string()),
+            clock_offset_ms: Some(-1500),
+            ..ProcessOptions::default()
+        };
+
+        let params = build_process_params("show \"hi\"", opts).expect("params build");
+        assert_eq!(params["timezone"], json!("Europe/Berlin"));
+        assert_eq!(params["locale"], json!("de-DE"));
+        assert_eq!(params["now"], json!("2026-01-01T00:00:00Z"));
+        assert_eq!(params["clockOffsetMs"], json!(-1500));
+
+        let plain =
+            build_process_params("show \"hi\"", ProcessOptions::default()).expect("params build");
+        assert!(!plain.contains_key("timezone"));
+        assert!(!plain.contains_key("locale"));
+        assert!(!plain.contains_key("now"));
+        assert!(!plain.contains_key("clockOffsetMs"));
+    }
+
+    #[test]
+    fn test_correlation_id_and_tags_travel_in_request_params() {
+        let opts = ProcessOptions {
+            correlation_id: Some("trace-abc123".to_string()),
+            tags: HashMap::from([("service".to_string(), "billing".to_string())]),
+            ..ProcessOptions::default()
+        };
+
+        let params = build_process_params("show \"hi\"", opts).expect("params build");
+        assert_eq!(params["correlationId"], json!("trace-abc123"));
+        assert_eq!(params["tags"]["service"], json!("billing"));
+
+        let plain =
+            build_process_params("show \"hi\"", ProcessOptions::default()).expect("params build");
+        assert!(plain.get("correlationId").is_none());
+        assert!(plain.get("tags").is_none());
+    }
+
+    #[test]
+    fn test_timeout_errors_name_the_phase_that_expired() {
+        let queued = Error::Timeout(Box::new(TimeoutInfo::queued(Duration::from_secs(1))));
+        assert_eq!(queued.to_string(), "queue timeout after 1s");
+
+        let executing = Error::Timeout(Box::new(TimeoutInfo::bare(Duration::from_secs(2))));
+        assert_eq!(executing.to_string(), "execution timeout after 2s");
+    }
+
+    #[test]
+    fn test_metrics_aggregate_request_counters() {
+        let client = Client::new();
+        client.record_latency("process", Duration::from_millis(10), true);
+        client.record_latency("process", Duration::from_millis(20), false);
+        client.record_latency("analyze", Duration::from_millis(5), true);
+
+        let metrics = client.metrics();
+        assert_eq!(metrics.requests, 3);
+        assert_eq!(metrics.failures, 1);
+        assert_eq!(metrics.in_flight, 0);
+        assert_eq!(metrics.queued, 0);
+        assert_eq!(metrics.orphan_frames, 0);
+        assert_eq!(metrics.methods.len(), 2);
+    }
+
+    #[test]
+    fn test_log_lines_parse_json_and_text_prefixes() {
+        let json_line = LogLine::parse(r#"{"level":"warn","message":"slow import","requestId":7}"#);
+        assert_eq!(json_line.level, LogLevel::Warn);
+        assert_eq!(json_line.message, "slow import");
+        assert_eq!(json_line.request_id, Some(7));
+
+        let text_line = LogLine::parse("[ERROR] module not found");
+        assert_eq!(text_line.level, LogLevel::Error);
+        assert_eq!(text_line.message, "module not found");
+        assert_eq!(text_line.request_id, None);
+
+        let plain = LogLine::parse("starting interpreter");
+        assert_eq!(plain.level, LogLevel::Info);
+        assert_eq!(plain.message, "starting interpreter");
+        assert_eq!(plain.raw, "starting interpreter");
+    }
+
+    #[test]
+    fn test_error_codes_round_trip_wire_strings() {
+        assert_eq!(ErrorCode::from_wire("PARSE_ERROR"), ErrorCode::ParseError);
+        assert_eq!(ErrorCode::ParseError.as_str(), "PARSE_ERROR");
+        assert_eq!(
+            ErrorCode::from_wire("SOMETHING_NEW"),
+            ErrorCode::Other("SOMETHING_NEW".to_string())
+        );
+        assert_eq!(
+            ErrorCode::Other("SOMETHING_NEW".to_string()).as_str(),
+            "SOMETHING_NEW"
+        );
+    }
+
+    #[test]
+    fn test_error_payload_diagnostic_fields_are_parsed() {
+        let error = error_from_payload(&json!({
+            "message": "Unknown directive",
+            "code": "PARSE_ERROR",
+            "details": {
+                "filePath": "main.mld",
+                "line": 3,
+                "column": 1,
+                "source": "/vaar greeting = \"hi\"",
+                "directive": "vaar",
+                "hint": "did you mean var?"
+            }
+        }));
+
+        let Error::Mlld {
+            diagnostic: Some(diagnostic),
+            ..
+        } = error
+        else {
+            panic!("expected a diagnostic-bearing mlld error");
+        };
+        assert_eq!(diagnostic.file.as_deref(), Some("main.mld"));
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(diagnostic.column, Some(1));
+        assert_eq!(diagnostic.directive.as_deref(), Some("vaar"));
+        assert_eq!(diagnostic.hint.as_deref(), Some("did you mean var?"));
+
+        let plain = error_from_payload(&json!({ "message": "boom" }));
+        assert!(matches!(
+            plain,
+            Error::Mlld {
+                diagnostic: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_heartbeat_close_message_maps_to_dedicated_error() {
+        assert!(matches!(
+            closed_error(HEARTBEAT_LOST_MESSAGE.to_string()),
+            Error::HeartbeatLost
+        ));
+        assert!(matches!(
+            closed_error("stdout closed".to_string()),
+            Error::Transport(message) if message == "stdout closed"
+        ));
+    }
+
+    #[test]
+    fn test_closing_client_refuses_new_requests() {
+        let client = Client::new();
+        client.closing.store(true, Ordering::SeqCst);
+
+        match client.ping() {
+            Err(Error::Transport(message)) => assert_eq!(message, "client is closing"),
+            other => panic!("expected closing error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_after_consecutive_spawn_failures() {
+        let client = Client::new().with_circuit_breaker(CircuitBreaker {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(60),
+        });
+
+        assert!(client.check_circuit().is_ok());
+        client.record_spawn_failure();
+        assert!(client.check_circuit().is_ok());
+        client.record_spawn_failure();
+        assert!(matches!(
+            client.check_circuit(),
+            Err(Error::CircuitOpen { .. })
+        ));
+
+        client.record_spawn_success();
+        assert!(client.check_circuit().is_ok());
+    }
+
+    #[test]
+    fn test_retry_policy_backoff_grows_and_caps() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_millis(300),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff(8), Duration::from_millis(300));
+
+        assert!(is_transport_disconnect(&Error::Transport(
+            "live transport disconnected".to_string()
+        )));
+        assert!(!is_transport_disconnect(&Error::Transport(
+            "transport lock poisoned".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_token_bucket_spends_burst_then_rate_limits() {
+        let bucket = TokenBucket::default();
+        let limit = RateLimit {
+            requests_per_second: 0.001,
+            burst: 2,
+            max_wait: None,
+        };
+
+        bucket.take(&limit).expect("first burst token");
+        bucket.take(&limit).expect("second burst token");
+        match bucket.take(&limit) {
+            Err(Error::RateLimited { retry_after }) => assert!(retry_after > Duration::ZERO),
+            other => panic!("expected rate limit, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_trace_ring_keeps_last_requests() {
+        let client = Client::new().with_trace_limit(2);
+
+        for (id, method) in [(1u64, "process"), (2, "execute"), (3, "analyze")] {
+            if let Ok(mut pending) = client.trace_pending.lock() {
+                pending.insert(
+                    id,
+                    PendingTrace {
+                        method: method.to_string(),
+                        params: "{}".to_string(),
+                        started: Instant::now(),
+                        started_at: SystemTime::now(),
+                    },
+                );
+            }
+            client.finish_trace(id, id != 3, "{\"output\":\"done\"}");
+        }
+
+        let traces = client.recent_requests();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].method, "execute");
+        assert!(traces[0].success);
+        assert_eq!(traces[1].method, "analyze");
+        assert!(!traces[1].success);
+        assert_eq!(traces[1].outcome, "{\"output\":\"done\"}");
+
+        let untraced = Client::new();
+        untraced.finish_trace(9, true, "{}");
+        assert!(untraced.recent_requests().is_empty());
+    }
+
+    #[test]
+    fn test_worker_for_labels_matches_profiles() {
+        let client = Client::new()
+            .with_worker_profile(WorkerProfile {
+                labels: vec!["gpu".to_string(), "large-memory".to_string()],
+                ..WorkerProfile::default()
+            })
+            .with_worker_profile(WorkerProfile {
+                labels: vec!["large-memory".to_string()],
+                ..WorkerProfile::default()
+            });
+
+        assert_eq!(client.worker_for_labels(&[]).unwrap(), None);
+        assert_eq!(
+            client.worker_for_labels(&["gpu".to_string()]).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            client
+                .worker_for_labels(&["large-memory".to_string()])
+                .unwrap(),
+            Some(0)
+        );
+        assert!(client.worker_for_labels(&["tpu".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_queue_event_reads_phase_and_depth() {
+        let queued = parse_queue_event(&json!({ "id": 1, "type": "queue:queued", "depth": 4 }))
+            .expect("queued event");
+        assert_eq!(queued.phase, QueuePhase::Queued);
+        assert_eq!(queued.depth, Some(4));
+
+        let started =
+            parse_queue_event(&json!({ "id": 1, "type": "queue:started" })).expect("started event");
+        assert_eq!(started.phase, QueuePhase::Started);
+        assert_eq!(started.depth, None);
+
+        assert!(parse_queue_event(&json!({ "id": 1, "type": "state:write" })).is_none());
+    }
+
+    #[test]
+    fn test_package_need_parses_and_matches_ranges() {
+        let need = PackageNeed::parse("sharp@^0.33");
+        assert_eq!(need.package, "sharp");
+        assert_eq!(need.range.as_deref(), Some("^0.33"));
+        assert!(need.matches("0.33.5"));
+        assert!(!need.matches("0.34.0"));
+
+        let scoped = PackageNeed::parse("@img/sharp@>=1.2.3");
+        assert_eq!(scoped.package, "@img/sharp");
+        assert!(scoped.matches("1.2.3"));
+        assert!(scoped.matches("2.0.0"));
+        assert!(!scoped.matches("1.2.2"));
+
+        let bare = PackageNeed::parse("lodash");
+        assert_eq!(bare.range, None);
+        assert!(bare.matches("4.17.21"));
+
+        let tilde = PackageNeed::parse("left-pad@~1.3");
+        assert!(tilde.matches("1.3.9"));
+        assert!(!tilde.matches("1.4.0"));
+
+        let caret_major = PackageNeed::parse("react@^18.2.0");
+        assert!(caret_major.matches("18.3.1"));
+        assert!(!caret_major.matches("19.0.0"));
+    }
+
+    #[test]
+    fn test_stderr_ring_keeps_most_recent_lines() {
+        let mut ring = StderrRing::new(16);
+        ring.push_line("first line".to_string());
+        ring.push_line("second line".to_string());
+        ring.push_line("third".to_string());
+
+        let contents = ring.contents();
+        assert!(!contents.contains("first line"));
+        assert!(contents.contains("second line"));
+        assert!(contents.contains("third"));
+    }
+
+    #[test]
+    fn test_stats_reports_percentiles_per_method() {
+        let client = Client::new();
+        for ms in [10u64, 20, 30, 40, 50, 60, 70, 80, 90, 100] {
+            client.record_latency("process", Duration::from_millis(ms), true);
+        }
+        client.record_latency("analyze", Duration::from_millis(5), false);
+
+        let stats = client.stats();
+        assert_eq!(stats.methods.len(), 2);
+
+        let analyze = &stats.methods[0];
+        assert_eq!(analyze.method, "analyze");
+        assert_eq!(analyze.count, 1);
+        assert_eq!(analyze.errors, 1);
+
+        let process = &stats.methods[1];
+        assert_eq!(process.method, "process");
+        assert_eq!(process.count, 10);
+        assert_eq!(process.errors, 0);
+        assert_eq!(process.p50, Duration::from_millis(50));
+        assert_eq!(process.p95, Duration::from_millis(100));
+        assert_eq!(process.p99, Duration::from_millis(100));
+    }
+
     #[test]
     fn test_live_execute_roundtrip_with_state_and_dynamic_modules() {
         let cli_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -1303,7 +9549,7 @@ mod tests {
             .iter()
             .find(|write| write.path == "count")
             .expect("first run writes count");
-        assert_eq!(state_write_as_i64(&first_write.value), Some(1));
+        assert_eq!(first_write.as_i64(), Some(1));
 
         let second = client
             .execute(
@@ -1329,7 +9575,7 @@ mod tests {
             .iter()
             .find(|write| write.path == "count")
             .expect("second run writes count");
-        assert_eq!(state_write_as_i64(&second_write.value), Some(2));
+        assert_eq!(second_write.as_i64(), Some(2));
 
         client.close();
         let _ = fs::remove_file(script_path);
@@ -1458,17 +9704,10 @@ mod tests {
         match error {
             Error::Mlld {
                 code: Some(code), ..
-            } => assert_eq!(code, "REQUEST_NOT_FOUND"),
+            } => assert_eq!(code, ErrorCode::RequestNotFound),
             other => panic!("expected REQUEST_NOT_FOUND error, got {other:?}"),
         }
 
         client.close();
     }
-    fn state_write_as_i64(value: &Value) -> Option<i64> {
-        match value {
-            Value::Number(number) => number.as_i64(),
-            Value::String(text) => text.parse::<i64>().ok(),
-            _ => None,
-        }
-    }
 }
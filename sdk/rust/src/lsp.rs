@@ -0,0 +1,326 @@
+//! Minimal typed proxy for the mlld language server.
+//!
+//! Spawns `mlld language-server` and exposes just enough of the LSP
+//! surface for CI annotators and embedded editors: open a document,
+//! collect diagnostics, request completions. Messages use standard
+//! JSON-RPC framing with `Content-Length` headers over stdio.
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Position in a document, zero-based.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// Range in a document.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A diagnostic published by the language server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Option<i64>,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+/// A completion item offered by the language server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: Option<i64>,
+    pub detail: Option<String>,
+}
+
+/// Handle to a running mlld language server.
+pub struct Lsp {
+    child: Child,
+    stdin: ChildStdin,
+    next_id: u64,
+    timeout: Duration,
+    pending: Arc<Mutex<HashMap<u64, Sender<Value>>>>,
+    diagnostics: Arc<Mutex<HashMap<String, Vec<Diagnostic>>>>,
+    reader_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Lsp {
+    /// Spawn the language server using the client's command configuration
+    /// and perform the LSP initialize handshake.
+    pub(crate) fn spawn(client: &crate::Client) -> Result<Self> {
+        let mut args = client.command_args.to_vec();
+        args.push("language-server".to_string());
+
+        let mut cmd = Command::new(&client.command);
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        if let Some(dir) = &client.working_dir {
+            cmd.current_dir(dir);
+        }
+
+        let mut child = cmd.spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::Transport("language server stdin is unavailable".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Transport("language server stdout is unavailable".to_string()))?;
+
+        let pending: Arc<Mutex<HashMap<u64, Sender<Value>>>> = Arc::new(Mutex::new(HashMap::new()));
+        let diagnostics: Arc<Mutex<HashMap<String, Vec<Diagnostic>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+
+        let reader_thread = Some(start_reader_thread(
+            stdout,
+            Arc::clone(&pending),
+            Arc::clone(&diagnostics),
+        ));
+
+        let mut lsp = Self {
+            child,
+            stdin,
+            next_id: 1,
+            timeout: client.timeout.unwrap_or(Duration::from_secs(10)),
+            pending,
+            diagnostics,
+            reader_thread,
+        };
+
+        lsp.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": Value::Null,
+                "capabilities": {}
+            }),
+        )?;
+        lsp.notify("initialized", json!({}))?;
+
+        Ok(lsp)
+    }
+
+    /// Open (or replace) a document so the server analyzes it.
+    pub fn open_document(&mut self, uri: &str, text: &str) -> Result<()> {
+        if let Ok(mut diagnostics) = self.diagnostics.lock() {
+            diagnostics.remove(uri);
+        }
+
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "mlld",
+                    "version": 1,
+                    "text": text
+                }
+            }),
+        )
+    }
+
+    /// Diagnostics most recently published for `uri`, waiting up to the
+    /// client timeout for the first publish after a document open.
+    pub fn diagnostics(&mut self, uri: &str) -> Result<Vec<Diagnostic>> {
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            if let Ok(diagnostics) = self.diagnostics.lock() {
+                if let Some(found) = diagnostics.get(uri) {
+                    return Ok(found.clone());
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout(Box::new(crate::TimeoutInfo::bare(
+                    self.timeout,
+                ))));
+            }
+            thread::sleep(Duration::from_millis(25));
+        }
+    }
+
+    /// Request completions at a document position.
+    pub fn completions(&mut self, uri: &str, position: Position) -> Result<Vec<CompletionItem>> {
+        let result = self.request(
+            "textDocument/completion",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": position
+            }),
+        )?;
+
+        let items = match &result {
+            Value::Array(items) => items.clone(),
+            Value::Object(map) => map
+                .get("items")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        items
+            .into_iter()
+            .map(|item| serde_json::from_value::<CompletionItem>(item).map_err(crate::Error::from))
+            .collect()
+    }
+
+    /// Shut the server down cleanly.
+    pub fn shutdown(mut self) -> Result<()> {
+        let _ = self.request("shutdown", Value::Null);
+        let _ = self.notify("exit", Value::Null);
+        let _ = self.child.wait();
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let (sender, receiver): (Sender<Value>, Receiver<Value>) = mpsc::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, sender);
+        }
+
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        }))?;
+
+        match receiver.recv_timeout(self.timeout) {
+            Ok(response) => {
+                if let Some(error) = response.get("error") {
+                    return Err(Error::Transport(format!(
+                        "language server error for {method}: {error}"
+                    )));
+                }
+                Ok(response.get("result").cloned().unwrap_or(Value::Null))
+            }
+            Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(Box::new(
+                crate::TimeoutInfo::bare(self.timeout),
+            ))),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Transport(
+                "language server connection closed".to_string(),
+            )),
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params
+        }))
+    }
+
+    fn send(&mut self, message: &Value) -> Result<()> {
+        let body = serde_json::to_string(message)?;
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Lsp {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(thread_handle) = self.reader_thread.take() {
+            let _ = thread_handle.join();
+        }
+    }
+}
+
+fn start_reader_thread(
+    stdout: ChildStdout,
+    pending: Arc<Mutex<HashMap<u64, Sender<Value>>>>,
+    diagnostics: Arc<Mutex<HashMap<String, Vec<Diagnostic>>>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+
+        while let Some(message) = read_message(&mut reader) {
+            if message.get("method").and_then(Value::as_str)
+                == Some("textDocument/publishDiagnostics")
+            {
+                let Some(params) = message.get("params") else {
+                    continue;
+                };
+                let Some(uri) = params.get("uri").and_then(Value::as_str) else {
+                    continue;
+                };
+                let published = params
+                    .get("diagnostics")
+                    .and_then(Value::as_array)
+                    .map(|items| {
+                        items
+                            .iter()
+                            .filter_map(|item| {
+                                serde_json::from_value::<Diagnostic>(item.clone()).ok()
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                if let Ok(mut map) = diagnostics.lock() {
+                    map.insert(uri.to_string(), published);
+                }
+                continue;
+            }
+
+            if let Some(id) = message.get("id").and_then(Value::as_u64) {
+                let sender = pending.lock().ok().and_then(|mut map| map.remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(message);
+                }
+            }
+        }
+    })
+}
+
+/// Read one Content-Length framed JSON-RPC message; `None` on EOF or
+/// malformed framing.
+fn read_message<R: BufRead>(reader: &mut R) -> Option<Value> {
+    let mut content_length: Option<usize> = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            break;
+        }
+        if let Some(value) = trimmed.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse().ok();
+        }
+    }
+
+    let length = content_length?;
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
@@ -0,0 +1,348 @@
+//! Pool of live transports for parallel script execution.
+//!
+//! A single mlld process serializes heavy evaluations; a [`Pool`] runs
+//! several `live --stdio` children side by side and dispatches
+//! process/execute calls across them. Sizing is bounded by
+//! [`PoolOptions`]: the pool starts with `min_workers` (optionally
+//! warmed up front) and grows on demand up to `max_workers` while
+//! every worker is busy. Per-worker health counters are exposed
+//! through [`Pool::worker_health`]. Dispatch order is controlled by
+//! [`Strategy`], optionally bounded by a per-worker concurrency cap so
+//! long-running scripts do not starve short requests.
+
+use crate::{Client, Error, ExecuteOptions, ExecuteResult, ProcessOptions, ProcessResult, Result};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How the pool chooses a worker for the next request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Rotate through workers in order.
+    #[default]
+    RoundRobin,
+
+    /// Prefer the worker with the fewest requests in flight.
+    LeastInFlight,
+
+    /// Prefer the worker with the lowest mean request latency,
+    /// breaking ties by in-flight count.
+    LatencyAware,
+}
+
+/// Sizing and warm-up controls for a [`Pool`].
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    /// Workers created when the pool is built.
+    pub min_workers: usize,
+
+    /// Upper bound on workers created under load.
+    pub max_workers: usize,
+
+    /// Spawn each new worker's child process immediately instead of on
+    /// its first request.
+    pub warm: bool,
+
+    /// How workers are chosen for dispatch.
+    pub strategy: Strategy,
+
+    /// Requests a single worker runs at once. Dispatch waits for
+    /// capacity when every worker is at the cap and the pool cannot
+    /// grow; `None` leaves workers uncapped.
+    pub max_in_flight_per_worker: Option<u64>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            min_workers: 1,
+            max_workers: 4,
+            warm: true,
+            strategy: Strategy::default(),
+            max_in_flight_per_worker: None,
+        }
+    }
+}
+
+/// Health counters for one pool worker.
+#[derive(Debug, Clone)]
+pub struct WorkerHealth {
+    /// Requests currently running on the worker.
+    pub in_flight: u64,
+
+    /// Requests the worker has been handed in total.
+    pub requests: u64,
+
+    /// Requests that ended in an error.
+    pub failures: u64,
+
+    /// Mean wall-clock duration of the worker's completed requests.
+    pub average_latency: Duration,
+
+    /// Whether the worker's child process is currently running.
+    pub running: bool,
+}
+
+#[derive(Clone)]
+struct PoolWorker {
+    client: Client,
+    in_flight: Arc<AtomicU64>,
+    requests: Arc<AtomicU64>,
+    failures: Arc<AtomicU64>,
+    latency_total_ms: Arc<AtomicU64>,
+    latency_samples: Arc<AtomicU64>,
+}
+
+impl PoolWorker {
+    fn avg_latency_ms(&self) -> u64 {
+        let samples = self.latency_samples.load(Ordering::Relaxed);
+        if samples == 0 {
+            return 0;
+        }
+        self.latency_total_ms.load(Ordering::Relaxed) / samples
+    }
+}
+
+/// A bounded set of live transports that process/execute calls are
+/// dispatched across.
+pub struct Pool {
+    template: Client,
+    options: PoolOptions,
+    workers: Mutex<Vec<PoolWorker>>,
+    next: AtomicUsize,
+}
+
+impl Pool {
+    /// Build a pool from `client`'s configuration, creating
+    /// `min_workers` workers up front.
+    pub fn new(client: Client, options: PoolOptions) -> Result<Self> {
+        let pool = Self {
+            template: client,
+            options,
+            workers: Mutex::new(Vec::new()),
+            next: AtomicUsize::new(0),
+        };
+
+        {
+            let mut workers = pool.lock_workers()?;
+            while workers.len() < pool.options.min_workers {
+                let worker = pool.new_worker()?;
+                workers.push(worker);
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Execute a script string on a pool worker and return the output.
+    pub fn process(&self, script: &str, opts: Option<ProcessOptions>) -> Result<String> {
+        self.run(|client| client.process(script, opts))
+    }
+
+    /// Execute a script string on a pool worker and return the full
+    /// result.
+    pub fn process_full(
+        &self,
+        script: &str,
+        opts: Option<ProcessOptions>,
+    ) -> Result<ProcessResult> {
+        self.run(|client| client.process_full(script, opts))
+    }
+
+    /// Run an mlld file on a pool worker.
+    pub fn execute<P: Serialize>(
+        &self,
+        filepath: &str,
+        payload: Option<P>,
+        opts: Option<ExecuteOptions>,
+    ) -> Result<ExecuteResult> {
+        self.run(|client| client.execute(filepath, payload, opts))
+    }
+
+    /// Health counters for every worker, in worker order.
+    pub fn worker_health(&self) -> Vec<WorkerHealth> {
+        let Ok(workers) = self.lock_workers() else {
+            return Vec::new();
+        };
+
+        workers
+            .iter()
+            .map(|worker| WorkerHealth {
+                in_flight: worker.in_flight.load(Ordering::SeqCst),
+                requests: worker.requests.load(Ordering::Relaxed),
+                failures: worker.failures.load(Ordering::Relaxed),
+                average_latency: Duration::from_millis(worker.avg_latency_ms()),
+                running: worker.client.transport_running(),
+            })
+            .collect()
+    }
+
+    /// Shut down every worker's child process. Workers are respawned
+    /// on the next dispatch.
+    pub fn close(&self) {
+        if let Ok(workers) = self.lock_workers() {
+            for worker in workers.iter() {
+                worker.client.close();
+            }
+        }
+    }
+
+    fn run<T>(&self, operation: impl FnOnce(&Client) -> Result<T>) -> Result<T> {
+        let worker = self.pick_worker()?;
+        worker.in_flight.fetch_add(1, Ordering::SeqCst);
+        worker.requests.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        let outcome = operation(&worker.client);
+        let elapsed = started.elapsed();
+        worker.in_flight.fetch_sub(1, Ordering::SeqCst);
+        worker
+            .latency_total_ms
+            .fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+        worker.latency_samples.fetch_add(1, Ordering::Relaxed);
+        if outcome.is_err() {
+            worker.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        outcome
+    }
+
+    /// Choose a worker: an idle one per the configured strategy, a
+    /// fresh worker when all are busy and the pool may still grow,
+    /// otherwise the least objectionable busy worker under the
+    /// concurrency cap. Waits when every worker is at the cap.
+    fn pick_worker(&self) -> Result<PoolWorker> {
+        loop {
+            {
+                let mut workers = self.lock_workers()?;
+
+                if workers.is_empty() {
+                    let worker = self.new_worker()?;
+                    workers.push(worker.clone());
+                    return Ok(worker);
+                }
+
+                if let Some(worker) = self.select(&workers, true) {
+                    return Ok(worker);
+                }
+                if workers.len() < self.options.max_workers {
+                    let worker = self.new_worker()?;
+                    workers.push(worker.clone());
+                    return Ok(worker);
+                }
+                if let Some(worker) = self.select(&workers, false) {
+                    return Ok(worker);
+                }
+            }
+
+            // Every worker is at its concurrency cap and the pool
+            // cannot grow; wait for a request to finish.
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Pick among workers the strategy considers admissible:
+    /// `idle_only` restricts the pass to workers with nothing in
+    /// flight, and the per-worker concurrency cap always applies.
+    fn select(&self, workers: &[PoolWorker], idle_only: bool) -> Option<PoolWorker> {
+        let admissible = |worker: &PoolWorker| {
+            let in_flight = worker.in_flight.load(Ordering::SeqCst);
+            if idle_only && in_flight > 0 {
+                return false;
+            }
+            match self.options.max_in_flight_per_worker {
+                Some(cap) => in_flight < cap,
+                None => true,
+            }
+        };
+
+        match self.options.strategy {
+            Strategy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                for offset in 0..workers.len() {
+                    let candidate = &workers[(start + offset) % workers.len()];
+                    if admissible(candidate) {
+                        return Some(candidate.clone());
+                    }
+                }
+                None
+            }
+            Strategy::LeastInFlight => workers
+                .iter()
+                .filter(|worker| admissible(worker))
+                .min_by_key(|worker| worker.in_flight.load(Ordering::SeqCst))
+                .cloned(),
+            Strategy::LatencyAware => workers
+                .iter()
+                .filter(|worker| admissible(worker))
+                .min_by_key(|worker| {
+                    (
+                        worker.avg_latency_ms(),
+                        worker.in_flight.load(Ordering::SeqCst),
+                    )
+                })
+                .cloned(),
+        }
+    }
+
+    fn new_worker(&self) -> Result<PoolWorker> {
+        let client = self.template.detached();
+        if self.options.warm {
+            client.ensure_live()?;
+        }
+
+        Ok(PoolWorker {
+            client,
+            in_flight: Arc::new(AtomicU64::new(0)),
+            requests: Arc::new(AtomicU64::new(0)),
+            failures: Arc::new(AtomicU64::new(0)),
+            latency_total_ms: Arc::new(AtomicU64::new(0)),
+            latency_samples: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    fn lock_workers(&self) -> Result<MutexGuard<'_, Vec<PoolWorker>>> {
+        self.workers
+            .lock()
+            .map_err(|_| Error::Transport("pool worker lock poisoned".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_starts_min_workers_and_tracks_health() {
+        let options = PoolOptions {
+            min_workers: 2,
+            warm: false,
+            ..PoolOptions::default()
+        };
+        let pool = Pool::new(Client::new(), options).expect("pool");
+
+        let health = pool.worker_health();
+        assert_eq!(health.len(), 2);
+        assert!(health
+            .iter()
+            .all(|worker| worker.requests == 0 && !worker.running));
+    }
+
+    #[test]
+    fn test_pool_grows_when_workers_hit_the_concurrency_cap() {
+        let options = PoolOptions {
+            min_workers: 1,
+            max_workers: 2,
+            warm: false,
+            strategy: Strategy::LeastInFlight,
+            max_in_flight_per_worker: Some(1),
+        };
+        let pool = Pool::new(Client::new(), options).expect("pool");
+        pool.lock_workers().unwrap()[0]
+            .in_flight
+            .store(1, Ordering::SeqCst);
+
+        let picked = pool.pick_worker().expect("worker");
+        assert_eq!(picked.in_flight.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.worker_health().len(), 2);
+    }
+}
@@ -0,0 +1,79 @@
+//! Prometheus metric collectors for the live client, behind the
+//! `prometheus` feature. The client records into these alongside its
+//! built-in latency windows; applications scrape via
+//! [`crate::Client::prometheus_registry`].
+
+use prometheus::{
+    histogram_opts, opts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Registry,
+};
+
+pub(crate) struct PromMetrics {
+    pub(crate) registry: Registry,
+    pub(crate) requests: IntCounterVec,
+    pub(crate) latency: HistogramVec,
+    pub(crate) transport_restarts: IntCounter,
+    pub(crate) in_flight: IntGauge,
+}
+
+impl PromMetrics {
+    pub(crate) fn new() -> Self {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            opts!("mlld_requests_total", "Live protocol requests by outcome"),
+            &["method", "outcome"],
+        )
+        .expect("valid metric definition");
+
+        let latency = HistogramVec::new(
+            histogram_opts!(
+                "mlld_request_duration_seconds",
+                "Live protocol request latency"
+            ),
+            &["method"],
+        )
+        .expect("valid metric definition");
+
+        let transport_restarts = IntCounter::with_opts(opts!(
+            "mlld_transport_restarts_total",
+            "Times the live transport child was respawned after dying"
+        ))
+        .expect("valid metric definition");
+
+        let in_flight = IntGauge::with_opts(opts!(
+            "mlld_requests_in_flight",
+            "Requests sent to the live transport and not yet resolved"
+        ))
+        .expect("valid metric definition");
+
+        registry
+            .register(Box::new(requests.clone()))
+            .expect("register requests");
+        registry
+            .register(Box::new(latency.clone()))
+            .expect("register latency");
+        registry
+            .register(Box::new(transport_restarts.clone()))
+            .expect("register restarts");
+        registry
+            .register(Box::new(in_flight.clone()))
+            .expect("register in_flight");
+
+        Self {
+            registry,
+            requests,
+            latency,
+            transport_restarts,
+            in_flight,
+        }
+    }
+
+    pub(crate) fn observe_request(&self, method: &str, elapsed_secs: f64, ok: bool) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.requests.with_label_values(&[method, outcome]).inc();
+        self.latency
+            .with_label_values(&[method])
+            .observe(elapsed_secs);
+        self.in_flight.dec();
+    }
+}
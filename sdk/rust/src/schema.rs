@@ -0,0 +1,201 @@
+//! Minimal JSON Schema validation used to enforce export contracts.
+//!
+//! Supports the subset our module contracts actually use: `type`,
+//! `properties`, `required`, `items`, `enum`, `additionalProperties`
+//! (boolean form), `minimum`/`maximum`, and `minLength`/`maxLength`.
+//! Unknown keywords are ignored rather than rejected.
+
+use serde_json::Value;
+
+/// A single schema violation found while validating exports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    /// JSON path to the offending value, e.g. `exports.items[2].name`.
+    pub path: String,
+
+    /// Human-readable description of the violation.
+    pub message: String,
+}
+
+/// Validate `value` against `schema`, returning every violation found.
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    validate_at(schema, value, "exports", &mut violations);
+    violations
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, violations: &mut Vec<SchemaViolation>) {
+    let Value::Object(schema) = schema else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        if !type_matches(expected, value) {
+            violations.push(SchemaViolation {
+                path: path.to_string(),
+                message: format!("expected {expected}, got {}", type_name(value)),
+            });
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            violations.push(SchemaViolation {
+                path: path.to_string(),
+                message: format!("value {value} is not one of the allowed enum values"),
+            });
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < minimum {
+                violations.push(SchemaViolation {
+                    path: path.to_string(),
+                    message: format!("{number} is below minimum {minimum}"),
+                });
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > maximum {
+                violations.push(SchemaViolation {
+                    path: path.to_string(),
+                    message: format!("{number} is above maximum {maximum}"),
+                });
+            }
+        }
+    }
+
+    if let Some(text) = value.as_str() {
+        let length = text.chars().count();
+        if let Some(min_length) = schema.get("minLength").and_then(Value::as_u64) {
+            if (length as u64) < min_length {
+                violations.push(SchemaViolation {
+                    path: path.to_string(),
+                    message: format!("string length {length} is below minLength {min_length}"),
+                });
+            }
+        }
+        if let Some(max_length) = schema.get("maxLength").and_then(Value::as_u64) {
+            if (length as u64) > max_length {
+                violations.push(SchemaViolation {
+                    path: path.to_string(),
+                    message: format!("string length {length} is above maxLength {max_length}"),
+                });
+            }
+        }
+    }
+
+    if let Value::Object(object) = value {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    violations.push(SchemaViolation {
+                        path: format!("{path}.{name}"),
+                        message: "required property is missing".to_string(),
+                    });
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        if let Some(properties) = properties {
+            for (name, property_schema) in properties {
+                if let Some(property) = object.get(name) {
+                    validate_at(
+                        property_schema,
+                        property,
+                        &format!("{path}.{name}"),
+                        violations,
+                    );
+                }
+            }
+        }
+
+        if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+            for name in object.keys() {
+                let declared = properties.map(|p| p.contains_key(name)).unwrap_or(false);
+                if !declared {
+                    violations.push(SchemaViolation {
+                        path: format!("{path}.{name}"),
+                        message: "property is not allowed by the schema".to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Value::Array(items) = value {
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                validate_at(item_schema, item, &format!("{path}[{index}]"), violations);
+            }
+        }
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_reports_paths_for_nested_violations() {
+        let schema = json!({
+            "type": "object",
+            "required": ["items"],
+            "properties": {
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": { "type": "string", "minLength": 1 },
+                            "count": { "type": "integer", "minimum": 0 }
+                        }
+                    }
+                }
+            }
+        });
+
+        let valid = json!({ "items": [{ "name": "a", "count": 2 }] });
+        assert!(validate(&schema, &valid).is_empty());
+
+        let invalid = json!({ "items": [{ "count": -1 }] });
+        let violations = validate(&schema, &invalid);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].path, "exports.items[0].name");
+        assert_eq!(violations[1].path, "exports.items[0].count");
+
+        let missing = json!({});
+        let violations = validate(&schema, &missing);
+        assert_eq!(violations[0].path, "exports.items");
+    }
+}
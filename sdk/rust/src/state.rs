@@ -0,0 +1,387 @@
+//! Persistent state backends for the load-run-persist cycle.
+//!
+//! Scripts that keep durable state across runs all follow the same
+//! shape: load stored state, inject it as `@state` via
+//! [`ExecuteOptions::state`], run, then apply the run's
+//! [`state_writes`](crate::ExecuteResult::state_writes) back to
+//! storage. [`StateStore`] abstracts the storage half — dotted-path
+//! get/set/list over a JSON tree — with an in-memory implementation
+//! for tests and a JSON-file implementation for simple deployments.
+//! [`execute_with_store`] wires a store through one run end to end.
+//! [`StateSnapshot`] captures a tree at a point in time and diffs two
+//! captures into a typed changeset, so hosts can audit exactly what a
+//! run modified before persisting it.
+
+use crate::{Client, Error, ExecuteOptions, ExecuteResult, Result, StateWrite};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+
+/// Durable storage for a JSON state tree, addressed by dotted paths
+/// like the `state://` protocol (`agents.builder.status`).
+pub trait StateStore {
+    /// The value at `path`, or `None` when nothing is stored there.
+    fn get(&self, path: &str) -> Result<Option<Value>>;
+
+    /// Write `value` at `path`, creating intermediate objects along
+    /// the way and replacing any non-object encountered en route.
+    fn set(&mut self, path: &str, value: Value) -> Result<()>;
+
+    /// Dotted paths of every leaf in the tree, in traversal order.
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// The whole tree, as injected into [`ExecuteOptions::state`].
+    fn load(&self) -> Result<Value>;
+}
+
+/// A [`StateStore`] holding the tree in memory. State lives only as
+/// long as the store; useful for tests and single-process hosts.
+#[derive(Debug, Clone)]
+pub struct MemoryStateStore {
+    root: Value,
+}
+
+impl MemoryStateStore {
+    /// An empty store.
+    pub fn new() -> Self {
+        Self {
+            root: Value::Object(Map::new()),
+        }
+    }
+
+    /// A store seeded with an existing tree.
+    pub fn with_state(root: Value) -> Self {
+        Self { root }
+    }
+}
+
+impl Default for MemoryStateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn get(&self, path: &str) -> Result<Option<Value>> {
+        Ok(tree_get(&self.root, path).cloned())
+    }
+
+    fn set(&mut self, path: &str, value: Value) -> Result<()> {
+        tree_set(&mut self.root, path, value)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(leaf_paths(&self.root))
+    }
+
+    fn load(&self) -> Result<Value> {
+        Ok(self.root.clone())
+    }
+}
+
+/// A [`StateStore`] persisting the tree as a JSON file. Each operation
+/// reads or rewrites the whole file, which is fine for the small
+/// configuration-sized trees `@state` is meant for; a missing file
+/// reads as an empty tree.
+#[derive(Debug, Clone)]
+pub struct FileStateStore {
+    path: PathBuf,
+}
+
+impl FileStateStore {
+    /// A store backed by the JSON file at `path`. The file is created
+    /// on the first write.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn read(&self) -> Result<Value> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => Ok(serde_json::from_str(&text)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Value::Object(Map::new()))
+            }
+            Err(error) => Err(Error::Io(error)),
+        }
+    }
+
+    fn write(&self, root: &Value) -> Result<()> {
+        let text = serde_json::to_string_pretty(root)?;
+        std::fs::write(&self.path, text).map_err(Error::Io)
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn get(&self, path: &str) -> Result<Option<Value>> {
+        Ok(tree_get(&self.read()?, path).cloned())
+    }
+
+    fn set(&mut self, path: &str, value: Value) -> Result<()> {
+        let mut root = self.read()?;
+        tree_set(&mut root, path, value)?;
+        self.write(&root)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(leaf_paths(&self.read()?))
+    }
+
+    fn load(&self) -> Result<Value> {
+        self.read()
+    }
+}
+
+/// Run an mlld file with the store's tree injected as `@state` and the
+/// run's state writes persisted back into the store afterwards. Writes
+/// are applied in arrival order, so later writes to the same path win,
+/// and nothing is persisted when the run fails.
+pub fn execute_with_store<P: Serialize>(
+    client: &Client,
+    store: &mut dyn StateStore,
+    filepath: &str,
+    payload: Option<P>,
+    opts: Option<ExecuteOptions>,
+) -> Result<ExecuteResult> {
+    let mut opts = opts.unwrap_or_default();
+    opts.state = Some(store.load()?);
+
+    let result = client.execute(filepath, payload, Some(opts))?;
+    for write in &result.state_writes {
+        store.set(&write.path, write.value.clone())?;
+    }
+    Ok(result)
+}
+
+/// A point-in-time view of a state tree.
+///
+/// Build one from a store's current tree or from the
+/// [`state_writes`](crate::ExecuteResult::state_writes) of a run, then
+/// [`diff`](Self::diff) two captures to see what changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSnapshot {
+    root: Value,
+}
+
+impl StateSnapshot {
+    /// A snapshot of an existing tree.
+    pub fn new(root: Value) -> Self {
+        Self { root }
+    }
+
+    /// The tree a run's state writes build up, applied in arrival
+    /// order so later writes to the same path win.
+    pub fn from_writes(writes: &[StateWrite]) -> Self {
+        let mut root = Value::Object(Map::new());
+        for write in writes {
+            // Skip writes with empty paths rather than failing the
+            // whole snapshot; the server never emits them.
+            let _ = tree_set(&mut root, &write.path, write.value.clone());
+        }
+        Self { root }
+    }
+
+    /// The value at dotted `path`, or `None`.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        tree_get(&self.root, path)
+    }
+
+    /// Dotted paths of every leaf, in traversal order.
+    pub fn paths(&self) -> Vec<String> {
+        leaf_paths(&self.root)
+    }
+
+    /// The leaf-level changes going from this snapshot to `other`.
+    pub fn diff(&self, other: &StateSnapshot) -> StateDiff {
+        let mut diff = StateDiff::default();
+
+        for path in other.paths() {
+            let after = other.get(&path).cloned().unwrap_or(Value::Null);
+            match self.get(&path) {
+                None => diff.added.push((path, after)),
+                Some(before) if *before != after => {
+                    diff.changed.push((path, before.clone(), after));
+                }
+                Some(_) => {}
+            }
+        }
+        for path in self.paths() {
+            if other.get(&path).is_none() {
+                let before = self.get(&path).cloned().unwrap_or(Value::Null);
+                diff.removed.push((path, before));
+            }
+        }
+        diff
+    }
+}
+
+/// Leaf-level changes between two [`StateSnapshot`]s, in the old
+/// snapshot's traversal order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// Paths only the new snapshot has, with their values.
+    pub added: Vec<(String, Value)>,
+
+    /// Paths both snapshots have with differing values, as
+    /// `(path, before, after)`.
+    pub changed: Vec<(String, Value, Value)>,
+
+    /// Paths only the old snapshot has, with the values they held.
+    pub removed: Vec<(String, Value)>,
+}
+
+impl StateDiff {
+    /// Whether the snapshots are identical at the leaf level.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+fn tree_get<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = root;
+    for segment in path.split('.') {
+        current = current.as_object()?.get(segment)?;
+    }
+    Some(current)
+}
+
+fn tree_set(root: &mut Value, path: &str, value: Value) -> Result<()> {
+    if path.trim().is_empty() {
+        return Err(Error::Transport(
+            "state update path is required".to_string(),
+        ));
+    }
+
+    let mut current = root;
+    let segments: Vec<&str> = path.split('.').collect();
+    for segment in &segments[..segments.len() - 1] {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("just ensured an object")
+            .entry(segment.to_string())
+            .or_insert_with(|| json!({}));
+    }
+
+    if !current.is_object() {
+        *current = Value::Object(Map::new());
+    }
+    current
+        .as_object_mut()
+        .expect("just ensured an object")
+        .insert(segments[segments.len() - 1].to_string(), value);
+    Ok(())
+}
+
+fn leaf_paths(root: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_leaf_paths(root, String::new(), &mut paths);
+    paths
+}
+
+fn collect_leaf_paths(value: &Value, prefix: String, paths: &mut Vec<String>) {
+    match value.as_object() {
+        Some(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                collect_leaf_paths(child, path, paths);
+            }
+        }
+        _ => {
+            if !prefix.is_empty() {
+                paths.push(prefix);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store_gets_sets_and_lists_dotted_paths() {
+        let mut store = MemoryStateStore::new();
+        store
+            .set("agents.builder.status", json!("running"))
+            .unwrap();
+        store.set("agents.builder.phase", json!(2)).unwrap();
+        store.set("counter", json!(7)).unwrap();
+
+        assert_eq!(
+            store.get("agents.builder.status").unwrap(),
+            Some(json!("running"))
+        );
+        assert_eq!(store.get("agents.missing").unwrap(), None);
+        assert_eq!(
+            store.list().unwrap(),
+            vec!["agents.builder.phase", "agents.builder.status", "counter"]
+        );
+        assert!(store.set("", json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_from_writes_applies_later_writes_last() {
+        let writes = vec![
+            StateWrite {
+                path: "run.status".to_string(),
+                value: json!("starting"),
+                timestamp: None,
+            },
+            StateWrite {
+                path: "run.status".to_string(),
+                value: json!("done"),
+                timestamp: None,
+            },
+        ];
+
+        let snapshot = StateSnapshot::from_writes(&writes);
+        assert_eq!(snapshot.get("run.status"), Some(&json!("done")));
+        assert_eq!(snapshot.paths(), vec!["run.status"]);
+    }
+
+    #[test]
+    fn test_snapshot_diff_sorts_changes_into_added_changed_removed() {
+        let before = StateSnapshot::new(json!({
+            "keep": 1,
+            "tweak": "old",
+            "drop": true
+        }));
+        let after = StateSnapshot::new(json!({
+            "keep": 1,
+            "tweak": "new",
+            "fresh": [1, 2]
+        }));
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec![("fresh".to_string(), json!([1, 2]))]);
+        assert_eq!(
+            diff.changed,
+            vec![("tweak".to_string(), json!("old"), json!("new"))]
+        );
+        assert_eq!(diff.removed, vec![("drop".to_string(), json!(true))]);
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[test]
+    fn test_file_store_round_trips_through_disk() {
+        let path =
+            std::env::temp_dir().join(format!("mlld-state-store-test-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = FileStateStore::new(&path);
+        assert_eq!(store.load().unwrap(), json!({}));
+
+        store.set("run.count", json!(1)).unwrap();
+        let reopened = FileStateStore::new(&path);
+        assert_eq!(reopened.get("run.count").unwrap(), Some(json!(1)));
+        assert_eq!(reopened.list().unwrap(), vec!["run.count"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}